@@ -2,10 +2,29 @@ import discord
 import asyncio
 import typing
 from datetime import datetime, timedelta
+from sqlalchemy import text
 from hourai import utils
 from hourai.utils import fake, format
 from hourai.db import models, proto, escalation_history
 
+# The maximum number of pending actions claimed at once.
+CLAIM_BATCH_SIZE = 50
+# How long a claimed action is reserved before others can claim it again.
+CLAIM_LEASE = timedelta(minutes=5)
+
+# Must match the query used by the Rust logger: PendingAction::claim_expired.
+CLAIM_PENDING_ACTIONS = text("""
+    UPDATE pending_actions SET timestamp = :lease
+    WHERE id IN (
+        SELECT id FROM pending_actions
+        WHERE timestamp < now()
+        ORDER BY timestamp
+        LIMIT :limit
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING id
+""")
+
 
 def _get_reason(action: proto.Action) -> str:
     if action.HasField('reason'):
@@ -46,14 +65,24 @@ class ActionScheduler:
                                                  data=action))
             session.commit()
 
-    def query_pending_actions(self, session):
-        """Queries a SQLAlchemy session for of the currently unexecuted pending
-        actions.
+    def claim_pending_actions(self, session):
+        """Claims the currently unexecuted pending actions.
+
+        Claimed actions are leased by pushing their timestamp forward, so
+        neither other shards nor the Rust logger, which polls the same table,
+        will run them as well. Claimed actions must be deleted once executed.
+        If they are not, they will be claimed again once the lease expires.
         """
-        now = datetime.utcnow()
+        lease = datetime.utcnow() + CLAIM_LEASE
+        ids = [row.id for row in session.execute(
+            CLAIM_PENDING_ACTIONS,
+            {'lease': lease, 'limit': CLAIM_BATCH_SIZE})]
+        session.commit()
+        if len(ids) <= 0:
+            return []
         return session.query(models.PendingAction) \
-            .filter(models.PendingAction.timestamp < now) \
-            .order_by(models.PendingAction.timestamp) \
+            .filter(models.PendingAction.id.in_(ids)) \
+            .order_by(models.PendingAction.id) \
             .all()
 
 
@@ -74,7 +74,7 @@ class Admin(escalation.EscalationMixin, cogs.BaseCog):
         try:
             session = self.bot.create_storage_session()
             with session:
-                query = self.bot.action_manager.query_pending_actions(session)
+                query = self.bot.action_manager.claim_pending_actions(session)
                 for pending_action in query:
                     await self.bot.action_manager.execute(pending_action.data)
                     session.delete(pending_action)
@@ -3,14 +3,40 @@ import discord
 import logging
 import texttable
 import typing
-from datetime import datetime
+from datetime import datetime, timedelta
 from discord.ext import commands, tasks
-from hourai.db import escalation_history, models
+from sqlalchemy import text
+from hourai.db import escalation_history
 from hourai.utils import fake, checks, format
 
 
 log = logging.getLogger(__name__)
 
+# The maximum number of pending deescalations claimed at once.
+CLAIM_BATCH_SIZE = 50
+# How long a claimed deescalation is reserved before others can claim it again.
+CLAIM_LEASE = timedelta(minutes=5)
+
+# Must match the query used by the Rust logger:
+# PendingDeescalation::claim_expired.
+CLAIM_PENDING_DEESCALATIONS = text("""
+    UPDATE pending_deescalations SET expiration = :lease
+    WHERE (user_id, guild_id) IN (
+        SELECT user_id, guild_id FROM pending_deescalations
+        WHERE expiration < now()
+        ORDER BY expiration
+        LIMIT :limit
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING user_id, guild_id, amount, entry_id
+""")
+
+# Deletes a claimed deescalation, unless it has since been replaced.
+DELETE_CLAIMED_DEESCALATION = text("""
+    DELETE FROM pending_deescalations
+    WHERE guild_id = :guild_id AND user_id = :user_id AND entry_id = :entry_id
+""")
+
 
 def require_escalation_config(ctx):
     if ctx.guild is None:
@@ -37,29 +63,49 @@ class EscalationMixin:
         try:
             session = self.bot.create_storage_session()
             with session:
-                for deesc in self.__query_pending_deescalations(session):
-                    await self.__apply_pending_deescalation(session, deesc)
+                for deesc in self.__claim_pending_deescalations(session):
+                    try:
+                        await self.__apply_pending_deescalation(session,
+                                                                deesc)
+                    except Exception:
+                        # Left in place to be retried once the lease expires.
+                        session.rollback()
+                        log.exception('Error in running pending '
+                                      'deescalation:')
         except Exception:
             log.exception('Error in running pending deescalation:')
 
     async def __apply_pending_deescalation(self, session, deesc):
         guild = self.bot.get_guild(deesc.guild_id)
-        if guild is not None:
+        # Nothing left to deescalate if the ladder has since been removed.
+        if guild is not None and \
+           len(guild.config.moderation.escalation_ladder.rung) > 0:
             history = escalation_history.UserEscalationHistory(
                 bot=self.bot,
                 user=fake.FakeSnowflake(deesc.user_id),
                 guild=guild, session=session)
             await history.apply_diff(guild.me, 'Automatic Deescalation',
                                      deesc.amount, execute=False)
-        session.delete(deesc)
+        session.execute(DELETE_CLAIMED_DEESCALATION, {
+            'guild_id': deesc.guild_id,
+            'user_id': deesc.user_id,
+            'entry_id': deesc.entry_id,
+        })
         session.commit()
 
-    def __query_pending_deescalations(self, session):
-        now = datetime.utcnow()
-        return session.query(models.PendingDeescalation) \
-                      .filter(models.PendingDeescalation.expiration < now) \
-                      .order_by(models.PendingDeescalation.expiration) \
-                      .all()
+    def __claim_pending_deescalations(self, session):
+        """Claims the currently expired pending deescalations.
+
+        Claimed deescalations are leased by pushing their expiration forward,
+        so neither other shards nor the Rust logger, which polls the same
+        table, will apply them as well.
+        """
+        lease = datetime.utcnow() + CLAIM_LEASE
+        claimed = list(session.execute(
+            CLAIM_PENDING_DEESCALATIONS,
+            {'lease': lease, 'limit': CLAIM_BATCH_SIZE}))
+        session.commit()
+        return claimed
 
     @apply_pending_deescalations.before_loop
     async def before_apply_pending_deescalations(self):
@@ -0,0 +1,129 @@
+use crate::{escalation, Client};
+use anyhow::Result;
+use chrono::Utc;
+use hourai::actions::{self, ActionError, ActionResult};
+use hourai::proto::action::{Action, Action_oneof_details};
+use hourai_sql::actions::PendingAction;
+use std::{future::Future, pin::Pin, time::Duration};
+use tracing::{debug, error, warn};
+
+/// The maximum number of pending actions claimed in one polling iteration.
+const CLAIM_BATCH_SIZE: i64 = 50;
+/// How long a claimed action is reserved for this process before other processes can claim it.
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+/// The maximum number of attempts made at executing a pending action.
+const MAX_ATTEMPTS: i32 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+/// Executes an action. If the action has a duration and is successfully applied, the action
+/// undoing it will be scheduled to run after the duration expires.
+///
+/// Boxed as escalations may recursively execute other actions.
+pub fn execute(
+    client: &Client,
+    action: Action,
+) -> Pin<Box<dyn Future<Output = ActionResult> + Send + '_>> {
+    Box::pin(async move {
+        let result = match action.details {
+            Some(Action_oneof_details::escalate(_)) => {
+                escalation::execute_action(client, action).await
+            }
+            _ => client.actions.execute(action).await,
+        };
+        if result.is_success() && result.action.has_duration() {
+            if let Err(err) = schedule_undo(client, &result.action).await {
+                error!(
+                    "Failed to schedule undo for {} action: {}",
+                    actions::action_type(&result.action),
+                    err
+                );
+            }
+        }
+        result
+    })
+}
+
+/// Executes multiple actions sequentially. All of the actions will be executed even if earlier
+/// ones fail.
+pub async fn execute_all(
+    client: &Client,
+    actions: impl IntoIterator<Item = Action>,
+) -> Vec<ActionResult> {
+    let mut results = Vec::new();
+    for action in actions {
+        results.push(execute(client, action).await);
+    }
+    results
+}
+
+async fn schedule_undo(client: &Client, action: &Action) -> Result<()> {
+    if let Some(undo) = actions::invert_action(action) {
+        let duration = chrono::Duration::seconds(action.get_duration() as i64);
+        PendingAction::schedule(undo, Utc::now() + duration)
+            .execute(&client.sql)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Periodically polls for and executes expired pending actions.
+pub async fn run_pending_actions(client: Client, interval: Duration) {
+    loop {
+        if let Err(err) = run_expired_actions(&client).await {
+            error!("Error while running pending actions: {}", err);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn run_expired_actions(client: &Client) -> Result<()> {
+    let lease = Utc::now() + chrono::Duration::from_std(CLAIM_LEASE)?;
+    let pending = PendingAction::claim_expired(CLAIM_BATCH_SIZE, lease)
+        .fetch_all(&client.sql)
+        .await?;
+    if !pending.is_empty() {
+        debug!("Claimed {} pending actions", pending.len());
+    }
+
+    for pending_action in pending {
+        let result = execute(client, pending_action.action().clone()).await;
+        match result.outcome {
+            Err(ActionError::Failed(_)) if pending_action.attempts() + 1 < MAX_ATTEMPTS => {
+                let delay = retry_delay(pending_action.attempts());
+                warn!(
+                    "Pending {} action failed. Retrying in {} seconds.",
+                    actions::action_type(pending_action.action()),
+                    delay.as_secs()
+                );
+                pending_action
+                    .reschedule(Utc::now() + chrono::Duration::from_std(delay)?)
+                    .execute(&client.sql)
+                    .await?;
+            }
+            Err(ActionError::Unsupported(action_type)) => {
+                // Left for the Python bot, which can run every type of action.
+                debug!("Releasing unsupported pending {} action.", action_type);
+                pending_action.release().execute(&client.sql).await?;
+            }
+            Err(_) => {
+                error!(
+                    "Dropping pending {} action after {} attempts.",
+                    actions::action_type(pending_action.action()),
+                    pending_action.attempts() + 1
+                );
+                pending_action.delete().execute(&client.sql).await?;
+            }
+            Ok(_) => {
+                pending_action.delete().execute(&client.sql).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let multiplier = 1u32 << attempts.max(0).min(16) as u32;
+    std::cmp::min(BASE_RETRY_DELAY * multiplier, MAX_RETRY_DELAY)
+}
@@ -0,0 +1,313 @@
+use crate::{actions, modlog, Client};
+use anyhow::Result;
+use futures::future::join_all;
+use hourai::models::{id::*, MessageLike, UserLike};
+use hourai::proto::{
+    action::*, auto_config::*, guild_configs::VerificationConfig, util::FilterSettings,
+};
+use hourai_redis::{CachedGuild, GuildConfig};
+use regex::RegexSet;
+use tracing::error;
+
+/// Checks if a value passes a filter's compiled blacklist and whitelist. The blacklist applies
+/// first: if any entry matches, the value is rejected unless an entry in the whitelist also
+/// matches. If only a whitelist is provided, the value must match one of its entries.
+pub fn meets_filter(value: &str, blacklist: &RegexSet, whitelist: &RegexSet) -> bool {
+    if !blacklist.is_empty() {
+        !blacklist.is_match(value) || whitelist.is_match(value)
+    } else if !whitelist.is_empty() {
+        whitelist.is_match(value)
+    } else {
+        true
+    }
+}
+
+/// Runs the on_message events that apply to a newly created or edited message. Deletes the
+/// message if any of the matching events requests it, unless `deleted` shows it already has been.
+pub async fn on_message(
+    client: &Client,
+    message: &impl MessageLike,
+    event_type: MessageEvent_Type,
+    deleted: &mut bool,
+) -> Result<()> {
+    let guild_id = match message.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    if message.author().bot() || message.author().id() == client.user_id {
+        return Ok(());
+    }
+
+    let events = get_events(client, guild_id, |group| group.get_on_message()).await?;
+    let mut delete = false;
+    let mut tasks = Vec::new();
+    for (channel_id, event) in events {
+        let in_channel = channel_id.map_or(true, |id| id == message.channel_id());
+        let matches_type =
+            !event.has_field_type() || (event.get_field_type() as i32 & event_type as i32) != 0;
+        if !in_channel || !matches_type {
+            continue;
+        }
+        if event.has_content_filter()
+            && !check_filter(
+                client,
+                guild_id,
+                "on_message",
+                channel_id,
+                message.content(),
+                event.get_content_filter(),
+            )
+            .await
+        {
+            continue;
+        }
+        delete |= event.get_delete_message();
+        let actions = parameterize_actions(
+            event.get_action(),
+            guild_id,
+            message.author().id(),
+            channel_id,
+        );
+        tasks.push(run_actions(client, guild_id, "on_message", actions));
+    }
+    join_all(tasks).await;
+
+    if delete && !*deleted {
+        client
+            .http_client
+            .delete_message(message.channel_id(), message.id())
+            .await?;
+        *deleted = true;
+    }
+    Ok(())
+}
+
+/// Runs the on_join events for a member that has fully joined the guild.
+pub async fn on_member_join(
+    client: &Client,
+    guild_id: GuildId,
+    user: &impl UserLike,
+) -> Result<()> {
+    user_event(client, guild_id, user, "on_join", |group| {
+        group.get_on_join()
+    })
+    .await
+}
+
+pub async fn on_member_leave(
+    client: &Client,
+    guild_id: GuildId,
+    user: &impl UserLike,
+) -> Result<()> {
+    user_event(client, guild_id, user, "on_leave", |group| {
+        group.get_on_leave()
+    })
+    .await
+}
+
+pub async fn on_member_ban(client: &Client, guild_id: GuildId, user: &impl UserLike) -> Result<()> {
+    user_event(client, guild_id, user, "on_ban", |group| group.get_on_ban()).await
+}
+
+/// Runs the on_verify events if the guild's verification role was added to a member.
+pub async fn on_member_roles_update(
+    client: &Client,
+    guild_id: GuildId,
+    user: &impl UserLike,
+    before: &[RoleId],
+    after: &[RoleId],
+) -> Result<()> {
+    let mut redis = client.redis.clone();
+    let config = GuildConfig::fetch_or_default::<VerificationConfig>(guild_id, &mut redis).await?;
+    if !config.get_enabled() || !config.has_role_id() {
+        return Ok(());
+    }
+    let role_id = RoleId(config.get_role_id());
+    if !before.contains(&role_id) && after.contains(&role_id) {
+        user_event(client, guild_id, user, "on_verify", |group| {
+            group.get_on_verify()
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+async fn user_event(
+    client: &Client,
+    guild_id: GuildId,
+    user: &impl UserLike,
+    trigger: &'static str,
+    field: impl Fn(&EventGroup) -> &[UserChangeEvent],
+) -> Result<()> {
+    if user.bot() {
+        return Ok(());
+    }
+
+    let mut tasks = Vec::new();
+    for (channel_id, event) in get_events(client, guild_id, field).await? {
+        if event.has_username_filter()
+            && !check_filter(
+                client,
+                guild_id,
+                trigger,
+                channel_id,
+                user.name(),
+                event.get_username_filter(),
+            )
+            .await
+        {
+            continue;
+        }
+        let actions = parameterize_actions(event.get_action(), guild_id, user.id(), channel_id);
+        tasks.push(run_actions(client, guild_id, trigger, actions));
+    }
+    join_all(tasks).await;
+    Ok(())
+}
+
+/// Checks a value against an event's filter. An invalid filter does not match, so a
+/// misconfigured event is skipped without affecting the other events. It is reported to the
+/// modlog the first time it is seen.
+async fn check_filter(
+    client: &Client,
+    guild_id: GuildId,
+    trigger: &str,
+    channel_id: Option<ChannelId>,
+    value: &str,
+    filter: &FilterSettings,
+) -> bool {
+    let blacklist = client.regexes.get(guild_id, filter.get_blacklist());
+    let whitelist = client.regexes.get(guild_id, filter.get_whitelist());
+    let is_new = blacklist.is_new || whitelist.is_new;
+    match (blacklist.set, whitelist.set) {
+        (Ok(blacklist), Ok(whitelist)) => meets_filter(value, &blacklist, &whitelist),
+        (Err(err), _) | (_, Err(err)) => {
+            if !is_new {
+                return false;
+            }
+            let scope = match channel_id {
+                Some(channel_id) => format!("in <#{}>", channel_id),
+                None => "server-wide".to_owned(),
+            };
+            report_errors(
+                client,
+                guild_id,
+                vec![format!(
+                    ":x: Skipped automatic `{}` event ({}) with an invalid filter: {}",
+                    trigger, scope, err
+                )],
+            )
+            .await;
+            false
+        }
+    }
+}
+
+/// Collects all of the events of a given type configured for a guild. Guild-wide events are
+/// returned without a channel. Channel events may be keyed by either the channel's name or ID;
+/// events for channels that cannot be found are ignored.
+async fn get_events<T: Clone>(
+    client: &Client,
+    guild_id: GuildId,
+    field: impl Fn(&EventGroup) -> &[T],
+) -> Result<Vec<(Option<ChannelId>, T)>> {
+    let mut redis = client.redis.clone();
+    let config = GuildConfig::fetch_or_default::<AutoConfig>(guild_id, &mut redis).await?;
+    let mut events: Vec<_> = field(config.get_guild_events())
+        .iter()
+        .map(|evt| (None, evt.clone()))
+        .collect();
+    if config.get_channel_events().is_empty() {
+        return Ok(events);
+    }
+
+    let channels = CachedGuild::fetch_channels(guild_id, &mut redis).await?;
+    for (key, group) in config.get_channel_events() {
+        let channel = channels
+            .iter()
+            .find(|ch| ch.get_name() == key.as_str() || ch.get_channel_id().to_string() == *key);
+        if let Some(channel) = channel {
+            let channel_id = Some(ChannelId(channel.get_channel_id()));
+            events.extend(field(group).iter().map(|evt| (channel_id, evt.clone())));
+        }
+    }
+    Ok(events)
+}
+
+fn parameterize_actions(
+    actions: &[Action],
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: Option<ChannelId>,
+) -> Vec<Action> {
+    actions
+        .iter()
+        .map(|action| {
+            let mut action = action.clone();
+            action.set_guild_id(guild_id.0);
+            action.set_user_id(user_id.0);
+            if let Some(channel_id) = channel_id {
+                match action.details {
+                    Some(Action_oneof_details::send_message(ref mut details)) => {
+                        details.set_channel_id(channel_id.0)
+                    }
+                    Some(Action_oneof_details::command(ref mut details)) => {
+                        details.set_channel_id(channel_id.0)
+                    }
+                    _ => {}
+                }
+            }
+            action
+        })
+        .collect()
+}
+
+/// Runs the actions of a triggered event, reporting any failures to the modlog.
+async fn run_actions(client: &Client, guild_id: GuildId, trigger: &str, actions: Vec<Action>) {
+    let results = actions::execute_all(client, actions).await;
+    let errors: Vec<String> = results
+        .iter()
+        .filter_map(|result| match result.outcome {
+            Err(ref err) => Some(format!(
+                ":x: Failed to run automatic {} action for `{}` event: {}",
+                hourai::actions::action_type(&result.action),
+                trigger,
+                err
+            )),
+            Ok(_) => None,
+        })
+        .collect();
+    report_errors(client, guild_id, errors).await;
+}
+
+async fn report_errors(client: &Client, guild_id: GuildId, errors: Vec<String>) {
+    if errors.is_empty() {
+        return;
+    }
+    if let Err(err) = modlog::send(client, guild_id, errors.join("\n")).await {
+        error!(
+            "Error while reporting automatic event errors to the modlog: {}",
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn meets(value: &str, blacklist: &[&str], whitelist: &[&str]) -> bool {
+        let blacklist = RegexSet::new(blacklist).unwrap();
+        let whitelist = RegexSet::new(whitelist).unwrap();
+        meets_filter(value, &blacklist, &whitelist)
+    }
+
+    #[test]
+    fn test_meets_filter() {
+        assert!(meets("anything", &[], &[]));
+        assert!(!meets("bad word", &["bad"], &[]));
+        assert!(meets("bad word", &["bad"], &["word"]));
+        assert!(meets("good", &[], &["good"]));
+        assert!(!meets("other", &[], &["good"]));
+    }
+}
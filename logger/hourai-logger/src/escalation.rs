@@ -0,0 +1,421 @@
+use crate::{actions, modlog, Client};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use hourai::actions::{ActionError, ActionOutcome, ActionResult};
+use hourai::models::{id::*, UserLike};
+use hourai::proto::{action::*, escalation::*, guild_configs::ModerationConfig};
+use hourai_redis::GuildConfig;
+use hourai_sql::escalation::{EscalationEntry, PendingDeescalation};
+use std::time::Duration;
+use tracing::error;
+
+const DEESCALATION_BATCH_SIZE: i64 = 50;
+/// How long a claimed deescalation is reserved for this process before other processes can claim
+/// it.
+const DEESCALATION_LEASE: Duration = Duration::from_secs(300);
+
+/// The user responsible for an escalation.
+#[derive(Debug, Clone)]
+pub struct Authorizer {
+    pub id: UserId,
+    pub name: String,
+}
+
+impl Authorizer {
+    pub fn user(user: &impl UserLike) -> Self {
+        Self {
+            id: user.id(),
+            name: user.display_name(),
+        }
+    }
+
+    /// Creates an authorizer for the bot itself, for automated escalations.
+    pub async fn bot(client: &Client) -> Result<Self> {
+        let user = client.http_client.current_user().await?;
+        Ok(Self {
+            id: user.id,
+            name: format!("{}#{}", user.name, user.discriminator),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct EscalationResult {
+    /// The level of the user after the escalation was applied.
+    pub level: i64,
+    pub entry_id: i32,
+    pub display_name: String,
+    pub current_rung: Option<EscalationLadderRung>,
+    pub next_rung: Option<EscalationLadderRung>,
+    /// When the user will be automatically deescalated, if ever.
+    pub expiration: Option<DateTime<Utc>>,
+    /// The results of the actions run as a part of the escalation.
+    pub results: Vec<ActionResult>,
+}
+
+/// Gets the rung of the ladder for a given level. Levels past the end of the ladder use the
+/// last rung.
+pub fn get_rung(ladder: &EscalationLadder, level: i64) -> Option<&EscalationLadderRung> {
+    if level < 0 || ladder.get_rung().is_empty() {
+        return None;
+    }
+    let idx = std::cmp::min(level as usize, ladder.get_rung().len() - 1);
+    ladder.get_rung().get(idx)
+}
+
+/// The escalation history of a single user in a guild.
+pub struct EscalationHistory<'a> {
+    client: &'a Client,
+    guild_id: GuildId,
+    user_id: UserId,
+    entries: Vec<EscalationEntry>,
+}
+
+impl<'a> EscalationHistory<'a> {
+    pub async fn fetch(client: &'a Client, guild_id: GuildId, user_id: UserId) -> Result<Self> {
+        let entries = EscalationEntry::fetch_history(guild_id, user_id)
+            .fetch_all(&client.sql)
+            .await?;
+        Ok(Self {
+            client,
+            guild_id,
+            user_id,
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> &[EscalationEntry] {
+        &self.entries
+    }
+
+    /// The current escalation level of the user. Users who have never been escalated, or have
+    /// been fully deescalated, are at level -1.
+    pub fn current_level(&self) -> i64 {
+        EscalationEntry::compute_level(&self.entries)
+    }
+
+    /// Escalates the user and applies the corresponding rung from the escalation ladder.
+    pub async fn escalate(
+        &mut self,
+        authorizer: &Authorizer,
+        reason: &str,
+    ) -> Result<EscalationResult> {
+        self.apply_diff(authorizer, reason, 1, true).await
+    }
+
+    /// Deescalates the user. No actions are run when deescalating.
+    pub async fn deescalate(
+        &mut self,
+        authorizer: &Authorizer,
+        reason: &str,
+    ) -> Result<EscalationResult> {
+        self.apply_diff(authorizer, reason, -1, false).await
+    }
+
+    /// Changes the level of the user by `diff`. The level will not go below -1. If `execute` is
+    /// true, the actions of the resultant rung are run against the user.
+    pub async fn apply_diff(
+        &mut self,
+        authorizer: &Authorizer,
+        reason: &str,
+        diff: i64,
+        execute: bool,
+    ) -> Result<EscalationResult> {
+        let mut redis = self.client.redis.clone();
+        let config =
+            GuildConfig::fetch_or_default::<ModerationConfig>(self.guild_id, &mut redis).await?;
+        let ladder = config.get_escalation_ladder();
+        if reason.is_empty() {
+            bail!("A reason must be provided.");
+        } else if ladder.get_rung().is_empty() {
+            bail!("No escalation ladder has been configured.");
+        }
+
+        let level = std::cmp::max(-1, self.current_level() + diff);
+        let rung = get_rung(ladder, level).cloned();
+
+        let mut actions = ActionSet::new();
+        if execute && rung.is_some() {
+            for rung_action in rung.as_ref().unwrap().get_action() {
+                let mut action = rung_action.clone();
+                self.setup_action(&mut action, reason);
+                actions.mut_action().push(action);
+            }
+        } else {
+            let mut action = Action::new();
+            action.mut_escalate().set_amount(diff);
+            self.setup_action(&mut action, reason);
+            actions.mut_action().push(action);
+        }
+
+        let results = if execute {
+            actions::execute_all(self.client, actions.get_action().iter().cloned()).await
+        } else {
+            Vec::new()
+        };
+
+        let display_name = match rung {
+            Some(ref rung) if diff >= 0 => rung.get_display_name().to_owned(),
+            _ => "Deescalate".to_owned(),
+        };
+        let timestamp = Utc::now();
+        let (entry_id,) = EscalationEntry::insert(
+            self.guild_id,
+            self.user_id,
+            authorizer.id,
+            authorizer.name.clone(),
+            display_name.clone(),
+            timestamp,
+            actions,
+            diff as i32,
+        )
+        .fetch_one(&self.client.sql)
+        .await?;
+
+        let expiration = self
+            .schedule_deescalation(rung.as_ref(), entry_id, timestamp)
+            .await?;
+
+        self.entries = EscalationEntry::fetch_history(self.guild_id, self.user_id)
+            .fetch_all(&self.client.sql)
+            .await?;
+
+        let result = EscalationResult {
+            level,
+            entry_id,
+            display_name,
+            current_rung: rung,
+            next_rung: get_rung(ladder, level + 1).cloned(),
+            expiration,
+            results,
+        };
+
+        if let Err(err) = self.log_result(authorizer, reason, diff, &result).await {
+            error!("Error while logging escalation to the modlog: {}", err);
+        }
+
+        Ok(result)
+    }
+
+    async fn schedule_deescalation(
+        &self,
+        rung: Option<&EscalationLadderRung>,
+        entry_id: i32,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        match rung {
+            Some(rung) if rung.has_deescalation_period() => {
+                let period = chrono::Duration::seconds(rung.get_deescalation_period() as i64);
+                let expiration = timestamp + period;
+                PendingDeescalation::schedule(
+                    self.guild_id,
+                    self.user_id,
+                    expiration,
+                    -1,
+                    entry_id,
+                )
+                .execute(&self.client.sql)
+                .await?;
+                Ok(Some(expiration))
+            }
+            _ => {
+                // Remove any pending deescalation if there doesn't need to be one.
+                PendingDeescalation::delete(self.guild_id, self.user_id)
+                    .execute(&self.client.sql)
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn log_result(
+        &self,
+        authorizer: &Authorizer,
+        reason: &str,
+        diff: i64,
+        result: &EscalationResult,
+    ) -> Result<()> {
+        let expiration = result
+            .expiration
+            .map(|exp| exp.to_rfc2822())
+            .unwrap_or_else(|| "Never".to_owned());
+        let mut message = format!(
+            "{}**<@{}> {} <@{}>**\nReason: {}\nAction: {}\nExpiration: {}",
+            if diff > 0 {
+                ":arrow_up:"
+            } else {
+                ":arrow_down:"
+            },
+            authorizer.id,
+            if diff > 0 { "escalated" } else { "deescalated" },
+            self.user_id,
+            reason,
+            result.display_name,
+            expiration
+        );
+        for failed in result.results.iter().filter(|r| !r.is_success()) {
+            if let Err(ref err) = failed.outcome {
+                message.push_str(&format!(
+                    "\n:x: Failed to run {} action: {}",
+                    hourai::actions::action_type(&failed.action),
+                    err
+                ));
+            }
+        }
+        modlog::send(self.client, self.guild_id, message).await
+    }
+
+    fn setup_action(&self, action: &mut Action, reason: &str) {
+        action.set_guild_id(self.guild_id.0);
+        action.set_user_id(self.user_id.0);
+        action.set_reason(reason.to_owned());
+    }
+}
+
+/// Executes an escalate action. Positive amounts escalate the user and run the corresponding
+/// rung. Negative amounts deescalate the user.
+pub async fn execute_action(client: &Client, action: Action) -> ActionResult {
+    let outcome = execute_action_inner(client, &action).await;
+    ActionResult { action, outcome }
+}
+
+async fn execute_action_inner(
+    client: &Client,
+    action: &Action,
+) -> std::result::Result<ActionOutcome, ActionError> {
+    if !action.has_guild_id() {
+        return Err(ActionError::MissingField("guild_id"));
+    } else if !action.has_user_id() {
+        return Err(ActionError::MissingField("user_id"));
+    }
+
+    let amount = action.get_escalate().get_amount();
+    if amount == 0 {
+        return Ok(ActionOutcome::Skipped("Escalation amount was zero."));
+    }
+
+    let reason = if action.has_reason() {
+        action.get_reason()
+    } else {
+        "Automated escalation"
+    };
+    let authorizer = Authorizer::bot(client).await?;
+    let mut history = EscalationHistory::fetch(
+        client,
+        GuildId(action.get_guild_id()),
+        UserId(action.get_user_id()),
+    )
+    .await?;
+    history
+        .apply_diff(&authorizer, reason, amount, amount > 0)
+        .await?;
+    Ok(ActionOutcome::Applied)
+}
+
+/// Periodically polls for and applies expired deescalations.
+pub async fn run_pending_deescalations(client: Client, interval: Duration) {
+    loop {
+        if let Err(err) = run_expired_deescalations(&client).await {
+            error!("Error while running pending deescalations: {}", err);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn run_expired_deescalations(client: &Client) -> Result<()> {
+    let lease = Utc::now() + chrono::Duration::from_std(DEESCALATION_LEASE)?;
+    let pending = PendingDeescalation::claim_expired(DEESCALATION_BATCH_SIZE, lease)
+        .fetch_all(&client.sql)
+        .await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let authorizer = Authorizer::bot(client).await?;
+    let mut redis = client.redis.clone();
+    for deescalation in pending {
+        let config =
+            GuildConfig::fetch_or_default::<ModerationConfig>(deescalation.guild_id(), &mut redis)
+                .await?;
+        if config.get_escalation_ladder().get_rung().is_empty() {
+            // The ladder has since been removed. There is nothing left to deescalate.
+            deescalation.delete_claimed().execute(&client.sql).await?;
+            continue;
+        }
+
+        let result = async {
+            let mut history =
+                EscalationHistory::fetch(client, deescalation.guild_id(), deescalation.user_id())
+                    .await?;
+            history
+                .apply_diff(
+                    &authorizer,
+                    "Automatic Deescalation",
+                    deescalation.amount,
+                    false,
+                )
+                .await
+        };
+        match result.await {
+            Ok(_) => {
+                deescalation.delete_claimed().execute(&client.sql).await?;
+            }
+            // Left in place to be retried once the lease expires.
+            Err(err) => error!(
+                "Error while deescalating user {} in guild {}: {}",
+                deescalation.user_id(),
+                deescalation.guild_id(),
+                err
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ladder(rungs: &[&str]) -> EscalationLadder {
+        let mut ladder = EscalationLadder::new();
+        for name in rungs {
+            let mut rung = EscalationLadderRung::new();
+            rung.set_display_name(name.to_string());
+            ladder.mut_rung().push(rung);
+        }
+        ladder
+    }
+
+    fn rung_name(ladder: &EscalationLadder, level: i64) -> Option<&str> {
+        get_rung(ladder, level).map(|rung| rung.get_display_name())
+    }
+
+    #[test]
+    fn test_get_rung() {
+        let ladder = ladder(&["Warn", "Mute", "Ban"]);
+        assert_eq!(rung_name(&ladder, 0), Some("Warn"));
+        assert_eq!(rung_name(&ladder, 1), Some("Mute"));
+        assert_eq!(rung_name(&ladder, 2), Some("Ban"));
+    }
+
+    #[test]
+    fn test_get_rung_past_end_uses_last_rung() {
+        let ladder = ladder(&["Warn", "Mute", "Ban"]);
+        assert_eq!(rung_name(&ladder, 3), Some("Ban"));
+        assert_eq!(rung_name(&ladder, 100), Some("Ban"));
+    }
+
+    #[test]
+    fn test_get_rung_below_zero() {
+        let ladder = ladder(&["Warn", "Mute", "Ban"]);
+        assert_eq!(rung_name(&ladder, -1), None);
+        assert_eq!(rung_name(&ladder, -5), None);
+    }
+
+    #[test]
+    fn test_get_rung_empty_ladder() {
+        let ladder = ladder(&[]);
+        assert_eq!(rung_name(&ladder, 0), None);
+        assert_eq!(rung_name(&ladder, 5), None);
+    }
+}
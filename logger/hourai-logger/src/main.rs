@@ -1,12 +1,24 @@
+#[macro_use]
+extern crate lazy_static;
+
+mod actions;
 mod announcements;
+mod auto;
+mod escalation;
 mod listings;
+mod member_logging;
+mod message_filter;
 mod message_logging;
+mod moderation;
+mod modlog;
+mod regex_cache;
 mod roles;
 
 use anyhow::Result;
 use core::time::Duration;
 use futures::stream::StreamExt;
 use hourai::{
+    actions::ActionExecutor,
     cache::{InMemoryCache, ResourceType},
     config,
     gateway::{cluster::*, Event, EventType, EventTypeFlags, Intents},
@@ -18,6 +30,7 @@ use hourai::{
         id::*,
         user::User,
     },
+    proto::auto_config::MessageEvent_Type,
 };
 use hourai_redis::*;
 use hourai_sql::*;
@@ -83,6 +96,9 @@ async fn main() {
             .expect("User should not fail to load.");
         Client {
             user_id: user.id,
+            actions: ActionExecutor::new(http_client.clone()),
+            message_filter: message_filter::MessageFilter::new(&config),
+            regexes: regex_cache::RegexCache::default(),
             http_client,
             gateway: gateway.clone(),
             cache: cache.clone(),
@@ -104,6 +120,14 @@ async fn main() {
     // Setup background tasks
     tokio::spawn(client.clone().log_bans());
     tokio::spawn(flush_online(cache.clone(), redis.clone()));
+    tokio::spawn(actions::run_pending_actions(
+        client.clone(),
+        Duration::from_secs(1),
+    ));
+    tokio::spawn(escalation::run_pending_deescalations(
+        client.clone(),
+        Duration::from_secs(1),
+    ));
 
     let mut events = gateway.some_events(BOT_EVENTS);
     while let Some((shard_id, evt)) = events.next().await {
@@ -146,6 +170,9 @@ async fn flush_online(cache: InMemoryCache, mut redis: RedisPool) {
 pub struct Client {
     pub user_id: UserId,
     pub http_client: hourai::http::Client,
+    pub actions: ActionExecutor,
+    pub message_filter: message_filter::MessageFilter,
+    pub regexes: regex_cache::RegexCache,
     pub gateway: Cluster,
     pub cache: InMemoryCache,
     pub sql: SqlPool,
@@ -265,7 +292,10 @@ impl Client {
                     Ok(())
                 }
             }
-            Event::MemberAdd(evt) => self.on_member_add(evt.0).await,
+            Event::MemberAdd(evt) => {
+                tokio::spawn(member_logging::on_member_join(self.clone(), evt.0.clone()));
+                self.on_member_add(evt.0).await
+            }
             Event::MemberChunk(evt) => self.on_member_chunk(evt).await,
             Event::MemberRemove(evt) => self.on_member_remove(evt).await,
             Event::MemberUpdate(evt) => self.on_member_update(*evt).await,
@@ -316,29 +346,44 @@ impl Client {
     }
 
     async fn on_ban_add(self, evt: BanAdd) -> Result<()> {
-        let (res1, res2) = futures::join!(
+        let (res1, res2, res3) = futures::join!(
             self.log_users(vec![evt.user.clone()]),
-            announcements::on_member_ban(&self, evt.clone())
+            announcements::on_member_ban(&self, evt.clone()),
+            auto::on_member_ban(&self, evt.guild_id, &evt.user)
         );
 
         let perms = self
             .fetch_guild_permissions(evt.guild_id, self.user_id)
             .await?;
+        let mut reason = None;
         if perms.contains(Permissions::BAN_MEMBERS) {
             if let Some(ban) = self.http_client.ban(evt.guild_id, evt.user.id).await? {
+                reason = ban.reason.clone();
                 Ban::from(evt.guild_id, ban)
                     .insert()
                     .execute(&self.sql)
                     .await?;
             }
         }
+        tokio::spawn(member_logging::on_ban(
+            self.clone(),
+            evt.guild_id,
+            evt.user,
+            reason,
+        ));
 
         res1?;
         res2?;
+        res3?;
         Ok(())
     }
 
     async fn on_ban_remove(self, evt: BanRemove) -> Result<()> {
+        tokio::spawn(member_logging::on_unban(
+            self.clone(),
+            evt.guild_id,
+            evt.user.clone(),
+        ));
         let (res1, res2) = futures::join!(
             self.log_users(vec![evt.user.clone()]),
             Ban::clear_ban(evt.guild_id, evt.user.id).execute(&self.sql)
@@ -351,10 +396,14 @@ impl Client {
 
     async fn on_member_add(&self, member: Member) -> Result<()> {
         if !member.pending {
-            let res = roles::on_member_join(&self, &member).await;
+            let (res1, res2) = futures::join!(
+                roles::on_member_join(&self, &member),
+                auto::on_member_join(&self, member.guild_id, &member.user)
+            );
             let members = vec![member.clone()];
             self.log_members(&members).await?;
-            res?;
+            res1?;
+            res2?;
         }
         announcements::on_member_join(&self, member.guild_id, member.user).await?;
         Ok(())
@@ -370,23 +419,66 @@ impl Client {
             return Ok(());
         }
 
+        let before = hourai_sql::Member::fetch(evt.guild_id, evt.user.id)
+            .fetch_optional(&self.sql)
+            .await?;
+        let before_roles: Vec<RoleId> = match before {
+            Some(ref member) => member.role_ids().collect(),
+            None => evt.roles.clone(),
+        };
+        let res = auto::on_member_roles_update(
+            &self,
+            evt.guild_id,
+            &evt.user,
+            &before_roles,
+            &evt.roles,
+        )
+        .await;
+        if let Some(before) = before {
+            tokio::spawn(member_logging::on_member_update(
+                self.clone(),
+                evt.clone(),
+                before,
+            ));
+        }
+
         hourai_sql::Member::from(&evt)
             .insert()
             .execute(&self.sql)
             .await?;
-        Username::new(&evt.user).insert().execute(&self.sql).await?;
+
+        // Member updates for a username change arrive once per guild the user is in. Only the
+        // first one to record the new name logs the change.
+        let previous = Username::fetch_latest(evt.user.id)
+            .fetch_optional(&self.sql)
+            .await?;
+        let recorded = Username::new(&evt.user)
+            .insert_latest()
+            .fetch_optional(&self.sql)
+            .await?;
+        if let (Some(previous), Some(_)) = (previous, recorded) {
+            tokio::spawn(member_logging::on_username_change(
+                self.clone(),
+                evt.user.clone(),
+                previous,
+            ));
+        }
+        res?;
         Ok(())
     }
 
     async fn on_member_remove(&self, evt: MemberRemove) -> Result<()> {
-        let (res1, res2, res3) = futures::join!(
+        tokio::spawn(member_logging::on_member_leave(self.clone(), evt.clone()));
+        let (res1, res2, res3, res4) = futures::join!(
             hourai_sql::Member::set_present(evt.guild_id, evt.user.id, false).execute(&self.sql),
             self.log_users(vec![evt.user.clone()]),
-            announcements::on_member_leave(&self, evt)
+            auto::on_member_leave(&self, evt.guild_id, &evt.user),
+            announcements::on_member_leave(&self, evt.clone())
         );
         res1?;
         res2?;
         res3?;
+        res4?;
         Ok(())
     }
 
@@ -424,43 +516,98 @@ impl Client {
     }
 
     async fn on_message_create(mut self, evt: Message) -> Result<()> {
+        let embed_urls = evt
+            .embeds
+            .iter()
+            .filter_map(|embed| embed.url.clone())
+            .chain(evt.attachments.iter().map(|a| a.url.clone()))
+            .collect();
+        // Run sequentially so that the message is only deleted once.
+        let mut deleted = false;
+        let res1 = message_filter::check_message(&self, &evt, Some(embed_urls), &mut deleted).await;
+        let res2 = auto::on_message(
+            &self,
+            &evt,
+            MessageEvent_Type::MESSAGE_CREATES,
+            &mut deleted,
+        )
+        .await;
         if !evt.author.bot {
+            if !evt.attachments.is_empty() {
+                tokio::spawn(message_logging::cache_attachments(
+                    self.clone(),
+                    evt.clone(),
+                ));
+            }
             CachedMessage::new(evt)
                 .flush()
                 .query_async(&mut self.redis)
                 .await?;
         }
+        res1?;
+        res2?;
         Ok(())
     }
 
     async fn on_message_update(mut self, evt: MessageUpdate) -> Result<()> {
         // TODO(james7132): Properly implement this
         let cached = CachedMessage::fetch(evt.channel_id, evt.id, &mut self.redis).await?;
-        if let Some(mut msg) = cached {
-            if let Some(content) = evt.content {
-                let before = msg.clone();
-                msg.set_content(content);
-                tokio::spawn(message_logging::on_message_update(
-                    self.clone(),
-                    before.clone(),
-                    msg,
-                ));
-                CachedMessage::new(before)
-                    .flush()
-                    .query_async(&mut self.redis)
-                    .await?;
-            }
+        let mut msg = match cached {
+            Some(msg) => msg,
+            None => return Ok(()),
+        };
+
+        // Link previews are added to messages with updates that do not change the content.
+        let before = msg.clone();
+        if let Some(ref embeds) = evt.embeds {
+            msg.set_embeds(embeds.iter().map(Into::into).collect());
+        }
+        if let Some(ref attachments) = evt.attachments {
+            msg.set_attachments(attachments.iter().map(Into::into).collect());
         }
 
-        Ok(())
+        let mut result = Ok(());
+        if let Some(content) = evt.content {
+            msg.set_content(content);
+            let embed_urls = match (evt.embeds, evt.attachments) {
+                (Some(embeds), Some(attachments)) => Some(
+                    embeds
+                        .into_iter()
+                        .filter_map(|embed| embed.url)
+                        .chain(attachments.into_iter().map(|a| a.url))
+                        .collect(),
+                ),
+                _ => None,
+            };
+            let mut deleted = false;
+            let res1 = message_filter::check_message(&self, &msg, embed_urls, &mut deleted).await;
+            let res2 =
+                auto::on_message(&self, &msg, MessageEvent_Type::MESSAGE_EDITS, &mut deleted).await;
+            tokio::spawn(message_logging::on_message_update(
+                self.clone(),
+                before,
+                msg.clone(),
+            ));
+            result = res1.and(res2);
+        }
+
+        CachedMessage::new(msg)
+            .flush()
+            .query_async(&mut self.redis)
+            .await?;
+        result
     }
 
     async fn on_message_delete(mut self, evt: MessageDelete) -> Result<()> {
-        message_logging::on_message_delete(&mut self, &evt).await?;
+        // The cached message is removed even if logging its deletion fails.
+        let result = message_logging::on_message_delete(&mut self, &evt).await;
         CachedMessage::delete(evt.channel_id, evt.id)
             .query_async(&mut self.redis)
             .await?;
-        Ok(())
+        CachedAttachment::delete(evt.channel_id, evt.id)
+            .query_async(&mut self.redis)
+            .await?;
+        result
     }
 
     async fn on_message_bulk_delete(mut self, evt: MessageDeleteBulk) -> Result<()> {
@@ -468,6 +615,9 @@ impl Client {
             self.clone(),
             evt.clone(),
         ));
+        CachedAttachment::bulk_delete(evt.channel_id, evt.ids.iter().cloned())
+            .query_async(&mut self.redis)
+            .await?;
         CachedMessage::bulk_delete(evt.channel_id, evt.ids)
             .query_async(&mut self.redis)
             .await?;
@@ -0,0 +1,301 @@
+use crate::Client;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use hourai::models::gateway::payload::{MemberRemove, MemberUpdate};
+use hourai::models::guild::member::Member;
+use hourai::models::id::*;
+use hourai::models::user::User;
+use hourai::models::{Snowflake, UserLike};
+use hourai::proto::guild_configs::*;
+use hourai_redis::GuildConfig;
+use hourai_sql::Username;
+use tracing::error;
+use twilight_embed_builder::*;
+
+fn user_embed(user: &impl UserLike) -> Result<EmbedBuilder> {
+    Ok(EmbedBuilder::new()
+        .footer(
+            EmbedFooterBuilder::new(format!("{} ({})", user.display_name(), user.id()))?
+                .icon_url(ImageSource::url(user.avatar_url())?),
+        )
+        .timestamp(Utc::now().to_rfc3339()))
+}
+
+fn account_age_field(user: &impl UserLike) -> Result<EmbedFieldBuilder> {
+    let created_at = user.created_at();
+    Ok(EmbedFieldBuilder::new(
+        "Account Created",
+        format!(
+            "{} ({} ago)",
+            created_at.format("%Y-%m-%d %H:%M UTC"),
+            format_age(Utc::now() - created_at)
+        ),
+    )?)
+}
+
+fn get_output_channel(
+    config: &LoggingConfig,
+    type_config: &MemberLoggingConfig,
+) -> Option<ChannelId> {
+    if !type_config.get_enabled() {
+        return None;
+    }
+    let id = if config.has_modlog_channel_id() {
+        config.get_modlog_channel_id()
+    } else if type_config.has_output_channel_id() {
+        type_config.get_output_channel_id()
+    } else {
+        return None;
+    };
+    Some(ChannelId(id))
+}
+
+/// Sends a log message if the given type of member event is logged in the guild.
+async fn log(
+    client: &Client,
+    guild_id: GuildId,
+    type_config: fn(&LoggingConfig) -> &MemberLoggingConfig,
+    content: String,
+    embed: EmbedBuilder,
+) -> Result<()> {
+    let mut redis = client.redis.clone();
+    let config = GuildConfig::fetch_or_default::<LoggingConfig>(guild_id, &mut redis).await?;
+    if let Some(channel_id) = get_output_channel(&config, type_config(&config)) {
+        client
+            .http_client
+            .create_message(channel_id)
+            .content(content)?
+            .embed(embed.build()?)?
+            .await?;
+    }
+    Ok(())
+}
+
+pub(super) async fn on_member_join(client: Client, member: Member) -> Result<()> {
+    let embed = user_embed(&member.user)?
+        .field(account_age_field(&member.user)?)
+        .color(0x1f8b4c)?; // Dark green
+    log(
+        &client,
+        member.guild_id,
+        LoggingConfig::get_member_joins,
+        format!("<@{}> joined the server.", member.user.id),
+        embed,
+    )
+    .await
+}
+
+pub(super) async fn on_member_leave(client: Client, evt: MemberRemove) -> Result<()> {
+    let member = hourai_sql::Member::fetch(evt.guild_id, evt.user.id)
+        .fetch_optional(&client.sql)
+        .await?;
+    let mut embed = user_embed(&evt.user)?.field(account_age_field(&evt.user)?);
+    if let Some(joined_at) = member.and_then(|m| m.joined_at) {
+        embed = embed.field(EmbedFieldBuilder::new(
+            "Time in Server",
+            format_age(Utc::now() - joined_at),
+        )?);
+    }
+    log(
+        &client,
+        evt.guild_id,
+        LoggingConfig::get_member_leaves,
+        format!("**{}** left the server.", evt.user.display_name()),
+        embed.color(0x546e7a)?, // Dark grey
+    )
+    .await
+}
+
+/// Logs nickname and role changes, given the member as they were saved before the update.
+pub(super) async fn on_member_update(
+    client: Client,
+    evt: MemberUpdate,
+    before: hourai_sql::Member,
+) -> Result<()> {
+    if before.nickname != evt.nick {
+        let embed = user_embed(&evt.user)?
+            .field(EmbedFieldBuilder::new(
+                "Before",
+                before.nickname.as_deref().unwrap_or("(None)"),
+            )?)
+            .field(EmbedFieldBuilder::new(
+                "After",
+                evt.nick.as_deref().unwrap_or("(None)"),
+            )?)
+            .color(0x206694)?; // Dark blue
+        log(
+            &client,
+            evt.guild_id,
+            LoggingConfig::get_nickname_changes,
+            format!("<@{}> changed their nickname.", evt.user.id),
+            embed,
+        )
+        .await?;
+    }
+
+    let before_roles: Vec<RoleId> = before.role_ids().collect();
+    let (added, removed) = diff_roles(&before_roles, &evt.roles);
+    if !added.is_empty() || !removed.is_empty() {
+        let mut embed = user_embed(&evt.user)?;
+        if !added.is_empty() {
+            embed = embed.field(EmbedFieldBuilder::new("Added", mention_roles(&added))?);
+        }
+        if !removed.is_empty() {
+            embed = embed.field(EmbedFieldBuilder::new("Removed", mention_roles(&removed))?);
+        }
+        log(
+            &client,
+            evt.guild_id,
+            LoggingConfig::get_role_changes,
+            format!("Roles of <@{}> changed.", evt.user.id),
+            embed.color(0x71368a)?, // Dark purple
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Logs a username change to every guild the user is in.
+pub(super) async fn on_username_change(client: Client, user: User, before: Username) -> Result<()> {
+    let before_name = match before.discriminator {
+        Some(discriminator) => format!("{}#{:04}", before.name, discriminator),
+        None => before.name.clone(),
+    };
+    let guild_ids = hourai_sql::Member::fetch_user_guild_ids(user.id)
+        .fetch_all(&client.sql)
+        .await?;
+    for (guild_id,) in guild_ids {
+        let embed = user_embed(&user)?
+            .field(EmbedFieldBuilder::new("Before", before_name.clone())?)
+            .field(EmbedFieldBuilder::new("After", user.display_name())?)
+            .color(0x206694)?; // Dark blue
+        let result = log(
+            &client,
+            GuildId(guild_id as u64),
+            LoggingConfig::get_username_changes,
+            format!("<@{}> changed their username.", user.id),
+            embed,
+        )
+        .await;
+        if let Err(err) = result {
+            error!(
+                "Error while logging username change in guild {}: {}",
+                guild_id, err
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(super) async fn on_ban(
+    client: Client,
+    guild_id: GuildId,
+    user: User,
+    reason: Option<String>,
+) -> Result<()> {
+    let embed = user_embed(&user)?
+        .field(EmbedFieldBuilder::new(
+            "Reason",
+            reason.as_deref().unwrap_or("No reason provided."),
+        )?)
+        .color(0x992d22)?; // Dark red
+    log(
+        &client,
+        guild_id,
+        LoggingConfig::get_bans,
+        format!("**{}** was banned.", user.display_name()),
+        embed,
+    )
+    .await
+}
+
+pub(super) async fn on_unban(client: Client, guild_id: GuildId, user: User) -> Result<()> {
+    let embed = user_embed(&user)?.color(0x1f8b4c)?; // Dark green
+    log(
+        &client,
+        guild_id,
+        LoggingConfig::get_bans,
+        format!("**{}** was unbanned.", user.display_name()),
+        embed,
+    )
+    .await
+}
+
+fn mention_roles(roles: &[RoleId]) -> String {
+    roles
+        .iter()
+        .map(|id| format!("<@&{}>", id))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Diffs a member's roles. Returns the roles that were added and the roles that were removed.
+fn diff_roles(before: &[RoleId], after: &[RoleId]) -> (Vec<RoleId>, Vec<RoleId>) {
+    let added = after
+        .iter()
+        .filter(|id| !before.contains(id))
+        .cloned()
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|id| !after.contains(id))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// Formats a length of time as its two largest units, like `2 years, 15 days`.
+fn format_age(age: Duration) -> String {
+    fn unit(count: i64, name: &str) -> String {
+        if count == 1 {
+            format!("{} {}", count, name)
+        } else {
+            format!("{} {}s", count, name)
+        }
+    }
+
+    let days = age.num_days();
+    if days >= 365 {
+        format!("{}, {}", unit(days / 365, "year"), unit(days % 365, "day"))
+    } else if days >= 1 {
+        format!(
+            "{}, {}",
+            unit(days, "day"),
+            unit(age.num_hours() % 24, "hour")
+        )
+    } else if age.num_hours() >= 1 {
+        format!(
+            "{}, {}",
+            unit(age.num_hours(), "hour"),
+            unit(age.num_minutes() % 60, "minute")
+        )
+    } else {
+        unit(std::cmp::max(age.num_minutes(), 0), "minute")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff_roles() {
+        let before = vec![RoleId(1), RoleId(2), RoleId(3)];
+        let after = vec![RoleId(2), RoleId(3), RoleId(4)];
+        assert_eq!(
+            diff_roles(&before, &after),
+            (vec![RoleId(4)], vec![RoleId(1)])
+        );
+        assert_eq!(diff_roles(&before, &before), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::seconds(30)), "0 minutes");
+        assert_eq!(format_age(Duration::minutes(1)), "1 minute");
+        assert_eq!(format_age(Duration::minutes(61)), "1 hour, 1 minute");
+        assert_eq!(format_age(Duration::hours(50)), "2 days, 2 hours");
+        assert_eq!(format_age(Duration::days(365 * 2 + 15)), "2 years, 15 days");
+        assert_eq!(format_age(Duration::seconds(-5)), "0 minutes");
+    }
+}
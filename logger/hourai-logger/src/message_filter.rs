@@ -0,0 +1,365 @@
+use crate::{actions, message_logging, moderation, modlog, Client};
+use anyhow::Result;
+use hourai::config::{self, HouraiConfig};
+use hourai::models::{
+    guild::{Guild, Permissions},
+    id::*,
+    MessageLike, UserLike,
+};
+use hourai::proto::guild_configs::*;
+use hourai_redis::{CachedGuild, GuildConfig};
+use regex::{Regex, RegexSet};
+use std::collections::HashSet;
+use tracing::{error, info};
+
+lazy_static! {
+    static ref INVITE_REGEX: Regex =
+        Regex::new(r"discord(?:\.gg|(?:app)?\.com/invite)/([a-zA-Z0-9\-]+)").unwrap();
+    static ref USER_MENTION_REGEX: Regex = Regex::new(r"<@!?(\d+)>").unwrap();
+    static ref ROLE_MENTION_REGEX: Regex = Regex::new(r"<@&(\d+)>").unwrap();
+}
+
+/// Globally shared state for evaluating message filter rules.
+#[derive(Clone)]
+pub struct MessageFilter {
+    slurs: Option<Regex>,
+}
+
+impl MessageFilter {
+    pub fn new(config: &HouraiConfig) -> Self {
+        let slurs = config::load_list(config, "message_filter_slurs");
+        Self {
+            slurs: Self::make_slur_filter(&slurs),
+        }
+    }
+
+    fn make_slur_filter(slurs: &[String]) -> Option<Regex> {
+        if slurs.is_empty() {
+            return None;
+        }
+        let components: Vec<String> = slurs.iter().map(|s| generalize_filter(s)).collect();
+        let regex = format!("^({})", components.join("|"));
+        info!("Slur Filter: {}", regex);
+        match Regex::new(&regex) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                error!("Failed to compile slur filter: {}", err);
+                None
+            }
+        }
+    }
+
+    fn find_slur<'a>(&self, content: &'a str) -> Option<&'a str> {
+        let slurs = self.slurs.as_ref()?;
+        content.split_whitespace().find(|word| slurs.is_match(word))
+    }
+}
+
+/// Generalizes a filter so that repeated alphanumeric characters also match. For example,
+/// "abc" also matches "aaabbbccc".
+fn generalize_filter(filter: &str) -> String {
+    let mut output = String::new();
+    for chr in regex::escape(filter).chars() {
+        output.push(chr);
+        if chr.is_alphanumeric() {
+            output.push('+');
+        }
+    }
+    output
+}
+
+/// Checks a newly created or edited message against the guild's message filter rules. Every rule
+/// that the message violates is applied, but the message is only deleted once. `deleted` is set
+/// once the message has been deleted. Rules with invalid regexes are skipped, and reported to
+/// the modlog the first time they are seen.
+///
+/// `embed_urls` are the URLs of the embeds and attachments on the message, if known.
+pub async fn check_message(
+    client: &Client,
+    message: &impl MessageLike,
+    embed_urls: Option<HashSet<String>>,
+    deleted: &mut bool,
+) -> Result<()> {
+    let guild_id = match message.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    if message.author().id() == client.user_id {
+        return Ok(());
+    }
+
+    let mut redis = client.redis.clone();
+    let config = GuildConfig::fetch_or_default::<ModerationConfig>(guild_id, &mut redis).await?;
+    if !config.has_message_filter() {
+        return Ok(());
+    }
+
+    for rule in config.get_message_filter().get_rules() {
+        let criteria = rule.get_criteria();
+        let matches = client.regexes.get(guild_id, criteria.get_matches());
+        let reasons = match matches.set {
+            Ok(ref set) => get_rule_reasons(client, message, criteria, set, &embed_urls),
+            Err(_) if !matches.is_new => continue,
+            Err(err) => {
+                let content = format!(
+                    ":x: Skipped message filter rule '{}' with an invalid regex: {}",
+                    rule.get_name(),
+                    err
+                );
+                if let Err(err) = modlog::send(client, guild_id, content).await {
+                    error!("Error while reporting invalid message filter rule: {}", err);
+                }
+                continue;
+            }
+        };
+        if reasons.is_empty() || is_excluded(client, guild_id, message, criteria).await? {
+            continue;
+        }
+        apply_rule(client, guild_id, message, rule, &reasons, deleted).await?;
+    }
+    Ok(())
+}
+
+fn get_rule_reasons(
+    client: &Client,
+    message: &impl MessageLike,
+    criteria: &MessageFilterRule_Criteria,
+    matches: &RegexSet,
+    embed_urls: &Option<HashSet<String>>,
+) -> Vec<String> {
+    let content = message.content();
+    let mut reasons = Vec::new();
+
+    if matches.is_match(content) {
+        reasons.push("Message contains banned word or phrase.".to_owned());
+    }
+
+    if criteria.get_includes_slurs() {
+        if let Some(slur) = client.message_filter.find_slur(content) {
+            reasons.push(format!("Message contains recognized racial slur: {}", slur));
+        }
+    }
+
+    if criteria.get_includes_invite_links() && INVITE_REGEX.is_match(content) {
+        reasons.push("Message contains Discord invite link.".to_owned());
+    }
+
+    if criteria.has_mentions() {
+        reasons.extend(get_mention_reasons(content, criteria.get_mentions()));
+    }
+
+    if let Some(ref urls) = embed_urls {
+        let embeds = criteria.get_embeds();
+        if embeds.has_max_embed_count() && urls.len() > embeds.get_max_embed_count() as usize {
+            reasons.push(format!(
+                "Message has {} embeds or attachments. More than the server maximum of {}.",
+                urls.len(),
+                embeds.get_max_embed_count()
+            ));
+        }
+    }
+
+    reasons
+}
+
+fn get_mention_reasons(content: &str, criteria: &MentionFilterCriteria) -> Vec<String> {
+    let users: Vec<String> = USER_MENTION_REGEX
+        .captures_iter(content)
+        .map(|cap| format!("u{}", &cap[1]))
+        .collect();
+    let roles: Vec<String> = ROLE_MENTION_REGEX
+        .captures_iter(content)
+        .map(|cap| format!("r{}", &cap[1]))
+        .collect();
+    let all: Vec<String> = users.iter().chain(roles.iter()).cloned().collect();
+
+    let mut reasons = Vec::new();
+    if criteria.has_user_mention() {
+        check_mention_limits(
+            "user mentions",
+            criteria.get_user_mention(),
+            &users,
+            &mut reasons,
+        );
+    }
+    if criteria.has_role_mention() {
+        check_mention_limits(
+            "role mentions",
+            criteria.get_role_mention(),
+            &roles,
+            &mut reasons,
+        );
+    }
+    if criteria.has_any_mention() {
+        check_mention_limits("mentions", criteria.get_any_mention(), &all, &mut reasons);
+    }
+    reasons
+}
+
+fn check_mention_limits(
+    name: &str,
+    limits: &MentionFilterCriteria_MentionLimits,
+    mentions: &[String],
+    reasons: &mut Vec<String>,
+) {
+    if limits.has_maximum_total() && mentions.len() > limits.get_maximum_total() as usize {
+        reasons.push(format!(
+            "Total {} more than the server limit ({}).",
+            name,
+            limits.get_maximum_total()
+        ));
+    }
+    let unique: HashSet<&String> = mentions.iter().collect();
+    if limits.has_maximum_unique() && unique.len() > limits.get_maximum_unique() as usize {
+        reasons.push(format!(
+            "Unique {} more than the server limit ({}).",
+            name,
+            limits.get_maximum_unique()
+        ));
+    }
+}
+
+/// Checks if the message is exempt from the rule. The owner of the guild is always exempt.
+async fn is_excluded(
+    client: &Client,
+    guild_id: GuildId,
+    message: &impl MessageLike,
+    criteria: &MessageFilterRule_Criteria,
+) -> Result<bool> {
+    let author = message.author();
+    if criteria.get_exclude_bots() && author.bot() {
+        return Ok(true);
+    }
+    if criteria
+        .get_excluded_channels()
+        .contains(&message.channel_id().0)
+    {
+        return Ok(true);
+    }
+
+    let mut redis = client.redis.clone();
+    let guild = CachedGuild::fetch_resource::<Guild>(guild_id, guild_id, &mut redis).await?;
+    if guild.map(|g| g.get_owner_id()) == Some(author.id().0) {
+        return Ok(true);
+    }
+
+    Ok(criteria.get_exclude_moderators()
+        && moderation::is_moderator(client, guild_id, author.id()).await?)
+}
+
+/// Applies a rule the message violated. `deleted` tracks whether an earlier rule has already
+/// deleted the message, so that it is not deleted, and its author not notified, again.
+async fn apply_rule(
+    client: &Client,
+    guild_id: GuildId,
+    message: &impl MessageLike,
+    rule: &MessageFilterRule,
+    reasons: &[String],
+    deleted: &mut bool,
+) -> Result<()> {
+    let reasons_block = format!(
+        "\n```\n{}\n```",
+        reasons
+            .iter()
+            .map(|reason| format!("- {}", reason))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let mut mention_mod = rule.get_notify_moderator();
+    let mut action_taken = if rule.get_notify_moderator() {
+        "Message filter found notable message:".to_owned()
+    } else {
+        String::new()
+    };
+
+    if rule.get_delete_message() && *deleted {
+        if rule.get_notify_moderator() {
+            action_taken = "Message filter deleted message:".to_owned();
+        }
+    } else if rule.get_delete_message() {
+        let perms = client
+            .fetch_guild_permissions(guild_id, client.user_id)
+            .await?;
+        if perms.contains(Permissions::MANAGE_MESSAGES) {
+            if rule.get_notify_moderator() {
+                action_taken = "Message filter deleted message:".to_owned();
+            }
+            client
+                .http_client
+                .delete_message(message.channel_id(), message.id())
+                .await?;
+            *deleted = true;
+            if !message.author().bot() {
+                if let Err(err) = notify_author(client, guild_id, message, &reasons_block).await {
+                    error!("Failed to notify user of deleted message: {}", err);
+                }
+            }
+        } else {
+            mention_mod = true;
+            action_taken = format!(
+                "Attempted to delete, but don't have `Manage Messages` in <#{}>.",
+                message.channel_id()
+            );
+        }
+    }
+
+    let actions = rule.get_additional_actions().iter().map(|template| {
+        let mut action = template.clone();
+        action.set_guild_id(guild_id.0);
+        action.set_user_id(message.author().id().0);
+        if !action.has_reason() {
+            action.set_reason(format!("Triggered message filter: '{}'", rule.get_name()));
+        }
+        action
+    });
+    let failures: Vec<String> = actions::execute_all(client, actions)
+        .await
+        .into_iter()
+        .filter_map(|result| match result.outcome {
+            Err(err) => Some(format!(
+                "\n:x: Failed to run {} action: {}",
+                hourai::actions::action_type(&result.action),
+                err
+            )),
+            Ok(_) => None,
+        })
+        .collect();
+
+    if !mention_mod && action_taken.is_empty() && failures.is_empty() {
+        return Ok(());
+    }
+
+    let mut text = action_taken + &reasons_block + &failures.concat();
+    if mention_mod {
+        let mention = moderation::mention_online_moderator(client, guild_id).await?;
+        text = format!("{} {}", mention, text);
+    }
+    let embed = message_logging::message_to_embed(message)?.build()?;
+    modlog::send_embed(client, guild_id, text, embed).await
+}
+
+async fn notify_author(
+    client: &Client,
+    guild_id: GuildId,
+    message: &impl MessageLike,
+    reasons_block: &str,
+) -> Result<()> {
+    let mut redis = client.redis.clone();
+    let guild_name = CachedGuild::fetch_resource::<Guild>(guild_id, guild_id, &mut redis)
+        .await?
+        .map(|guild| guild.get_name().to_owned())
+        .unwrap_or_default();
+    let channel = client
+        .http_client
+        .create_private_channel(message.author().id())
+        .await?;
+    client
+        .http_client
+        .create_message(channel.id)
+        .content(format!(
+            "[{}] Your message was deleted for the following reasons: {}",
+            guild_name, reasons_block
+        ))?
+        .await?;
+    Ok(())
+}
@@ -2,14 +2,23 @@ use crate::Client;
 use anyhow::anyhow;
 use anyhow::Result;
 use chrono::Utc;
+use hourai::models::channel::Message;
 use hourai::models::gateway::payload::{MessageDelete, MessageDeleteBulk};
 use hourai::models::id::*;
 use hourai::models::{MessageLike, Snowflake, UserLike};
+use hourai::proto::cache::{CachedAttachmentProto, CachedEmbedProto, CachedMessageProto};
 use hourai::proto::guild_configs::*;
 use hourai::proto::util::IdFilter;
-use hourai_redis::{CachedMessage, GuildConfig};
+use hourai_redis::{CachedAttachment, CachedMessage, GuildConfig};
+use tracing::debug;
 use twilight_embed_builder::*;
 
+const MAX_FIELD_LENGTH: usize = 1024;
+/// The largest attachment that will be cached to be uploaded again.
+const MAX_CACHED_ATTACHMENT_SIZE: u64 = 512 * 1024;
+/// The most attachment data, in bytes, cached for each guild per day.
+const GUILD_ATTACHMENT_BUDGET: u64 = 32 * 1024 * 1024;
+
 fn message_base_embed(message: &impl MessageLike) -> Result<EmbedBuilder> {
     let author = message.author();
     Ok(EmbedBuilder::new()
@@ -22,8 +31,155 @@ fn message_base_embed(message: &impl MessageLike) -> Result<EmbedBuilder> {
         .timestamp(Utc::now().to_rfc3339()))
 }
 
-fn message_to_embed(message: &impl MessageLike) -> Result<EmbedBuilder> {
-    Ok(message_base_embed(message)?.description(message.content())?)
+pub(super) fn message_to_embed(message: &impl MessageLike) -> Result<EmbedBuilder> {
+    let mut embed = message_base_embed(message)?;
+    if !message.content().is_empty() {
+        embed = embed.description(message.content())?;
+    }
+    let attachments = message.attachments();
+    if !attachments.is_empty() {
+        let summary = attachments
+            .iter()
+            .map(summarize_attachment)
+            .collect::<Vec<_>>()
+            .join("\n");
+        embed = embed.field(EmbedFieldBuilder::new(
+            "Attachments",
+            truncate(&summary, MAX_FIELD_LENGTH),
+        )?);
+    }
+    let embeds = message.embeds();
+    if !embeds.is_empty() {
+        let summary = embeds
+            .iter()
+            .map(summarize_embed)
+            .collect::<Vec<_>>()
+            .join("\n");
+        embed = embed.field(EmbedFieldBuilder::new(
+            "Embeds",
+            truncate(&summary, MAX_FIELD_LENGTH),
+        )?);
+    }
+    Ok(embed)
+}
+
+fn summarize_attachment(attachment: &CachedAttachmentProto) -> String {
+    format!(
+        "[{}]({}) ({})",
+        attachment.get_filename(),
+        attachment.get_proxy_url(),
+        format_size(attachment.get_size())
+    )
+}
+
+fn summarize_embed(embed: &CachedEmbedProto) -> String {
+    let parts: Vec<&str> = vec![embed.get_title(), embed.get_url(), embed.get_image_url()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    if !parts.is_empty() {
+        parts.join(" - ")
+    } else if embed.has_description() {
+        truncate(embed.get_description(), 100)
+    } else {
+        "(Empty embed)".to_owned()
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        text.to_owned()
+    } else {
+        let mut truncated: String = text.chars().take(max_length - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// Downloads and caches the attachments of a new message, if the guild uploads the attachments
+/// of deleted messages again. Discord usually stops serving a message's attachments once it is
+/// deleted, so they are fetched while the message still exists. Only small attachments are
+/// cached, and only until the guild's daily budget runs out.
+pub(super) async fn cache_attachments(mut client: Client, message: Message) -> Result<()> {
+    let guild_id = match message.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+    let config = get_logging_config(&mut client, guild_id).await?;
+    let type_config = config.get_deleted_messages();
+    if !type_config.get_reupload_attachments()
+        || get_output_channel(&config, type_config).is_none()
+        || !should_log(type_config, message.channel_id)
+    {
+        return Ok(());
+    }
+
+    for attachment in message.attachments.iter() {
+        if attachment.size > MAX_CACHED_ATTACHMENT_SIZE {
+            continue;
+        }
+        let reserved = CachedAttachment::reserve(
+            guild_id,
+            attachment.size,
+            GUILD_ATTACHMENT_BUDGET,
+            &mut client.redis,
+        )
+        .await?;
+        if !reserved {
+            debug!("Attachment cache budget for guild {} is used up", guild_id);
+            break;
+        }
+        match download(&attachment.url).await {
+            Ok(data) => {
+                CachedAttachment::save(message.channel_id, message.id, attachment.id.0, data)
+                    .query_async::<_, ()>(&mut client.redis)
+                    .await?;
+            }
+            Err(err) => debug!("Failed to download attachment {}: {}", attachment.id, err),
+        }
+    }
+    Ok(())
+}
+
+/// Loads the cached contents of a deleted message's attachments. Attachments that were not
+/// cached are skipped.
+async fn load_attachments(
+    client: &mut Client,
+    message: &CachedMessageProto,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    for attachment in message.get_attachments() {
+        let data = CachedAttachment::fetch(
+            message.channel_id(),
+            message.id(),
+            attachment.get_id(),
+            &mut client.redis,
+        )
+        .await?;
+        if let Some(data) = data {
+            files.push((attachment.get_filename().to_owned(), data));
+        }
+    }
+    Ok(files)
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
 }
 
 fn message_diff_embed(before: &impl MessageLike, after: &impl MessageLike) -> Result<EmbedBuilder> {
@@ -106,6 +262,11 @@ pub(super) async fn on_message_delete(client: &mut Client, evt: &MessageDelete)
             if msg.author().bot() {
                 return Ok(());
             }
+            let files = if type_config.get_reupload_attachments() {
+                load_attachments(client, &msg).await?
+            } else {
+                Vec::new()
+            };
             client
                 .http_client
                 .create_message(output_channel.unwrap())
@@ -119,6 +280,7 @@ pub(super) async fn on_message_delete(client: &mut Client, evt: &MessageDelete)
                         .color(0x992d22)? // Dark red
                         .build()?,
                 )?
+                .files(files)
                 .await?;
         }
     }
@@ -146,3 +308,34 @@ pub(super) async fn on_message_bulk_delete(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(8 * 1024 * 1024), "8.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("abcd", 3), "ab…");
+    }
+
+    #[test]
+    fn test_summarize_embed() {
+        let mut embed = CachedEmbedProto::new();
+        assert_eq!(summarize_embed(&embed), "(Empty embed)");
+        embed.set_description("A description".to_owned());
+        assert_eq!(summarize_embed(&embed), "A description");
+        embed.set_title("Title".to_owned());
+        embed.set_url("https://example.com".to_owned());
+        assert_eq!(summarize_embed(&embed), "Title - https://example.com");
+    }
+}
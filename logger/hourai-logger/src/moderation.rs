@@ -0,0 +1,35 @@
+use crate::Client;
+use anyhow::Result;
+use hourai::models::id::*;
+use hourai_redis::{CachedGuild, OnlineStatus};
+
+/// Checks if a member of a guild is a moderator.
+pub async fn is_moderator(client: &Client, guild_id: GuildId, user_id: UserId) -> Result<bool> {
+    let member = hourai_sql::Member::fetch(guild_id, user_id)
+        .fetch_optional(&client.sql)
+        .await?;
+    let member = match member {
+        Some(member) if !member.bot => member,
+        _ => return Ok(false),
+    };
+    let mut redis = client.redis.clone();
+    let mod_roles = CachedGuild::fetch_moderator_roles(guild_id, &mut redis).await?;
+    Ok(member.role_ids().any(|id| mod_roles.contains(&id)))
+}
+
+/// Creates a mention for a random online moderator. If no moderator is online, the owner of
+/// the guild is mentioned instead.
+pub async fn mention_online_moderator(client: &Client, guild_id: GuildId) -> Result<String> {
+    let mut redis = client.redis.clone();
+    let mod_roles = CachedGuild::fetch_moderator_roles(guild_id, &mut redis).await?;
+    let online = OnlineStatus::fetch_online(guild_id, &mut redis).await?;
+    let moderators: Vec<UserId> = hourai_sql::Member::fetch_with_roles(guild_id, &mod_roles)
+        .fetch_all(&client.sql)
+        .await?
+        .iter()
+        .map(|member| member.user_id())
+        .filter(|id| online.contains(id))
+        .collect();
+    let owner_id = CachedGuild::fetch_owner_id(guild_id, &mut redis).await?;
+    Ok(hourai::moderation::mention_moderator(&moderators, owner_id))
+}
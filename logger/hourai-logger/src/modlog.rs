@@ -0,0 +1,41 @@
+use crate::Client;
+use anyhow::Result;
+use hourai::models::{channel::embed::Embed, id::*};
+use hourai_redis::GuildConfig;
+
+/// Gets the modlog channel for a guild, if one has been configured.
+pub async fn get_modlog_channel(client: &Client, guild_id: GuildId) -> Result<Option<ChannelId>> {
+    let mut redis = client.redis.clone();
+    GuildConfig::fetch_modlog_channel(guild_id, &mut redis).await
+}
+
+/// Sends a message to the guild's modlog. A no-op if no modlog channel is configured.
+pub async fn send(client: &Client, guild_id: GuildId, content: impl Into<String>) -> Result<()> {
+    if let Some(channel_id) = get_modlog_channel(client, guild_id).await? {
+        client
+            .http_client
+            .create_message(channel_id)
+            .content(content.into())?
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends a message with an embed to the guild's modlog. A no-op if no modlog channel is
+/// configured.
+pub async fn send_embed(
+    client: &Client,
+    guild_id: GuildId,
+    content: impl Into<String>,
+    embed: Embed,
+) -> Result<()> {
+    if let Some(channel_id) = get_modlog_channel(client, guild_id).await? {
+        client
+            .http_client
+            .create_message(channel_id)
+            .content(content.into())?
+            .embed(embed)?
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use hourai::models::id::GuildId;
+use regex::RegexSet;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of compiled sets kept per guild. Older versions of a guild's config are
+/// only dropped once this is exceeded.
+const MAX_SETS_PER_GUILD: usize = 64;
+
+type CachedSet = Result<Arc<RegexSet>, regex::Error>;
+
+/// A regex set compiled from a list of patterns in a guild's config.
+pub struct CompiledSet {
+    pub set: CachedSet,
+    /// Whether the set was compiled by this lookup, rather than already cached. Errors are only
+    /// reported for new sets, so each invalid pattern is reported once per config change.
+    pub is_new: bool,
+}
+
+/// Caches the regex sets compiled from guild configs, so that they are compiled once per version
+/// of the config instead of on every event. Sets are keyed by their patterns, so any change to
+/// the config compiles a new set. Sets that fail to compile are cached as well.
+#[derive(Clone, Default)]
+pub struct RegexCache(Arc<Mutex<HashMap<GuildId, HashMap<Vec<String>, CachedSet>>>>);
+
+impl RegexCache {
+    pub fn get(&self, guild_id: GuildId, patterns: &[String]) -> CompiledSet {
+        let mut guilds = self.0.lock().unwrap();
+        let sets = guilds.entry(guild_id).or_default();
+        if let Some(set) = sets.get(patterns) {
+            return CompiledSet {
+                set: set.clone(),
+                is_new: false,
+            };
+        }
+
+        if sets.len() >= MAX_SETS_PER_GUILD {
+            sets.clear();
+        }
+        let set = RegexSet::new(patterns).map(Arc::new);
+        sets.insert(patterns.to_vec(), set.clone());
+        CompiledSet { set, is_new: true }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_compiles_once() {
+        let cache = RegexCache::default();
+        let first = cache.get(GuildId(1), &patterns(&["a", "b"]));
+        assert!(first.is_new);
+        assert!(first.set.unwrap().is_match("b"));
+
+        let second = cache.get(GuildId(1), &patterns(&["a", "b"]));
+        assert!(!second.is_new);
+        assert!(cache.get(GuildId(2), &patterns(&["a", "b"])).is_new);
+        assert!(cache.get(GuildId(1), &patterns(&["a"])).is_new);
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_new_once() {
+        let cache = RegexCache::default();
+        let first = cache.get(GuildId(1), &patterns(&["("]));
+        assert!(first.is_new);
+        assert!(first.set.is_err());
+
+        let second = cache.get(GuildId(1), &patterns(&["("]));
+        assert!(!second.is_new);
+        assert!(second.set.is_err());
+    }
+}
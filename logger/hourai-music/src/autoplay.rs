@@ -0,0 +1,44 @@
+use crate::{prelude::*, track::Track, Client};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+const YOUTUBE_PREFIX: &str = "https://www.youtube.com/watch?v=";
+
+/// Builds the query used to find tracks related to a track. YouTube tracks use the video's
+/// auto-generated mix. All other tracks fall back to searching YouTube for the track's author.
+fn related_query(track: &Track) -> Option<String> {
+    if let Some(id) = track.info.uri.strip_prefix(YOUTUBE_PREFIX) {
+        return Some(format!("{}{}&list=RD{}", YOUTUBE_PREFIX, id, id));
+    }
+    track
+        .info
+        .author
+        .as_ref()
+        .or_else(|| track.info.title.as_ref())
+        .map(|query| format!("ytsearch:{}", query))
+}
+
+/// Finds a track related to a previously played track, skipping any live streams or recently
+/// played tracks. The found track is attributed to the requestor of the previous track.
+pub async fn find_related_track(
+    client: &Client<'static>,
+    node: &Node,
+    previous: &Track,
+    history: &VecDeque<String>,
+) -> Result<Option<Track>> {
+    let query = match related_query(previous) {
+        Some(query) => query,
+        None => return Ok(None),
+    };
+
+    let track = client
+        .load_tracks(node, query.as_str())
+        .await?
+        .tracks
+        .into_iter()
+        .filter(|track| !track.info.is_stream)
+        .find(|track| track.info.uri != previous.info.uri && !history.contains(&track.info.uri));
+
+    Ok(track.and_then(|track| Track::try_from((previous.requestor.clone(), track)).ok()))
+}
@@ -1,19 +1,42 @@
 use crate::prelude::*;
-use crate::{player::PlayerState, queue::MusicQueue, track::Track, ui::*, Client};
+use crate::{
+    fair_share::FairShare,
+    filters,
+    lyrics::{self, LyricsPager},
+    player::{PlayerState, RepeatMode},
+    queue::MusicQueue,
+    search,
+    track::Track,
+    ui::*,
+    votes::{self, VoteAction},
+    Client,
+};
 use anyhow::{bail, Result};
 use hourai::{
-    commands::{self, precondition::*, prelude::*, CommandError},
+    commands::{self, precondition::*, prelude::*, CommandError, Invocation},
+    http::request::channel::reaction::RequestReactionType,
     models::{
         channel::Message,
-        id::{ChannelId, GuildId, RoleId},
-        UserLike,
+        id::{ChannelId, GuildId, RoleId, UserId},
+        Snowflake, UserLike,
     },
     proto::guild_configs::MusicConfig,
 };
-use std::{collections::HashSet, convert::TryFrom};
-use twilight_command_parser::{Arguments, Command};
+use hourai_sql::playlists::{Playlist, PlaylistOwner, PlaylistTrack};
+use hourai_sql::sql_types::chrono::Utc;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+use std::sync::atomic::AtomicUsize;
+use twilight_command_parser::Arguments;
 use twilight_lavalink::http::LoadType;
 
+/// The default amount of time to rewind or fast forward by.
+const DEFAULT_SEEK_OFFSET: Duration = Duration::from_secs(10);
+/// The maximum number of tracks that can be saved to a playlist.
+const MAX_PLAYLIST_LENGTH: usize = 500;
+const MAX_PLAYLIST_NAME_LENGTH: usize = 255;
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
 macro_rules! get_player {
     ($client:expr, $guild_id: expr) => {
         $client.lavalink.players().get($guild_id).unwrap().value()
@@ -21,70 +44,84 @@ macro_rules! get_player {
 }
 
 pub async fn on_message_create(client: Client<'static>, evt: Message) -> Result<()> {
-    if evt.author.bot {
+    if evt.author.bot || search::on_message(&client, &evt) {
         return Ok(());
     }
 
     if let Some(command) = client.parser.parse(evt.content.as_str()) {
-        let ctx = commands::Context {
-            message: &evt,
-            http: client.http_client.clone(),
-        };
+        let ctx = commands::Context::new(Invocation::Message(&evt), client.http_client.clone());
+        run_command(&client, ctx, command.name, command.arguments).await?;
+    }
+    Ok(())
+}
 
-        let result = match command {
-            Command {
-                name: "play",
-                arguments,
-                ..
-            } => play(&client, ctx, arguments.into_remainder()).await,
-            Command { name: "pause", .. } => pause(&client, ctx, true).await,
-            Command { name: "stop", .. } => stop(&client, ctx).await,
-            Command {
-                name: "shuffle", ..
-            } => shuffle(&client, ctx).await,
-            Command { name: "skip", .. } => skip(&client, ctx).await,
-            Command {
-                name: "forceskip", ..
-            } => forceskip(&client, ctx).await,
-            Command {
-                name: "remove",
-                mut arguments,
-                ..
-            } => remove(&client, ctx, &mut arguments).await,
-            Command {
-                name: "removeall", ..
-            } => remove_all(&client, ctx).await,
-            Command {
-                name: "nowplaying", ..
-            } => now_playing(&client, ctx).await,
-            Command { name: "np", .. } => now_playing(&client, ctx).await,
-            Command { name: "queue", .. } => queue(&client, ctx).await,
-            Command {
-                name: "volume",
-                mut arguments,
-                ..
-            } => volume(&client, ctx, &mut arguments).await,
-            _ => {
-                debug!("Failed to find command: {}", evt.content.as_str());
-                Ok(())
-            }
-        };
+/// Runs a command, whether it was invoked by a message or an interaction. Errors meant for the
+/// user are sent back to them.
+pub async fn run_command(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    name: &str,
+    arguments: Arguments<'_>,
+) -> Result<()> {
+    let result = dispatch(client, ctx.clone(), name, arguments).await;
 
-        if let Err(err) = result {
-            match err.downcast::<CommandError>() {
-                Ok(command_error) => {
-                    client
-                        .http_client
-                        .create_message(evt.channel_id)
-                        .reply(evt.id)
-                        .content(format!(":x: {}", command_error))?
-                        .await?;
-                }
-                Err(err) => bail!(err),
-            }
+    // Remember where the last command was used to announce leaving while idle.
+    if let Some(guild_id) = ctx.guild_id() {
+        client.mutate_state(guild_id, |state| {
+            state.text_channel_id = Some(ctx.channel_id())
+        });
+    }
+
+    if let Err(err) = result {
+        match err.downcast::<CommandError>() {
+            Ok(command_error) => ctx.respond_error(&command_error).await?,
+            Err(err) => bail!(err),
+        }
+    }
+    ctx.finish().await
+}
+
+async fn dispatch(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    name: &str,
+    mut arguments: Arguments<'_>,
+) -> Result<()> {
+    match name {
+        "play" => play(client, ctx, arguments.into_remainder()).await,
+        "search" => search_tracks(client, ctx, arguments.into_remainder()).await,
+        "pause" => pause(client, ctx, true).await,
+        "stop" => stop(client, ctx).await,
+        "shuffle" => shuffle(client, ctx, &mut arguments).await,
+        "skip" => skip(client, ctx).await,
+        "forceskip" => forceskip(client, ctx).await,
+        "remove" => remove(client, ctx, &mut arguments).await,
+        "move" => move_track(client, ctx, &mut arguments).await,
+        "swap" => swap(client, ctx, &mut arguments).await,
+        "skipto" => skip_to(client, ctx, &mut arguments).await,
+        "removeall" => remove_all(client, ctx).await,
+        "clear" => clear(client, ctx).await,
+        "nowplaying" => now_playing(client, ctx).await,
+        "np" => now_playing(client, ctx).await,
+        "queue" => queue(client, ctx).await,
+        "lyrics" => show_lyrics(client, ctx).await,
+        "repeat" => toggle_repeat(client, ctx, RepeatMode::Track).await,
+        "loop" => toggle_repeat(client, ctx, RepeatMode::Queue).await,
+        "autoplay" => autoplay(client, ctx).await,
+        "seek" => seek(client, ctx, &mut arguments).await,
+        "rewind" => rewind(client, ctx, &mut arguments).await,
+        "fastforward" => fast_forward(client, ctx, &mut arguments).await,
+        "ff" => fast_forward(client, ctx, &mut arguments).await,
+        "replay" => replay(client, ctx).await,
+        "filter" => filter(client, ctx, &mut arguments).await,
+        "eq" => equalizer(client, ctx, &mut arguments).await,
+        "playlist" => playlist(client, ctx, &mut arguments).await,
+        "volume" => volume(client, ctx, &mut arguments).await,
+        _ => {
+            debug!("Failed to find command: {}", name);
+            Ok(())
         }
     }
-    Ok(())
 }
 
 async fn require_in_voice_channel(
@@ -94,7 +131,7 @@ async fn require_in_voice_channel(
     let guild_id = require_in_guild(&ctx)?;
 
     let mut redis = client.redis.clone();
-    let user: Option<u64> = hourai_redis::CachedVoiceState::get_channel(guild_id, ctx.message.author.id)
+    let user: Option<u64> = hourai_redis::CachedVoiceState::get_channel(guild_id, ctx.author().id)
         .query_async(&mut redis)
         .await?;
     let user = user.map(ChannelId);
@@ -132,9 +169,9 @@ fn is_dj(config: &MusicConfig, roles: &[RoleId]) -> bool {
 /// Requires that the author is a DJ on the server to use the command.
 async fn require_dj(client: &Client<'static>, ctx: &commands::Context<'_>) -> Result<()> {
     let (guild_id, _) = require_in_voice_channel(client, &ctx).await?;
-    if let Some(member) = &ctx.message.member {
+    if let Some(roles) = ctx.roles() {
         let config = client.get_config(guild_id).await?;
-        if is_dj(&config, &member.roles) {
+        if is_dj(&config, roles) {
             return Ok(());
         }
     }
@@ -143,6 +180,48 @@ async fn require_dj(client: &Client<'static>, ctx: &commands::Context<'_>) -> Re
     ))
 }
 
+/// Casts the author's vote for an action that affects everyone listening. DJs take the action
+/// without a vote. Returns None if the action should be taken, or a response with the current
+/// number of votes if it needs more.
+async fn vote(
+    client: &Client<'static>,
+    ctx: &commands::Context<'_>,
+    guild_id: GuildId,
+    action: VoteAction,
+) -> Result<Option<String>> {
+    require_in_voice_channel(client, ctx).await?;
+    let config = client.get_config(guild_id).await?;
+    let dj = ctx.roles().map_or(false, |roles| is_dj(&config, roles));
+    let threshold = action.threshold(&config);
+    if !dj && threshold == 0 {
+        bail!(CommandError::FailedPrecondition(
+            "User must be a DJ to use this command."
+        ));
+    }
+
+    let listeners = client.count_listeners(guild_id).await?;
+    let required = votes::required_votes(listeners, threshold);
+    let author = ctx.author().id;
+    let votes = client
+        .mutate_state(guild_id, |state| {
+            let votes = state.votes.cast(action, author);
+            if dj || votes >= required {
+                state.votes.clear(action);
+            }
+            votes
+        })
+        .unwrap_or_default();
+
+    if dj || votes >= required {
+        Ok(None)
+    } else {
+        Ok(Some(format!(
+            ":ballot_box: Votes to {}: `{}/{}`.",
+            action, votes, required
+        )))
+    }
+}
+
 async fn load_tracks(
     client: &Client<'static>,
     node: &Node,
@@ -170,9 +249,12 @@ async fn load_tracks(
             tracks,
             ..
         } => {
-            // TODO(james7132): This could be improved by doing a edit distance from
-            // the query for similarity matching.
-            vec![tracks[0].clone()]
+            let query = search::strip_search_prefix(query);
+            let names = tracks
+                .iter()
+                .map(|t| (t.info.title.as_deref(), t.info.author.as_deref()));
+            let idx = search::closest_match(query, names).unwrap_or(0);
+            vec![tracks[idx].clone()]
         }
         LoadedTracks {
             load_type: LoadType::PlaylistLoaded,
@@ -222,14 +304,14 @@ async fn play(
         if let Some(results) = load_tracks(client, &node, subquery.as_str()).await {
             queue = results
                 .into_iter()
-                .filter_map(|t| Track::try_from((ctx.message.author.clone(), t)).ok())
+                .filter_map(|t| Track::try_from((ctx.author().clone(), t)).ok())
                 .collect();
             break;
         }
     }
 
     let duration = format_duration(queue.iter().map(|t| t.info.length).sum());
-    let response = match queue.len() {
+    let mut response = match queue.len() {
         0 => format!(":bulb: No results found for `{}`", query),
         1 => format!(
             ":notes: Added `{}` ({}) to the music queue.",
@@ -242,71 +324,159 @@ async fn play(
     };
 
     if queue.len() > 0 {
-        if let Some(mut state) = client.states.get_mut(&guild_id) {
-            state.value_mut().queue.extend(ctx.message.author.id, queue);
-        } else {
+        let rejected = enqueue(client, &ctx, guild_id, channel_id, queue).await?;
+        response.push_str(&queue_limit_note(rejected));
+    }
+
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Searches for tracks and lets the author pick which one to add to the queue.
+async fn search_tracks(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    query: Option<&str>,
+) -> Result<()> {
+    let (guild_id, channel_id) = require_in_voice_channel(client, &ctx).await?;
+    let query = query
+        .map(|query| query.trim())
+        .filter(|query| !query.is_empty())
+        .ok_or(CommandError::MissingArgument)?;
+
+    let node = client.get_node(guild_id).await?;
+    let mut results = Vec::new();
+    for subquery in &[format!("ytsearch:{}", query), format!("scsearch:{}", query)] {
+        match client.load_tracks(&node, subquery).await {
+            Ok(loaded) if !loaded.tracks.is_empty() => {
+                results = loaded.tracks;
+                break;
+            }
+            Ok(_) => {}
+            Err(err) => error!("Failed to load query `{}`: {}", subquery, err),
+        }
+    }
+
+    if results.is_empty() {
+        ctx.respond(format!(":bulb: No results found for `{}`", query))
+            .await?;
+        return Ok(());
+    }
+
+    let picked = search::pick_result(client, &ctx, query, results).await?;
+    let track = Track::try_from((ctx.author().clone(), picked))?;
+    let response = format!(
+        ":notes: Added `{}` ({}) to the music queue.",
+        track.info,
+        format_duration(track.info.length)
+    );
+    enqueue(client, &ctx, guild_id, channel_id, vec![track]).await?;
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Adds tracks to the end of the author's queue. Starts playing in the given voice channel if
+/// nothing is currently playing.
+///
+/// Returns the number of tracks left out due to the server's queue limits. Fails if none of
+/// the tracks could be added.
+async fn enqueue(
+    client: &Client<'static>,
+    ctx: &commands::Context<'_>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    tracks: Vec<Track>,
+) -> Result<usize> {
+    let config = client.get_config(guild_id).await?;
+    let dj = ctx.roles().map_or(false, |roles| is_dj(&config, roles));
+    let author = ctx.author().id;
+    let count = tracks.len();
+    let rejected = if let Some(mut state) = client.states.get_mut(&guild_id) {
+        let queue = &mut state.value_mut().queue;
+        queue.policy_mut().configure(&config);
+        queue.policy_mut().set_dj(author, dj);
+        queue.extend(author, tracks)
+    } else {
+        let mut policy = FairShare::new(&config);
+        policy.set_dj(author, dj);
+        let mut state_queue = MusicQueue::with_policy(policy);
+        let rejected = state_queue.extend(author, tracks);
+        if rejected < count {
             client.connect(guild_id, channel_id).await?;
-            let mut state_queue = MusicQueue::new();
-            state_queue.extend(ctx.message.author.id, queue);
-            client.states.insert(
-                guild_id,
-                PlayerState {
-                    skip_votes: HashSet::new(),
-                    queue: state_queue,
-                    now_playing_ui: None,
-                    queue_ui: None,
-                },
-            );
+            client
+                .states
+                .insert(guild_id, PlayerState::new(state_queue));
             client.start_playing(guild_id).await?;
         }
+        rejected
+    };
+    if rejected >= count {
+        bail!(CommandError::FailedPrecondition(
+            "You have reached the server's limit on queued tracks."
+        ));
     }
+    Ok(rejected)
+}
 
-    ctx.respond().content(response)?.await?;
-    Ok(())
+/// Describes how many tracks were left out of the queue due to the server's queue limits.
+fn queue_limit_note(rejected: usize) -> String {
+    match rejected {
+        0 => String::new(),
+        1 => "\n:warning: 1 track was left out due to the server's queue limits.".to_owned(),
+        x => format!(
+            "\n:warning: **{}** tracks were left out due to the server's queue limits.",
+            x
+        ),
+    }
 }
 
 async fn pause(client: &Client<'static>, ctx: commands::Context<'_>, pause: bool) -> Result<()> {
     let guild_id = require_playing(client, &ctx)?;
     require_dj(client, &ctx).await?;
     get_player!(client, &guild_id).set_pause(pause)?;
+    client.mutate_state(guild_id, |state| state.paused_when_empty = false);
     let response = if pause {
         "The music bot has been paused."
     } else {
         "The music bot has been unpaused."
     };
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
     Ok(())
 }
 
 async fn stop(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
     let guild_id = require_playing(client, &ctx)?;
-    require_dj(client, &ctx).await?;
+    if let Some(response) = vote(client, &ctx, guild_id, VoteAction::Stop).await? {
+        ctx.respond(response).await?;
+        return Ok(());
+    }
     client.disconnect(guild_id).await?;
-    ctx.respond()
-        .content("The player has been stopped and the queue has been cleared")?
+    ctx.respond("The player has been stopped and the queue has been cleared")
         .await?;
     Ok(())
 }
 
 async fn skip(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
     let guild_id = require_playing(client, &ctx)?;
-    require_in_voice_channel(client, &ctx).await?;
-    let listeners = client.count_listeners(guild_id).await?;
-    let (votes, required, requestor) = client
-        .mutate_state(guild_id, |state| {
-            let (requestor, _) = state.currently_playing().unwrap();
-            state.skip_votes.insert(ctx.message.author.id);
-            (state.skip_votes.len(), listeners / 2, requestor)
-        })
-        .unwrap();
+    let requestor = client
+        .states
+        .get(&guild_id)
+        .and_then(|kv| kv.value().currently_playing())
+        .map(|(requestor, _)| requestor);
 
-    let response = if votes >= required || requestor == ctx.message.author.id {
-        format!("Skipped `{}`", client.play_next(guild_id).await?.unwrap())
+    // Whoever requested a track can skip it without a vote.
+    let pending = if requestor == Some(ctx.author().id) {
+        require_in_voice_channel(client, &ctx).await?;
+        None
     } else {
-        format!("Total votes: `{}/{}`.", votes, required)
+        vote(client, &ctx, guild_id, VoteAction::Skip).await?
+    };
+    let response = match pending {
+        Some(response) => response,
+        None => format!("Skipped `{}`", client.play_next(guild_id).await?.unwrap()),
     };
 
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
     Ok(())
 }
 
@@ -317,25 +487,32 @@ async fn remove(
 ) -> Result<()> {
     let guild_id = require_playing(client, &ctx)?;
     require_in_voice_channel(client, &ctx).await?;
-    let idx = arguments.parse_next::<usize>()?;
+    let range = parse_queue_range(arguments)?;
     commands::precondition::no_excess_arguments(arguments)?;
 
-    if idx == 0 {
+    if *range.start() == 0 {
         // Do not allow removing the currently playing song from the queue.
-        ctx.respond()
-            .content(":x: Invalid index for removal.")?
-            .await?;
+        ctx.respond(":x: Invalid index for removal.").await?;
         return Ok(());
     }
 
     let config = client.get_config(guild_id).await?;
-    let dj = is_dj(&config, &ctx.message.member.as_ref().unwrap().roles);
+    let dj = ctx.roles().map_or(false, |roles| is_dj(&config, roles));
+
+    let author = ctx.author().id;
+    if range.start() != range.end() {
+        let response = client
+            .mutate_state(guild_id, |state| remove_range(state, range, author, dj))
+            .unwrap();
+        ctx.respond(response).await?;
+        return Ok(());
+    }
 
-    let author = ctx.message.author.id;
+    let idx = *range.start();
     let response = client
         .mutate_state(guild_id, |state| {
             match state.queue.get(idx).map(|kv| kv.value.clone()) {
-                Some(track) if author == track.requestor.id || dj => {
+                Some(track) if author == track.requestor.id() || dj => {
                     state.queue.remove(idx);
                     format!("Removed `{}` from the queue.", track.info)
                 }
@@ -350,7 +527,190 @@ async fn remove(
         })
         .unwrap();
 
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+fn remove_range(
+    state: &mut PlayerState,
+    range: RangeInclusive<usize>,
+    author: UserId,
+    dj: bool,
+) -> String {
+    let (start, end) = (*range.start(), *range.end());
+    let mut requestors = state
+        .queue
+        .iter()
+        .skip(start)
+        .take(end - start + 1)
+        .map(|kv| kv.key);
+    if state.queue.len() <= start {
+        format!(
+            "There are no tracks between indices {} and {} in the queue.",
+            start, end
+        )
+    } else if !dj && requestors.any(|requestor| requestor != author) {
+        "Only a DJ can remove other users' tracks from the queue.".to_owned()
+    } else {
+        let count = state.queue.remove_range(range).len();
+        format!("Removed **{}** tracks from the queue.", count)
+    }
+}
+
+/// Parses a queue index or an inclusive range of queue indices. (i.e. `3` or `3-5`)
+fn parse_queue_range(arguments: &mut Arguments<'_>) -> Result<RangeInclusive<usize>> {
+    let arg = arguments.next().ok_or(CommandError::MissingArgument)?;
+    let err = || {
+        CommandError::InvalidArgument(format!(
+            "`{}` is not a valid index or range of indices. (i.e. `3` or `3-5`)",
+            arg
+        ))
+    };
+    let mut parts = arg.splitn(2, '-');
+    let start: usize = parts
+        .next()
+        .and_then(|idx| idx.parse().ok())
+        .ok_or_else(err)?;
+    let end: usize = match parts.next() {
+        Some(idx) => idx.parse().map_err(|_| err())?,
+        None => start,
+    };
+    if end < start {
+        bail!(err());
+    }
+    Ok(start..=end)
+}
+
+/// Parses the two queue indices of a command that rearranges the queue, and checks if the
+/// author is a DJ.
+async fn parse_rearrange(
+    client: &Client<'static>,
+    ctx: &commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<(GuildId, usize, usize, bool)> {
+    let guild_id = require_playing(client, ctx)?;
+    require_in_voice_channel(client, ctx).await?;
+    let a = arguments.parse_next::<usize>()?;
+    let b = arguments.parse_next::<usize>()?;
+    commands::precondition::no_excess_arguments(arguments)?;
+    if a == 0 || b == 0 {
+        bail!(CommandError::FailedPrecondition(
+            "The currently playing track cannot be moved."
+        ));
+    }
+
+    let config = client.get_config(guild_id).await?;
+    let dj = ctx.roles().map_or(false, |roles| is_dj(&config, roles));
+    Ok((guild_id, a, b, dj))
+}
+
+/// Gets the tracks at two indices if the author can rearrange them. Tracks can only be
+/// rearranged within the queue of the user that requested them, and only by that user or a DJ.
+/// Otherwise, returns the response explaining why not.
+fn check_rearrange(
+    state: &mut PlayerState,
+    a: usize,
+    b: usize,
+    author: UserId,
+    dj: bool,
+) -> std::result::Result<(Track, Track), String> {
+    let mut get = |idx: usize| {
+        state
+            .queue
+            .get(idx)
+            .map(|kv| (kv.key, kv.value.clone()))
+            .ok_or_else(|| format!("There is no track at index {} in the queue.", idx))
+    };
+    let (requestor, track_a) = get(a)?;
+    let (other, track_b) = get(b)?;
+    if requestor != other {
+        Err("Tracks can only be moved within the queue of the user that requested them.".to_owned())
+    } else if requestor != author && !dj {
+        Err(format!(
+            "Only a DJ or {} can rearrange their tracks in the queue.",
+            track_a.requestor.display_name()
+        ))
+    } else {
+        Ok((track_a, track_b))
+    }
+}
+
+async fn move_track(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let (guild_id, from, to, dj) = parse_rearrange(client, &ctx, arguments).await?;
+    let author = ctx.author().id;
+    let response = client
+        .mutate_state(guild_id, |state| {
+            match check_rearrange(state, from, to, author, dj) {
+                Ok((track, _)) => {
+                    state.queue.move_item(from, to);
+                    format!("Moved `{}` to position {} in the queue.", track.info, to)
+                }
+                Err(response) => response,
+            }
+        })
+        .unwrap();
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+async fn swap(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let (guild_id, a, b, dj) = parse_rearrange(client, &ctx, arguments).await?;
+    let author = ctx.author().id;
+    let response = client
+        .mutate_state(guild_id, |state| {
+            match check_rearrange(state, a, b, author, dj) {
+                Ok((track_a, track_b)) => {
+                    state.queue.swap(a, b);
+                    format!(
+                        "Swapped `{}` and `{}` in the queue.",
+                        track_a.info, track_b.info
+                    )
+                }
+                Err(response) => response,
+            }
+        })
+        .unwrap();
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Skips to a track in the queue. Only DJs can skip over tracks requested by other users.
+async fn skip_to(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    require_in_voice_channel(client, &ctx).await?;
+    let idx = arguments.parse_next::<usize>()?;
+    commands::precondition::no_excess_arguments(arguments)?;
+    if idx == 0 {
+        bail!(CommandError::FailedPrecondition(
+            "Cannot skip to the currently playing track."
+        ));
+    }
+
+    let author = ctx.author().id;
+    let only_author = client.states.get(&guild_id).map_or(false, |kv| {
+        kv.value().queue.iter().take(idx).all(|kv| kv.key == author)
+    });
+    if !only_author {
+        require_dj(client, &ctx).await?;
+    }
+
+    let response = match client.skip_to(guild_id, idx).await? {
+        Some(count) => format!("Skipped **{}** tracks.", count),
+        None => format!("There is no track at index {} in the queue.", idx),
+    };
+    ctx.respond(response).await?;
     Ok(())
 }
 
@@ -359,30 +719,73 @@ async fn remove_all(client: &Client<'static>, ctx: commands::Context<'_>) -> Res
     require_in_voice_channel(client, &ctx).await?;
     let response = client
         .mutate_state(guild_id, |state| {
-            if let Some(count) = state.queue.clear_key(ctx.message.author.id) {
+            if let Some(count) = state.queue.clear_key(ctx.author().id) {
                 format!("Removed **{}** tracks from the queue.", count)
             } else {
                 "You currently do not have any tracks in the queue.".to_owned()
             }
         })
         .unwrap();
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
     Ok(())
 }
 
-async fn shuffle(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
+/// Clears every track from the queue except the currently playing one.
+async fn clear(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
     let guild_id = require_playing(client, &ctx)?;
+    if let Some(response) = vote(client, &ctx, guild_id, VoteAction::Clear).await? {
+        ctx.respond(response).await?;
+        return Ok(());
+    }
+    let count = client
+        .mutate_state(guild_id, |state| state.queue.remove_range(1..).len())
+        .unwrap_or_default();
+    ctx.respond(format!("Cleared **{}** tracks from the queue.", count))
+        .await?;
+    Ok(())
+}
+
+async fn shuffle(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    let all = match arguments.next() {
+        Some("all") => true,
+        Some(arg) => bail!(CommandError::InvalidArgument(format!(
+            "Unknown argument `{}`. Use `all` to shuffle everyone's tracks.",
+            arg
+        ))),
+        None => false,
+    };
+    commands::precondition::no_excess_arguments(arguments)?;
+
+    if all {
+        let response = match vote(client, &ctx, guild_id, VoteAction::Shuffle).await? {
+            Some(response) => response,
+            None => {
+                let count = client
+                    .mutate_state(guild_id, |state| state.queue.shuffle_all())
+                    .unwrap_or_default();
+                format!("Shuffled **{}** tracks in the queue.", count)
+            }
+        };
+        ctx.respond(response).await?;
+        return Ok(());
+    }
+
     require_in_voice_channel(client, &ctx).await?;
     let response = client
         .mutate_state(guild_id, |state| {
-            if let Some(count) = state.queue.shuffle(ctx.message.author.id) {
+            if let Some(count) = state.queue.shuffle(ctx.author().id) {
                 format!("Shuffled **{}** tracks in the queue.", count)
             } else {
                 "You currently do not have any tracks in the queue.".to_owned()
             }
         })
         .unwrap();
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
     Ok(())
 }
 
@@ -394,7 +797,477 @@ async fn forceskip(client: &Client<'static>, ctx: commands::Context<'_>) -> Resu
     } else {
         "There is nothing in the queue right now.".to_owned()
     };
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Toggles between the given repeat mode and not repeating.
+async fn toggle_repeat(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    mode: RepeatMode,
+) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    require_dj(client, &ctx).await?;
+    let mode = client
+        .mutate_state(guild_id, |state| {
+            state.repeat_mode = if state.repeat_mode == mode {
+                RepeatMode::Off
+            } else {
+                mode
+            };
+            state.repeat_mode
+        })
+        .unwrap();
+    let response = match mode {
+        RepeatMode::Off => ":arrow_forward: No longer repeating.",
+        RepeatMode::Track => ":repeat_one: Repeating the current track.",
+        RepeatMode::Queue => ":repeat: Looping the queue.",
+    };
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+async fn autoplay(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    require_dj(client, &ctx).await?;
+    let autoplay = client
+        .mutate_state(guild_id, |state| {
+            state.autoplay = !state.autoplay;
+            state.autoplay
+        })
+        .unwrap();
+    let response = if autoplay {
+        ":infinity: Related tracks will be played when the queue runs out."
+    } else {
+        "Autoplay has been disabled."
+    };
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Parses a timestamp argument. Falls back to the default if no argument is provided.
+fn parse_timestamp(arguments: &mut Arguments<'_>, default: Option<Timestamp>) -> Result<Timestamp> {
+    let timestamp = match arguments.next() {
+        Some(arg) => Timestamp::parse_as(arg)
+            .map_err(|err| CommandError::InvalidArgument(err.to_string()))?,
+        None => default.ok_or(CommandError::MissingArgument)?,
+    };
+    commands::precondition::no_excess_arguments(arguments)?;
+    Ok(timestamp)
+}
+
+/// Parses the amount of time to rewind or fast forward by.
+fn parse_offset(arguments: &mut Arguments<'_>) -> Result<Duration> {
+    let default = Timestamp::Absolute(DEFAULT_SEEK_OFFSET);
+    match parse_timestamp(arguments, Some(default))? {
+        Timestamp::Absolute(offset) => Ok(offset),
+        _ => bail!(CommandError::InvalidArgument(
+            "Provide an amount of time without a sign. (i.e. `30s` or `1:30`)".into()
+        )),
+    }
+}
+
+async fn seek(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let timestamp = parse_timestamp(arguments, None)?;
+    seek_to(client, ctx, timestamp).await
+}
+
+async fn rewind(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let offset = parse_offset(arguments)?;
+    seek_to(client, ctx, Timestamp::Backward(offset)).await
+}
+
+async fn fast_forward(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let offset = parse_offset(arguments)?;
+    seek_to(client, ctx, Timestamp::Forward(offset)).await
+}
+
+async fn replay(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
+    seek_to(client, ctx, Timestamp::Absolute(Duration::from_secs(0))).await
+}
+
+async fn seek_to(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    timestamp: Timestamp,
+) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    require_dj(client, &ctx).await?;
+    let track = client.currently_playing(guild_id).unwrap();
+    if track.info.is_stream {
+        bail!(CommandError::FailedPrecondition(
+            "Cannot seek in a live stream."
+        ));
+    }
+
+    let current = client
+        .position(guild_id)
+        .map(|(position, _)| position)
+        .unwrap_or_default();
+    let position = timestamp.resolve(current);
+    if position > track.info.length {
+        bail!(CommandError::InvalidArgument(format!(
+            "`{}` is past the end of the track ({}).",
+            format_duration(position),
+            format_duration(track.info.length)
+        )));
+    }
+
+    client.seek(guild_id, position).await?;
+    let emoji = if position < current {
+        ":rewind:"
+    } else {
+        ":fast_forward:"
+    };
+    ctx.respond(format!(
+        "{} Moved to `{}/{}` in `{}`.",
+        emoji,
+        format_duration(position),
+        format_duration(track.info.length),
+        track.info
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Shows or changes the audio filter. Accepts the name of a preset, `off` to remove it, or any
+/// number of `speed=`, `pitch=` or `rate=` settings to change the timescale.
+async fn filter(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let guild_id = require_in_guild(&ctx)?;
+    let args: Vec<&str> = arguments.collect();
+    let mut config = client.get_config(guild_id).await?;
+
+    let name = match args.as_slice() {
+        [] => {
+            let current = filters::display_name(config.get_filter());
+            let presets: Vec<String> = filters::PRESETS
+                .iter()
+                .map(|preset| format!("`{}`: {}", preset.name, preset.description))
+                .collect();
+            ctx.respond(format!(
+                "Current filter: `{}`\nAvailable presets:\n{}",
+                current.as_deref().unwrap_or("off"),
+                presets.join("\n")
+            ))
+            .await?;
+            return Ok(());
+        }
+        [name] if !name.contains('=') => *name,
+        settings => {
+            require_dj(client, &ctx).await?;
+            let filter = config.mut_filter();
+            for setting in settings {
+                filters::parse_timescale(setting, filter)?;
+            }
+            filter.clear_preset();
+            save_filter(client, guild_id, config).await?;
+            ctx.respond(":level_slider: Updated the timescale.").await?;
+            return Ok(());
+        }
+    };
+
+    require_dj(client, &ctx).await?;
+    let response = if name.eq_ignore_ascii_case("off") {
+        config.clear_filter();
+        ":level_slider: Removed the audio filter.".to_owned()
+    } else if let Some(preset) = filters::find_preset(name) {
+        config.set_filter(preset.into());
+        format!(":level_slider: Set the audio filter to `{}`.", preset.name)
+    } else {
+        let presets: Vec<&str> = filters::PRESETS.iter().map(|preset| preset.name).collect();
+        bail!(CommandError::InvalidArgument(format!(
+            "`{}` is not a known filter. Available filters: `{}`",
+            name,
+            presets.join("`, `")
+        )));
+    };
+    save_filter(client, guild_id, config).await?;
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Shows or customizes the equalizer. Accepts any number of `<band>=<gain>` pairs, or `reset`
+/// to flatten all of the bands.
+async fn equalizer(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let guild_id = require_in_guild(&ctx)?;
+    let args: Vec<&str> = arguments.collect();
+    let mut config = client.get_config(guild_id).await?;
+    let mut gains = filters::equalizer_gains(config.get_filter());
+
+    if args.is_empty() {
+        let bands: Vec<String> = gains
+            .iter()
+            .enumerate()
+            .map(|(band, gain)| format!("`{:>2}: {:+.2}`", band, gain))
+            .collect();
+        ctx.respond(format!("Equalizer bands:\n{}", bands.join(" ")))
+            .await?;
+        return Ok(());
+    }
+
+    require_dj(client, &ctx).await?;
+    let response = if args.len() == 1 && args[0].eq_ignore_ascii_case("reset") {
+        config.clear_filter();
+        ":level_slider: Reset the equalizer.".to_owned()
+    } else {
+        for arg in args.iter() {
+            let (band, gain) = filters::parse_band(arg)?;
+            gains[band] = gain;
+        }
+        let filter = config.mut_filter();
+        filter.clear_preset();
+        filter.set_equalizer(gains.to_vec());
+        format!(":level_slider: Updated **{}** equalizer bands.", args.len())
+    };
+    save_filter(client, guild_id, config).await?;
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+/// Saves a guild's audio filter and applies it to the player, if one is playing.
+async fn save_filter(
+    client: &Client<'static>,
+    guild_id: GuildId,
+    config: MusicConfig,
+) -> Result<()> {
+    let filter = config.get_filter().clone();
+    client.set_config(guild_id, config).await?;
+    if client.states.contains_key(&guild_id) {
+        client.set_filter(guild_id, &filter).await?;
+    }
+    Ok(())
+}
+
+/// Saves, loads, lists or deletes saved playlists. Playlists belong to the author, unless
+/// `--server` is passed before the name, in which case they belong to the server.
+async fn playlist(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    arguments: &mut Arguments<'_>,
+) -> Result<()> {
+    let guild_id = require_in_guild(&ctx)?;
+    let subcommand = arguments.next().ok_or(CommandError::MissingArgument)?;
+    let mut arguments = arguments.peekable();
+    let owner = if arguments.peek() == Some(&"--server") {
+        arguments.next();
+        PlaylistOwner::Guild(guild_id)
+    } else {
+        PlaylistOwner::User(ctx.author().id)
+    };
+    let name = arguments.collect::<Vec<_>>().join(" ");
+
+    match subcommand {
+        "save" => save_playlist(client, ctx, owner, name).await,
+        "load" => load_playlist(client, ctx, owner, name).await,
+        "list" => list_playlists(client, ctx, owner).await,
+        "delete" => delete_playlist(client, ctx, owner, name).await,
+        _ => bail!(CommandError::InvalidArgument(format!(
+            "Unknown subcommand `{}`. Try `save`, `load`, `list` or `delete`.",
+            subcommand
+        ))),
+    }
+}
+
+fn playlist_owner_name(owner: PlaylistOwner) -> &'static str {
+    match owner {
+        PlaylistOwner::User(_) => "your",
+        PlaylistOwner::Guild(_) => "the server's",
+    }
+}
+
+fn require_playlist_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!(CommandError::MissingArgument);
+    } else if name.len() > MAX_PLAYLIST_NAME_LENGTH {
+        bail!(CommandError::InvalidArgument(format!(
+            "Playlist names cannot be longer than {} characters.",
+            MAX_PLAYLIST_NAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Requires that the author can change the playlists of an owner. Only DJs can change the
+/// server's playlists.
+async fn require_playlist_owner(
+    client: &Client<'static>,
+    ctx: &commands::Context<'_>,
+    owner: PlaylistOwner,
+) -> Result<()> {
+    match owner {
+        PlaylistOwner::User(_) => Ok(()),
+        PlaylistOwner::Guild(_) => require_dj(client, ctx).await,
+    }
+}
+
+/// Saves the current queue, in play order, to a playlist. Replaces the tracks of an existing
+/// playlist with the same name.
+async fn save_playlist(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    owner: PlaylistOwner,
+    name: String,
+) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    require_playlist_name(&name)?;
+    require_playlist_owner(client, &ctx, owner).await?;
+
+    let tracks: Vec<PlaylistTrack> = client
+        .get_queue(guild_id, |queue| {
+            queue
+                .iter()
+                .take(MAX_PLAYLIST_LENGTH)
+                .enumerate()
+                .map(|(idx, item)| {
+                    let mut track = PlaylistTrack::from(item.value);
+                    track.position = idx as i32;
+                    track
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let count = tracks.len();
+
+    let mut txn = client.sql.begin().await?;
+    let (playlist_id,) = Playlist::upsert(owner, name.clone(), ctx.author().id, Utc::now())
+        .fetch_one(&mut txn)
+        .await?;
+    PlaylistTrack::clear(playlist_id).execute(&mut txn).await?;
+    for track in tracks {
+        track.insert(playlist_id).execute(&mut txn).await?;
+    }
+    txn.commit().await?;
+
+    ctx.respond(format!(
+        ":floppy_disk: Saved **{}** tracks to {} playlist `{}`.",
+        count,
+        playlist_owner_name(owner),
+        name
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Adds all of the tracks in a playlist to the end of the author's queue.
+async fn load_playlist(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    owner: PlaylistOwner,
+    name: String,
+) -> Result<()> {
+    let (guild_id, channel_id) = require_in_voice_channel(client, &ctx).await?;
+    require_playlist_name(&name)?;
+
+    let playlist = Playlist::fetch(owner, name.clone())
+        .fetch_optional(&client.sql)
+        .await?
+        .ok_or_else(|| {
+            CommandError::InvalidArgument(format!(
+                "There is no playlist named `{}` in {} playlists.",
+                name,
+                playlist_owner_name(owner)
+            ))
+        })?;
+    let tracks: Vec<Track> = PlaylistTrack::fetch(playlist.id)
+        .fetch_all(&client.sql)
+        .await?
+        .into_iter()
+        .map(|track| Track::from((ctx.author().clone(), track)))
+        .collect();
+
+    let response = if tracks.is_empty() {
+        format!(":bulb: Playlist `{}` is empty.", name)
+    } else {
+        let mut response = format!(
+            ":notes: Added **{}** tracks ({}) from playlist `{}` to the music queue.",
+            tracks.len(),
+            format_duration(tracks.iter().map(|t| t.info.length).sum()),
+            name
+        );
+        let rejected = enqueue(client, &ctx, guild_id, channel_id, tracks).await?;
+        response.push_str(&queue_limit_note(rejected));
+        response
+    };
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+async fn list_playlists(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    owner: PlaylistOwner,
+) -> Result<()> {
+    let playlists = Playlist::list(owner).fetch_all(&client.sql).await?;
+    let mut response = if playlists.is_empty() {
+        match owner {
+            PlaylistOwner::User(_) => "You have no saved playlists.".to_owned(),
+            PlaylistOwner::Guild(_) => "The server has no saved playlists.".to_owned(),
+        }
+    } else {
+        format!(":notes: Saved playlists ({}):", playlists.len())
+    };
+    for playlist in playlists {
+        let line = format!(
+            "\n`{}` - **{}** tracks ({})",
+            playlist.name,
+            playlist.track_count,
+            format_duration(Duration::from_millis(playlist.length as u64))
+        );
+        if response.len() + line.len() > MAX_MESSAGE_LENGTH {
+            break;
+        }
+        response.push_str(&line);
+    }
+    ctx.respond(response).await?;
+    Ok(())
+}
+
+async fn delete_playlist(
+    client: &Client<'static>,
+    ctx: commands::Context<'_>,
+    owner: PlaylistOwner,
+    name: String,
+) -> Result<()> {
+    require_playlist_name(&name)?;
+    require_playlist_owner(client, &ctx, owner).await?;
+    let result = Playlist::delete(owner, name.clone())
+        .execute(&client.sql)
+        .await?;
+    let response = if result.rows_affected() > 0 {
+        format!(
+            ":wastebasket: Deleted playlist `{}` from {} playlists.",
+            name,
+            playlist_owner_name(owner)
+        )
+    } else {
+        format!(
+            "There is no playlist named `{}` in {} playlists.",
+            name,
+            playlist_owner_name(owner)
+        )
+    };
+    ctx.respond(response).await?;
     Ok(())
 }
 
@@ -407,12 +1280,16 @@ async fn volume(
     let volume = arguments.parse_next_opt::<i64>();
     commands::precondition::no_excess_arguments(arguments)?;
     let response = if let Some(vol) = volume {
-        require_dj(client, &ctx).await?;
         if vol < 0 || vol > 150 {
             bail!(CommandError::InvalidArgument(
                 "Volume must be between 0 and 150.".into()
             ));
         }
+        let action = VoteAction::Volume(vol as u32);
+        if let Some(response) = vote(client, &ctx, guild_id, action).await? {
+            ctx.respond(response).await?;
+            return Ok(());
+        }
         get_player!(client, &guild_id).set_volume(vol as u32)?;
 
         // Update config
@@ -427,7 +1304,7 @@ async fn volume(
             get_player!(client, &guild_id).volume_ref()
         )
     };
-    ctx.respond().content(response)?.await?;
+    ctx.respond(response).await?;
     Ok(())
 }
 
@@ -442,6 +1319,59 @@ async fn queue(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<(
     Ok(())
 }
 
+/// Shows the lyrics of the currently playing track.
+async fn show_lyrics(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
+    let guild_id = require_playing(client, &ctx)?;
+    let track = client
+        .currently_playing(guild_id)
+        .ok_or(CommandError::FailedPrecondition(
+            "No music is currently playing.",
+        ))?;
+    let found = match lyrics::find_lyrics(client.lyrics.as_ref(), &track.info).await? {
+        Some(found) => found,
+        None => {
+            ctx.respond(format!(":bulb: No lyrics found for `{}`.", track.info))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let pages = lyrics::paginate(&found.text, lyrics::LYRICS_PAGE_LENGTH);
+    let page_count = pages.len();
+    let page = Arc::new(AtomicUsize::new(0));
+    let builder = LyricsUI {
+        title: format!("{} - {}", found.artist, found.title),
+        uri: track.info.uri.clone(),
+        pages,
+        page: page.clone(),
+    };
+    let ui = EmbedUI::create_with(client.clone(), ctx, builder).await?;
+    let (channel_id, message_id) = (ui.channel_id, ui.message_id);
+    client.mutate_state(guild_id, move |state| {
+        let ui = MessageUI::run(ui, Duration::from_secs(60));
+        state
+            .lyrics_ui
+            .replace(LyricsPager::new(message_id, page, page_count, ui));
+    });
+
+    if page_count > 1 {
+        for emoji in [lyrics::PREVIOUS_PAGE_EMOJI, lyrics::NEXT_PAGE_EMOJI].iter() {
+            let reaction = RequestReactionType::Unicode {
+                name: (*emoji).to_owned(),
+            };
+            if let Err(err) = client
+                .http_client
+                .create_reaction(channel_id, message_id, reaction)
+                .await
+            {
+                warn!("Failed to add reaction to lyrics: {}", err);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn now_playing(client: &Client<'static>, ctx: commands::Context<'_>) -> Result<()> {
     let guild_id = require_in_guild(&ctx)?;
     let ui = EmbedUI::<NowPlayingUI>::create(client.clone(), ctx).await?;
@@ -0,0 +1,130 @@
+use crate::{queue::QueuePolicy, track::Track};
+use hourai::models::id::UserId;
+use hourai::proto::guild_configs::MusicConfig;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// A queue policy that keeps any one user from dominating the queue, using the limits set in a
+/// guild's music config.
+#[derive(Clone, Debug, Default)]
+pub struct FairShare {
+    djs: HashSet<UserId>,
+    dj_slots: u64,
+    max_tracks: Option<usize>,
+    max_duration: Option<Duration>,
+    max_consecutive: Option<Duration>,
+}
+
+impl FairShare {
+    pub fn new(config: &MusicConfig) -> Self {
+        let mut policy = Self::default();
+        policy.configure(config);
+        policy
+    }
+
+    /// Updates the limits to match a guild's music config. Users already marked as DJs stay
+    /// marked.
+    pub fn configure(&mut self, config: &MusicConfig) {
+        self.dj_slots = config.get_dj_queue_slots().into();
+        self.max_tracks = Some(config.get_max_queued_tracks() as usize).filter(|max| *max > 0);
+        self.max_duration = Some(config.get_max_queued_duration())
+            .filter(|max| *max > 0)
+            .map(Duration::from_secs);
+        self.max_consecutive = Some(config.get_max_consecutive_duration())
+            .filter(|max| *max > 0)
+            .map(Duration::from_secs);
+    }
+
+    /// Marks whether a user should get a DJ's extra turns.
+    pub fn set_dj(&mut self, user_id: UserId, dj: bool) {
+        if dj {
+            self.djs.insert(user_id);
+        } else {
+            self.djs.remove(&user_id);
+        }
+    }
+
+    fn slots(&self, user_id: UserId) -> u64 {
+        if self.djs.contains(&user_id) {
+            std::cmp::max(self.dj_slots, 1)
+        } else {
+            1
+        }
+    }
+}
+
+impl QueuePolicy<UserId, Track> for FairShare {
+    fn admits(&self, _: UserId, queued: &VecDeque<Track>, track: &Track) -> bool {
+        if self.max_tracks.map_or(false, |max| queued.len() >= max) {
+            return false;
+        }
+        match self.max_duration {
+            Some(max) => queued
+                .iter()
+                .try_fold(track.info.length, |total, t| {
+                    total.checked_add(t.info.length)
+                })
+                .map_or(false, |total| total <= max),
+            None => true,
+        }
+    }
+
+    fn turn_length(&self, user_id: UserId) -> u64 {
+        match self.max_consecutive {
+            Some(max) => (max.as_millis() as u64).saturating_mul(self.slots(user_id)),
+            None => self.slots(user_id),
+        }
+    }
+
+    fn cost(&self, track: &Track) -> u64 {
+        match self.max_consecutive {
+            Some(_) => track.info.length.as_millis() as u64,
+            None => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::queue::MusicQueue;
+    use crate::track::TrackInfo;
+    use hourai::proto::cache::CachedUserProto;
+
+    fn track(uri: &str, secs: u64) -> Track {
+        Track {
+            requestor: CachedUserProto::new(),
+            info: TrackInfo {
+                title: None,
+                author: None,
+                uri: uri.to_owned(),
+                length: Duration::from_secs(secs),
+                is_stream: false,
+            },
+            track: Vec::new(),
+        }
+    }
+
+    fn uris(queue: &MusicQueue<UserId, Track, FairShare>) -> Vec<&str> {
+        queue
+            .iter()
+            .map(|item| item.value.info.uri.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_cleared_user_starts_new_turn() {
+        let mut config = MusicConfig::new();
+        config.set_max_consecutive_duration(300);
+        let mut queue = MusicQueue::with_policy(FairShare::new(&config));
+        queue.extend(UserId(1), vec![track("a", 120), track("b", 120)]);
+        assert_eq!(queue.pop().unwrap().value.info.uri, "a");
+        assert_eq!(queue.clear_key(UserId(1)), Some(1));
+
+        // Each turn lasts 300 seconds, so user 1 gets three tracks before user 2.
+        let tracks = ["c", "d", "e", "f"].iter().map(|uri| track(uri, 120));
+        queue.extend(UserId(1), tracks);
+        queue.extend(UserId(2), vec![track("g", 60)]);
+        assert_eq!(uris(&queue), vec!["c", "d", "e", "g", "f"]);
+    }
+}
@@ -0,0 +1,264 @@
+use hourai::commands::CommandError;
+use hourai::models::id::GuildId;
+use hourai::proto::guild_configs::AudioFilterConfig;
+use twilight_lavalink::model::{self, EqualizerBand, Opcode};
+
+/// The number of equalizer bands supported by Lavalink.
+pub const EQUALIZER_BANDS: usize = 15;
+const MIN_GAIN: f64 = -0.25;
+const MAX_GAIN: f64 = 1.0;
+const MIN_TIMESCALE: f64 = 0.5;
+const MAX_TIMESCALE: f64 = 2.0;
+const FLAT: [f64; EQUALIZER_BANDS] = [0.0; EQUALIZER_BANDS];
+
+/// Multipliers of the speed, pitch and rate of playback.
+pub struct Timescale {
+    pub speed: f64,
+    pub pitch: f64,
+    pub rate: f64,
+}
+
+/// A named combination of filters.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub equalizer: [f64; EQUALIZER_BANDS],
+    pub timescale: Option<Timescale>,
+    /// Whether vocals are filtered out, with the default karaoke settings.
+    pub karaoke: bool,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "bassboost",
+        description: "Boosts the low end.",
+        equalizer: [
+            0.25, 0.2, 0.15, 0.1, 0.05, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        ],
+        timescale: None,
+        karaoke: false,
+    },
+    Preset {
+        name: "treble",
+        description: "Boosts the high end for a bright sound.",
+        equalizer: [
+            -0.05, -0.05, 0.0, 0.0, 0.0, 0.0, 0.0, 0.05, 0.1, 0.15, 0.2, 0.25, 0.25, 0.2, 0.15,
+        ],
+        timescale: None,
+        karaoke: false,
+    },
+    Preset {
+        name: "muffled",
+        description: "Cuts the high end for a muffled, bass heavy sound.",
+        equalizer: [
+            0.15, 0.15, 0.1, 0.1, 0.05, 0.0, 0.0, -0.05, -0.1, -0.15, -0.2, -0.25, -0.25, -0.25,
+            -0.25,
+        ],
+        timescale: None,
+        karaoke: false,
+    },
+    Preset {
+        name: "nightcore",
+        description: "Speeds up and raises the pitch of tracks.",
+        equalizer: FLAT,
+        timescale: Some(Timescale {
+            speed: 1.25,
+            pitch: 1.25,
+            rate: 1.0,
+        }),
+        karaoke: false,
+    },
+    Preset {
+        name: "vaporwave",
+        description: "Slows down and lowers the pitch of tracks, with boosted bass.",
+        equalizer: [
+            0.3, 0.3, 0.2, 0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        ],
+        timescale: Some(Timescale {
+            speed: 0.85,
+            pitch: 0.8,
+            rate: 1.0,
+        }),
+        karaoke: false,
+    },
+    Preset {
+        name: "karaoke",
+        description: "Filters out the vocals of tracks.",
+        equalizer: FLAT,
+        timescale: None,
+        karaoke: true,
+    },
+];
+
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+impl From<&Preset> for AudioFilterConfig {
+    fn from(value: &Preset) -> Self {
+        let mut config = Self::new();
+        config.set_preset(value.name.to_owned());
+        config.set_equalizer(value.equalizer.to_vec());
+        if let Some(ref timescale) = value.timescale {
+            let config = config.mut_timescale();
+            config.set_speed(timescale.speed);
+            config.set_pitch(timescale.pitch);
+            config.set_rate(timescale.rate);
+        }
+        if value.karaoke {
+            config.mut_karaoke();
+        }
+        config
+    }
+}
+
+/// Gets the gain of every equalizer band in a filter, including the unset ones.
+pub fn equalizer_gains(config: &AudioFilterConfig) -> [f64; EQUALIZER_BANDS] {
+    let mut gains = [0.0; EQUALIZER_BANDS];
+    for (gain, value) in gains.iter_mut().zip(config.get_equalizer()) {
+        *gain = *value;
+    }
+    gains
+}
+
+/// Builds the filters to send to Lavalink. Every filter is included, so that filters from a
+/// previous config are reset.
+pub fn lavalink_filters(guild_id: GuildId, config: &AudioFilterConfig) -> model::Filters {
+    let equalizer = equalizer_gains(config)
+        .iter()
+        .enumerate()
+        .map(|(band, gain)| EqualizerBand::new(band as i64, *gain))
+        .collect();
+    let timescale = config.get_timescale();
+    let karaoke = if config.has_karaoke() {
+        let karaoke = config.get_karaoke();
+        Some(model::Karaoke {
+            level: karaoke.get_level(),
+            mono_level: karaoke.get_mono_level(),
+            filter_band: karaoke.get_filter_band(),
+            filter_width: karaoke.get_filter_width(),
+        })
+    } else {
+        None
+    };
+    model::Filters {
+        equalizer,
+        guild_id,
+        karaoke,
+        op: Opcode::Filters,
+        timescale: Some(model::Timescale {
+            speed: timescale.get_speed(),
+            pitch: timescale.get_pitch(),
+            rate: timescale.get_rate(),
+        }),
+    }
+}
+
+/// Gets a short, human readable name for a filter. Returns None if the filter does nothing.
+pub fn display_name(config: &AudioFilterConfig) -> Option<String> {
+    if config.has_preset() {
+        Some(config.get_preset().to_owned())
+    } else if config.has_timescale()
+        || config.has_karaoke()
+        || config.get_equalizer().iter().any(|gain| *gain != 0.0)
+    {
+        Some("custom".to_owned())
+    } else {
+        None
+    }
+}
+
+/// Parses a custom equalizer band setting of the form `<band>=<gain>`.
+pub fn parse_band(value: &str) -> Result<(usize, f64), CommandError> {
+    let err = || {
+        CommandError::InvalidArgument(format!(
+            "`{}` is not a valid band. Use `<band>=<gain>`, with a band from 0 to {} and a gain \
+             from {} to {}. (i.e. `0=0.25`)",
+            value,
+            EQUALIZER_BANDS - 1,
+            MIN_GAIN,
+            MAX_GAIN
+        ))
+    };
+    let mut parts = value.splitn(2, '=');
+    let band: usize = parts.next().and_then(|b| b.parse().ok()).ok_or_else(err)?;
+    let gain: f64 = parts.next().and_then(|g| g.parse().ok()).ok_or_else(err)?;
+    if band >= EQUALIZER_BANDS || !(MIN_GAIN..=MAX_GAIN).contains(&gain) {
+        return Err(err());
+    }
+    Ok((band, gain))
+}
+
+/// Parses a custom timescale setting of the form `<speed|pitch|rate>=<multiplier>` and applies
+/// it to the filter.
+pub fn parse_timescale(value: &str, config: &mut AudioFilterConfig) -> Result<(), CommandError> {
+    let err = || {
+        CommandError::InvalidArgument(format!(
+            "`{}` is not a valid setting. Use `speed=<value>`, `pitch=<value>` or `rate=<value>`, \
+             with a value from {} to {}. (i.e. `speed=1.25`)",
+            value, MIN_TIMESCALE, MAX_TIMESCALE
+        ))
+    };
+    let mut parts = value.splitn(2, '=');
+    let name = parts.next().unwrap_or_default().to_lowercase();
+    let multiplier: f64 = parts.next().and_then(|m| m.parse().ok()).ok_or_else(err)?;
+    if !(MIN_TIMESCALE..=MAX_TIMESCALE).contains(&multiplier) {
+        return Err(err());
+    }
+    match name.as_str() {
+        "speed" => config.mut_timescale().set_speed(multiplier),
+        "pitch" => config.mut_timescale().set_pitch(multiplier),
+        "rate" => config.mut_timescale().set_rate(multiplier),
+        _ => return Err(err()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_band() {
+        assert_eq!(parse_band("0=0.25").unwrap(), (0, 0.25));
+        assert_eq!(parse_band("14=-0.25").unwrap(), (14, -0.25));
+        for value in &["", "0", "0=", "=0.1", "15=0.1", "0=1.5", "0=-0.5", "a=b"] {
+            assert!(parse_band(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_timescale() {
+        let mut config = AudioFilterConfig::new();
+        parse_timescale("speed=1.25", &mut config).unwrap();
+        parse_timescale("PITCH=0.8", &mut config).unwrap();
+        assert_eq!(config.get_timescale().get_speed(), 1.25);
+        assert_eq!(config.get_timescale().get_pitch(), 0.8);
+        assert_eq!(config.get_timescale().get_rate(), 1.0);
+        for value in &["speed=", "=1.0", "tempo=1", "speed=0.1", "rate=3"] {
+            assert!(parse_timescale(value, &mut config).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_presets_set_filters() {
+        let nightcore = AudioFilterConfig::from(find_preset("nightcore").unwrap());
+        assert_eq!(nightcore.get_timescale().get_speed(), 1.25);
+        assert!(!nightcore.has_karaoke());
+        let karaoke = AudioFilterConfig::from(find_preset("Karaoke").unwrap());
+        assert!(karaoke.has_karaoke());
+        assert!(!karaoke.has_timescale());
+    }
+
+    #[test]
+    fn test_equalizer_gains_pads_missing_bands() {
+        let mut config = AudioFilterConfig::new();
+        config.set_equalizer(vec![0.1, 0.2]);
+        let gains = equalizer_gains(&config);
+        assert_eq!(gains[0], 0.1);
+        assert_eq!(gains[1], 0.2);
+        assert!(gains[2..].iter().all(|gain| *gain == 0.0));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{prelude::*, Client};
+use anyhow::Result;
+use hourai::models::id::GuildId;
+use std::time::Instant;
+
+/// Periodically checks every player for inactivity. Players are paused when everyone leaves
+/// their voice channel and resumed when someone comes back. Players that stay idle for longer
+/// than the guild's configured timeout are disconnected.
+pub async fn run_idle_timers(client: Client<'static>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let guild_ids: Vec<GuildId> = client.states.iter().map(|kv| *kv.key()).collect();
+        for guild_id in guild_ids {
+            if let Err(err) = check_idle(&client, guild_id).await {
+                error!(
+                    "Error while checking if guild {} is idle: {}",
+                    guild_id, err
+                );
+            }
+        }
+    }
+}
+
+async fn check_idle(client: &Client<'static>, guild_id: GuildId) -> Result<()> {
+    let empty = client.count_listeners(guild_id).await? == 0;
+    let paused = match client.position(guild_id) {
+        Some((_, paused)) => paused,
+        None => return Ok(()),
+    };
+
+    let paused_when_empty = client
+        .states
+        .get(&guild_id)
+        .map_or(false, |kv| kv.value().paused_when_empty);
+    let paused = if empty && !paused {
+        info!(
+            "Everyone left the voice channel in guild {}. Pausing.",
+            guild_id
+        );
+        set_pause(client, guild_id, true).await?;
+        true
+    } else if !empty && paused_when_empty {
+        info!(
+            "Listeners returned to the voice channel in guild {}. Resuming.",
+            guild_id
+        );
+        set_pause(client, guild_id, false).await?;
+        false
+    } else {
+        paused
+    };
+
+    let idle_since = client
+        .mutate_state(guild_id, |state| {
+            if paused {
+                *state.idle_since.get_or_insert_with(Instant::now)
+            } else {
+                state.idle_since = None;
+                Instant::now()
+            }
+        })
+        .unwrap_or_else(Instant::now);
+
+    let timeout = client.get_config(guild_id).await?.get_idle_timeout();
+    let timeout = Duration::from_secs(timeout.into());
+    if timeout.as_secs() > 0 && idle_since.elapsed() >= timeout {
+        disconnect(client, guild_id, timeout).await?;
+    }
+    Ok(())
+}
+
+async fn set_pause(client: &Client<'static>, guild_id: GuildId, paused: bool) -> Result<()> {
+    client
+        .lavalink
+        .player(guild_id)
+        .await?
+        .value()
+        .set_pause(paused)?;
+    client.mutate_state(guild_id, |state| {
+        state.paused_when_empty = paused;
+        state.refresh_ui();
+    });
+    Ok(())
+}
+
+/// Disconnects an idle player and announces it in the channel the last command was used in.
+async fn disconnect(client: &Client<'static>, guild_id: GuildId, timeout: Duration) -> Result<()> {
+    let channel_id = client
+        .states
+        .get(&guild_id)
+        .and_then(|kv| kv.value().text_channel_id);
+    info!("Disconnecting idle player in guild {}", guild_id);
+    client.disconnect(guild_id).await?;
+    if let Some(channel_id) = channel_id {
+        client
+            .http_client
+            .create_message(channel_id)
+            .content(format!(
+                ":wave: Left the voice channel after being idle for {}.",
+                format_duration(timeout)
+            ))?
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use crate::{prelude::*, track::TrackInfo, ui::MessageUI, Client};
+use anyhow::{bail, Result};
+use hourai::{
+    http::request::channel::reaction::RequestReactionType,
+    models::{
+        channel::{Reaction, ReactionType},
+        id::MessageId,
+    },
+};
+use http::{StatusCode, Uri};
+use hyper::{client::HttpConnector, Body, Client as HyperClient, Request};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The lyrics API used if none is configured.
+pub const DEFAULT_LYRICS_URI: &str = "http://api.lyrics.ovh";
+/// The most characters shown on one page of lyrics.
+pub const LYRICS_PAGE_LENGTH: usize = 2000;
+pub const PREVIOUS_PAGE_EMOJI: &str = "\u{25c0}\u{fe0f}";
+pub const NEXT_PAGE_EMOJI: &str = "\u{25b6}\u{fe0f}";
+
+/// Suffixes uploaders add to channel names that are not part of the artist's name.
+const AUTHOR_SUFFIXES: &[&str] = &[" - Topic", "VEVO", "Official"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lyrics {
+    pub artist: String,
+    pub title: String,
+    pub text: String,
+}
+
+/// A source of song lyrics.
+#[async_trait::async_trait]
+pub trait LyricsProvider: Send + Sync {
+    /// Looks up the lyrics of a song. Returns None if the provider does not have them.
+    async fn lookup(&self, artist: &str, title: &str) -> Result<Option<Lyrics>>;
+}
+
+/// Looks up lyrics from an HTTP API compatible with lyrics.ovh.
+pub struct HttpLyrics {
+    hyper: HyperClient<HttpConnector>,
+    base_uri: String,
+}
+
+impl HttpLyrics {
+    pub fn new(hyper: HyperClient<HttpConnector>, base_uri: impl Into<String>) -> Self {
+        Self {
+            hyper,
+            base_uri: base_uri.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LyricsProvider for HttpLyrics {
+    async fn lookup(&self, artist: &str, title: &str) -> Result<Option<Lyrics>> {
+        let uri: Uri = format!(
+            "{}/v1/{}/{}",
+            self.base_uri.trim_end_matches('/'),
+            encode_path_segment(artist),
+            encode_path_segment(title)
+        )
+        .parse()?;
+        let req = Request::get(uri).body(Body::empty())?;
+        let res = self.hyper.request(req).await?;
+        let status = res.status();
+        let response_bytes = hyper::body::to_bytes(res.into_body()).await?;
+        match status {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => parse_response(artist, title, &response_bytes),
+            status => bail!("Lyrics lookup failed with status {}", status),
+        }
+    }
+}
+
+/// Parses a response from a lyrics.ovh compatible API.
+fn parse_response(artist: &str, title: &str, body: &[u8]) -> Result<Option<Lyrics>> {
+    let response: serde_json::Value = serde_json::from_slice(body)?;
+    Ok(response
+        .get("lyrics")
+        .and_then(|lyrics| lyrics.as_str())
+        .map(|lyrics| lyrics.replace("\r\n", "\n").trim().to_owned())
+        .filter(|lyrics| !lyrics.is_empty())
+        .map(|text| Lyrics {
+            artist: artist.to_owned(),
+            title: title.to_owned(),
+            text,
+        }))
+}
+
+/// Finds the lyrics of a track. Track titles often include the artist's name, as in
+/// `Artist - Title`, which is tried before falling back to the track's author.
+pub async fn find_lyrics(
+    provider: &dyn LyricsProvider,
+    info: &TrackInfo,
+) -> Result<Option<Lyrics>> {
+    for (artist, title) in search_terms(info) {
+        if let Some(lyrics) = provider.lookup(&artist, &title).await? {
+            return Ok(Some(lyrics));
+        }
+    }
+    Ok(None)
+}
+
+/// Gets the artist and title pairs to look up the lyrics of a track with, in order of
+/// preference.
+fn search_terms(info: &TrackInfo) -> Vec<(String, String)> {
+    let title = match info.title.as_deref() {
+        Some(title) => strip_brackets(title),
+        None => return Vec::new(),
+    };
+    let mut terms = Vec::new();
+    if let Some((artist, song)) = title.split_once(" - ") {
+        terms.push((artist.trim().to_owned(), song.trim().to_owned()));
+    }
+    if let Some(author) = info.author.as_deref() {
+        let author = AUTHOR_SUFFIXES
+            .iter()
+            .fold(author, |author, suffix| {
+                author.strip_suffix(suffix).unwrap_or(author)
+            })
+            .trim();
+        if !author.is_empty() {
+            terms.push((author.to_owned(), title.trim().to_owned()));
+        }
+    }
+    terms
+}
+
+/// Removes bracketed annotations like `(Official Video)` or `[Lyrics]` from a title.
+fn strip_brackets(title: &str) -> String {
+    let mut depth = 0usize;
+    let stripped: String = title
+        .chars()
+        .filter(|c| match c {
+            '(' | '[' => {
+                depth += 1;
+                false
+            }
+            ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect();
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Percent-encodes a string for use as a single URI path segment.
+fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Splits lyrics into pages no longer than the given length. Pages break between verses where
+/// possible, then between lines.
+pub fn paginate(text: &str, max_length: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    for verse in text.split("\n\n") {
+        let separator = if page.is_empty() { "" } else { "\n\n" };
+        if page.len() + separator.len() + verse.len() <= max_length {
+            page.push_str(separator);
+            page.push_str(verse);
+            continue;
+        }
+        if !page.is_empty() {
+            pages.push(std::mem::take(&mut page));
+        }
+        for line in verse.lines() {
+            let separator = if page.is_empty() { "" } else { "\n" };
+            if page.len() + separator.len() + line.len() > max_length && !page.is_empty() {
+                pages.push(std::mem::take(&mut page));
+            }
+            if !page.is_empty() {
+                page.push('\n');
+            }
+            for c in line.chars() {
+                if page.len() + c.len_utf8() > max_length {
+                    pages.push(std::mem::take(&mut page));
+                }
+                page.push(c);
+            }
+        }
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// A running lyrics UI that can be paged through with reactions.
+pub struct LyricsPager {
+    pub message_id: MessageId,
+    page: Arc<AtomicUsize>,
+    pages: usize,
+    ui: MessageUI,
+}
+
+impl LyricsPager {
+    pub fn new(message_id: MessageId, page: Arc<AtomicUsize>, pages: usize, ui: MessageUI) -> Self {
+        Self {
+            message_id,
+            page,
+            pages,
+            ui,
+        }
+    }
+
+    /// Moves to the next or previous page, if there is one.
+    fn turn(&self, forward: bool) {
+        let current = self.page.load(Ordering::SeqCst);
+        let next = if forward {
+            std::cmp::min(current + 1, self.pages.saturating_sub(1))
+        } else {
+            current.saturating_sub(1)
+        };
+        if next != current {
+            self.page.store(next, Ordering::SeqCst);
+            self.ui.refresh();
+        }
+    }
+}
+
+/// Turns the page of a lyrics UI when someone reacts to it with an arrow.
+pub async fn on_reaction_add(client: &Client<'static>, reaction: &Reaction) -> Result<()> {
+    let (guild_id, name) = match (reaction.guild_id, &reaction.emoji) {
+        (Some(guild_id), ReactionType::Unicode { name }) => (guild_id, name),
+        _ => return Ok(()),
+    };
+    let forward = match name.as_str() {
+        PREVIOUS_PAGE_EMOJI => false,
+        NEXT_PAGE_EMOJI => true,
+        _ => return Ok(()),
+    };
+    if reaction.user_id == client.user_id {
+        return Ok(());
+    }
+
+    let turned = client
+        .states
+        .get(&guild_id)
+        .map_or(false, |kv| match &kv.value().lyrics_ui {
+            Some(pager) if pager.message_id == reaction.message_id => {
+                pager.turn(forward);
+                true
+            }
+            _ => false,
+        });
+    if turned {
+        // Remove the reaction so it can be used again.
+        let emoji = RequestReactionType::Unicode { name: name.clone() };
+        client
+            .http_client
+            .delete_reaction(
+                reaction.channel_id,
+                reaction.message_id,
+                emoji,
+                reaction.user_id,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../fixtures/lyrics.json");
+
+    /// Serves the same lyrics as the fixture for one song, and nothing for any other.
+    struct FixtureLyrics;
+
+    #[async_trait::async_trait]
+    impl LyricsProvider for FixtureLyrics {
+        async fn lookup(&self, artist: &str, title: &str) -> Result<Option<Lyrics>> {
+            if artist == "Jane Taylor" && title == "The Star" {
+                parse_response(artist, title, FIXTURE)
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    fn track_info(title: &str, author: &str) -> TrackInfo {
+        TrackInfo {
+            title: Some(title.to_owned()),
+            author: Some(author.to_owned()),
+            uri: "https://example.com".to_owned(),
+            length: Duration::from_secs(0),
+            is_stream: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let lyrics = parse_response("Jane Taylor", "The Star", FIXTURE)
+            .unwrap()
+            .unwrap();
+        assert!(lyrics.text.starts_with("Twinkle, twinkle, little star,"));
+        assert!(!lyrics.text.contains('\r'));
+        assert_eq!(
+            parse_response("a", "b", b"{\"lyrics\": \"\"}").unwrap(),
+            None
+        );
+        assert_eq!(parse_response("a", "b", b"{}").unwrap(), None);
+    }
+
+    #[test]
+    fn test_search_terms() {
+        let info = track_info("Jane Taylor - The Star (Official Video)", "Jane TaylorVEVO");
+        assert_eq!(
+            search_terms(&info),
+            vec![
+                ("Jane Taylor".to_owned(), "The Star".to_owned()),
+                (
+                    "Jane Taylor".to_owned(),
+                    "Jane Taylor - The Star".to_owned()
+                ),
+            ]
+        );
+        let info = track_info("The Star [Lyrics]", "Jane Taylor - Topic");
+        assert_eq!(
+            search_terms(&info),
+            vec![("Jane Taylor".to_owned(), "The Star".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("The Star"), "The%20Star");
+        assert_eq!(encode_path_segment("AC/DC"), "AC%2FDC");
+        assert_eq!(encode_path_segment("東方"), "%E6%9D%B1%E6%96%B9");
+    }
+
+    #[test]
+    fn test_paginate() {
+        assert_eq!(paginate("", 10), Vec::<String>::new());
+        assert_eq!(paginate("a\nb\n\nc\nd", 100), vec!["a\nb\n\nc\nd"]);
+        assert_eq!(
+            paginate("aaa\nbbb\n\nccc\nddd", 10),
+            vec!["aaa\nbbb", "ccc\nddd"]
+        );
+        assert_eq!(paginate("aaa\nbbb\nccc", 8), vec!["aaa\nbbb", "ccc"]);
+        assert_eq!(paginate("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[tokio::test]
+    async fn test_find_lyrics() {
+        let info = track_info("Jane Taylor - The Star (Audio)", "Somebody");
+        let lyrics = find_lyrics(&FixtureLyrics, &info).await.unwrap().unwrap();
+        assert_eq!(lyrics.artist, "Jane Taylor");
+        assert_eq!(lyrics.title, "The Star");
+
+        let pages = paginate(&lyrics.text, 200);
+        assert!(pages.len() > 1);
+        assert!(pages.iter().all(|page| page.len() <= 200));
+        assert_eq!(pages.join("\n\n"), lyrics.text);
+
+        let info = track_info("Something Else", "Somebody");
+        assert_eq!(find_lyrics(&FixtureLyrics, &info).await.unwrap(), None);
+    }
+}
@@ -1,12 +1,23 @@
+mod autoplay;
 mod commands;
+mod fair_share;
+mod filters;
+mod idle;
+mod lyrics;
+mod nodes;
+mod persistence;
 mod player;
 mod prelude;
 mod queue;
+mod search;
+mod slash;
 mod track;
 mod ui;
+mod votes;
 
 use crate::{
-    player::PlayerState,
+    fair_share::FairShare,
+    player::{PlayerState, RepeatMode},
     prelude::*,
     queue::MusicQueue,
     track::{Track, TrackInfo},
@@ -19,7 +30,7 @@ use hourai::{
     gateway::{cluster::*, Event, EventTypeFlags, Intents},
     init,
     models::id::*,
-    proto::guild_configs::MusicConfig,
+    proto::guild_configs::{AudioFilterConfig, MusicConfig},
 };
 use hourai_redis::*;
 use http::Uri;
@@ -31,12 +42,20 @@ use hyper::{
     service::Service,
     Body, Request,
 };
-use std::{convert::TryFrom, str::FromStr, collections::HashMap};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    str::FromStr,
+    time::Instant,
+};
 use twilight_command_parser::{CommandParserConfig, Parser};
 use twilight_lavalink::{model::*, Lavalink};
 
 const BOT_INTENTS: Intents = Intents::from_bits_truncate(
-    Intents::GUILDS.bits() | Intents::GUILD_MESSAGES.bits() | Intents::GUILD_VOICE_STATES.bits(),
+    Intents::GUILDS.bits()
+        | Intents::GUILD_MESSAGES.bits()
+        | Intents::GUILD_MESSAGE_REACTIONS.bits()
+        | Intents::GUILD_VOICE_STATES.bits(),
 );
 
 const BOT_EVENTS: EventTypeFlags = EventTypeFlags::from_bits_truncate(
@@ -45,7 +64,9 @@ const BOT_EVENTS: EventTypeFlags = EventTypeFlags::from_bits_truncate(
         | EventTypeFlags::CHANNEL_UPDATE.bits()
         | EventTypeFlags::GUILD_CREATE.bits()
         | EventTypeFlags::GUILD_DELETE.bits()
+        | EventTypeFlags::INTERACTION_CREATE.bits()
         | EventTypeFlags::MESSAGE_CREATE.bits()
+        | EventTypeFlags::REACTION_ADD.bits()
         | EventTypeFlags::READY.bits()
         | EventTypeFlags::VOICE_SERVER_UPDATE.bits()
         | EventTypeFlags::VOICE_STATE_UPDATE.bits(),
@@ -60,22 +81,41 @@ async fn main() {
         let mut parser = CommandParserConfig::new();
         parser.add_prefix(config.command_prefix.clone());
         parser.add_command("play", false);
+        parser.add_command("search", false);
         parser.add_command("pause", false);
         parser.add_command("stop", false);
         parser.add_command("shuffle", false);
         parser.add_command("skip", false);
         parser.add_command("forceskip", false);
         parser.add_command("remove", false);
+        parser.add_command("move", false);
+        parser.add_command("swap", false);
+        parser.add_command("skipto", false);
         parser.add_command("volume", false);
         parser.add_command("removeall", false);
+        parser.add_command("clear", false);
         parser.add_command("nowplaying", false);
         parser.add_command("np", false);
         parser.add_command("queue", false);
+        parser.add_command("repeat", false);
+        parser.add_command("loop", false);
+        parser.add_command("autoplay", false);
+        parser.add_command("seek", false);
+        parser.add_command("rewind", false);
+        parser.add_command("fastforward", false);
+        parser.add_command("ff", false);
+        parser.add_command("replay", false);
+        parser.add_command("filter", false);
+        parser.add_command("eq", false);
+        parser.add_command("playlist", false);
+        parser.add_command("lyrics", false);
         Parser::new(parser)
     };
 
     let http_client = init::http_client(&config);
     let current_user = http_client.current_user().await.unwrap();
+    let application = http_client.current_user_application().await.unwrap();
+    http_client.set_application_id(application.id);
     let gateway = init::cluster(&config, BOT_INTENTS)
         .shard_scheme(ShardScheme::Auto)
         .http_client(http_client.clone())
@@ -86,29 +126,61 @@ async fn main() {
     let shard_count = gateway.config().shard_config().shard()[1];
     let lavalink = Lavalink::new(current_user.id, shard_count);
     let redis = hourai_redis::init(&config).await;
+    let sql = hourai_sql::init(&config).await;
+    let hyper = HyperClient::new();
+    let lyrics_uri = config
+        .music
+        .lyrics_uri
+        .clone()
+        .unwrap_or_else(|| lyrics::DEFAULT_LYRICS_URI.to_owned());
     let client = Client {
         user_id: current_user.id,
         http_client,
         lavalink: lavalink.clone(),
         gateway: gateway.clone(),
         states: Arc::new(DashMap::new()),
-        hyper: HyperClient::new(),
+        pending_restores: Arc::new(DashMap::new()),
+        searches: Arc::new(DashMap::new()),
+        voice_events: Arc::new(DashMap::new()),
+        node_regions: Arc::new(DashMap::new()),
+        lyrics: Arc::new(lyrics::HttpLyrics::new(hyper.clone(), lyrics_uri)),
+        hyper,
         resolver: GaiResolver::new(),
         parser,
         redis,
+        sql,
     };
 
+    if let Err(err) = slash::register(&client).await {
+        error!("Failed to register application commands: {}", err);
+    }
+
     // Start the lavalink node connections.
     for node in config.music.nodes {
         tokio::spawn(client.clone().run_node(node));
     }
 
+    // Saved players are restored as their guilds become available, so they must be loaded first.
+    persistence::load_states(&client).await;
+
     info!("Starting gateway...");
     gateway.up().await;
     info!("Client started.");
 
+    tokio::spawn(persistence::run_persist_states(
+        client.clone(),
+        Duration::from_secs(5),
+    ));
+    tokio::spawn(idle::run_idle_timers(
+        client.clone(),
+        Duration::from_secs(5),
+    ));
+
     let mut events = gateway.some_events(BOT_EVENTS);
     while let Some((_, evt)) = events.next().await {
+        if let Err(err) = nodes::on_voice_event(&client, &evt).await {
+            error!("Error while selecting a Lavalink node: {}", err);
+        }
         if let Err(err) = lavalink.process(&evt).await {
             error!("Error while handling Lavalink event: {}", err);
         }
@@ -128,8 +200,16 @@ pub struct Client<'a> {
     pub gateway: Cluster,
     pub lavalink: twilight_lavalink::Lavalink,
     pub states: Arc<DashMap<GuildId, PlayerState>>,
+    /// Saved player states from a previous run that have yet to be restored.
+    pub pending_restores: Arc<DashMap<GuildId, persistence::PendingRestore>>,
+    pub searches: Arc<DashMap<MessageId, search::PendingSearch>>,
+    pub voice_events: Arc<DashMap<GuildId, nodes::VoiceEvents>>,
+    /// The configured region of each connected Lavalink node.
+    pub node_regions: Arc<DashMap<SocketAddr, String>>,
+    pub lyrics: Arc<dyn lyrics::LyricsProvider>,
     pub resolver: GaiResolver,
     pub redis: RedisPool,
+    pub sql: hourai_sql::SqlPool,
     pub parser: Parser<'a>,
 }
 
@@ -138,7 +218,7 @@ impl Client<'static> {
         &mut self,
         uri: &Uri,
         password: impl Into<String>,
-    ) -> Result<LavalinkEventStream> {
+    ) -> Result<(SocketAddr, LavalinkEventStream)> {
         let name = Name::from_str(uri.host().unwrap()).unwrap();
         let pass = password.into();
         for mut address in self.resolver.call(name).await? {
@@ -148,7 +228,7 @@ impl Client<'static> {
 
             debug!("Trying to connect to a Lavalink node at: {} ", address);
             match self.lavalink.add(address, pass.as_str()).await {
-                Ok((_, rx)) => return Ok(rx),
+                Ok((_, rx)) => return Ok((address, rx)),
                 Err(err) => debug!("Failed to connect to {}: {:?}", address, err),
             }
         }
@@ -161,8 +241,8 @@ impl Client<'static> {
         info!("Starting listener for node {}.", name.as_str());
         loop {
             let connect = self.connect_node(&uri, config.password.as_str());
-            let mut rx: LavalinkEventStream = match connect.await {
-                Ok(rx) => rx,
+            let (address, mut rx) = match connect.await {
+                Ok(connection) => connection,
                 Err(err) => {
                     error!("Error connecting to node {}: {:?}", name.as_str(), err);
                     debug!("Retrying connection to {} in 5 seconds.", name.as_str());
@@ -172,10 +252,15 @@ impl Client<'static> {
             };
 
             info!("Connected to node to {}.", name.as_str());
+            self.node_regions.insert(address, config.region.clone());
+            // Players left without a node while none were connected can move to this one.
+            nodes::migrate_players(&self).await;
             while let Some(event) = rx.next().await {
                 tokio::spawn(self.clone().handle_lavalink_event(event));
             }
             info!("Disconnected from node to ({}).", name.as_str());
+            self.node_regions.remove(&address);
+            nodes::on_node_lost(&self, address).await;
         }
     }
 
@@ -187,7 +272,15 @@ impl Client<'static> {
             Event::ChannelUpdate(_) => Ok(()),
             Event::ChannelDelete(_) => Ok(()),
             Event::MessageCreate(evt) => commands::on_message_create(self, evt.0).await,
-            Event::GuildCreate(_) => Ok(()),
+            Event::InteractionCreate(evt) => slash::on_interaction_create(self, evt.0).await,
+            Event::ReactionAdd(evt) => {
+                search::on_reaction_add(&self, &evt.0);
+                lyrics::on_reaction_add(&self, &evt.0).await
+            }
+            Event::GuildCreate(evt) => {
+                persistence::on_guild_create(&self, evt.id).await;
+                Ok(())
+            }
             Event::GuildDelete(evt) => {
                 if !evt.unavailable {
                     self.disconnect(evt.id).await
@@ -215,6 +308,11 @@ impl Client<'static> {
                 Ok(())
             }
             IncomingEvent::TrackEnd(evt) => self.on_track_end(evt).await,
+            IncomingEvent::PlayerUpdate(evt) => {
+                // The player's position is now up to date.
+                self.mutate_state(evt.guild_id, |state| state.seek = None);
+                Ok(())
+            }
             _ => Ok(()),
         };
 
@@ -234,9 +332,14 @@ impl Client<'static> {
             evt.track
         );
         match evt.reason.as_str() {
-            "FINISHED" => {
-                self.play_next(evt.guild_id).await?;
-            }
+            "FINISHED" => match self.currently_playing(evt.guild_id) {
+                Some(track) if self.repeat_mode(evt.guild_id) == RepeatMode::Track => {
+                    self.play(evt.guild_id, &track).await?;
+                }
+                _ => {
+                    self.play_next(evt.guild_id).await?;
+                }
+            },
             "LOAD_FAILED" => {
                 self.play_next(evt.guild_id).await?;
             }
@@ -264,7 +367,7 @@ impl Client<'static> {
     /// Gets some information about a guild's player queue.
     pub fn get_queue<F, R>(&self, guild_id: GuildId, f: F) -> Option<R>
     where
-        F: Fn(&MusicQueue<UserId, Track>) -> R,
+        F: Fn(&MusicQueue<UserId, Track, FairShare>) -> R,
     {
         self.states.get(&guild_id).map(|kv| f(&kv.value().queue))
     }
@@ -277,6 +380,22 @@ impl Client<'static> {
             .and_then(|kv| kv.value().currently_playing().map(|cp| cp.1))
     }
 
+    /// Gets the repeat mode of a guild's player. If not playing, returns RepeatMode::Off.
+    pub fn repeat_mode(&self, guild_id: GuildId) -> RepeatMode {
+        self.states
+            .get(&guild_id)
+            .map(|kv| kv.value().repeat_mode)
+            .unwrap_or(RepeatMode::Off)
+    }
+
+    /// Gets the playback position of a guild's player and whether it is paused.
+    /// If not playing, returns None.
+    pub fn position(&self, guild_id: GuildId) -> Option<(Duration, bool)> {
+        let state = self.states.get(&guild_id)?;
+        let player = self.lavalink.players().get(&guild_id)?;
+        Some(state.value().position(player.value()))
+    }
+
     /// Gets which voice channel the bot is currently connected to in
     /// a guild.
     pub fn get_channel(&self, guild_id: GuildId) -> Option<ChannelId> {
@@ -286,7 +405,7 @@ impl Client<'static> {
             .and_then(|kv| kv.value().channel_id())
     }
 
-    /// Counts the number of users in the same voice channel as the bot.
+    /// Counts the number of users, other than the bot, in the same voice channel as the bot.
     /// If not in a voice channel, returns 0.
     pub async fn count_listeners(&self, guild_id: GuildId) -> Result<usize> {
         Ok(if let Some(channel_id) = self.get_channel(guild_id) {
@@ -294,16 +413,27 @@ impl Client<'static> {
             let states: HashMap<u64, u64> = hourai_redis::CachedVoiceState::get_channels(guild_id)
                 .query_async(&mut redis)
                 .await?;
-            states.into_iter().filter(|(_, v)| *v == channel_id.0).count()
+            states
+                .into_iter()
+                .filter(|(k, v)| *v == channel_id.0 && *k != self.user_id.0)
+                .count()
         } else {
             0
         })
     }
 
     pub async fn get_node(&self, guild_id: GuildId) -> Result<twilight_lavalink::Node> {
-        Ok(match self.lavalink.players().get(&guild_id) {
-            Some(kv) => kv.value().node().clone(),
-            None => self.lavalink.best().await?,
+        let node = self
+            .lavalink
+            .players()
+            .get(&guild_id)
+            .map(|kv| kv.value().node().clone());
+        Ok(match node {
+            Some(node) => node,
+            None => {
+                let endpoint = nodes::voice_endpoint(self, guild_id);
+                nodes::select_node(self, endpoint.as_deref()).await?
+            }
         })
     }
 
@@ -326,38 +456,97 @@ impl Client<'static> {
 
     async fn play(&self, guild_id: GuildId, track: &Track) -> Result<()> {
         self.lavalink.player(guild_id).await?.value().play(track)?;
+        self.set_position(guild_id, Duration::from_secs(0));
+        Ok(())
+    }
+
+    /// Moves the currently playing track to a given position.
+    pub async fn seek(&self, guild_id: GuildId, position: Duration) -> Result<()> {
+        self.lavalink
+            .player(guild_id)
+            .await?
+            .value()
+            .seek(position)?;
+        self.set_position(guild_id, position);
+        Ok(())
+    }
+
+    /// Applies an audio filter to a guild's player.
+    pub async fn set_filter(&self, guild_id: GuildId, filter: &AudioFilterConfig) -> Result<()> {
+        self.lavalink
+            .player(guild_id)
+            .await?
+            .value()
+            .set_filters(filter)?;
+        self.mutate_state(guild_id, |state| {
+            state.filter = filters::display_name(filter);
+            state.refresh_ui();
+        });
         Ok(())
     }
 
+    fn set_position(&self, guild_id: GuildId, position: Duration) {
+        self.mutate_state(guild_id, |state| {
+            state.seek = Some((position, Instant::now()));
+            state.refresh_ui();
+        });
+    }
+
     pub async fn start_playing(&self, guild_id: GuildId) -> Result<()> {
+        self.start_playing_from(guild_id, Duration::from_secs(0))
+            .await
+    }
+
+    /// Starts playing the current track from a given position.
+    pub async fn start_playing_from(&self, guild_id: GuildId, position: Duration) -> Result<()> {
         if let Some(track) = self.currently_playing(guild_id) {
             let config = self.get_config(guild_id).await?;
-            let kv = self.lavalink.player(guild_id).await?;
-            let player = kv.value();
             let volume = if config.has_volume() {
                 config.get_volume()
             } else {
                 50
             };
-            player.set_volume(volume)?;
-            player.play(&track)?;
+            {
+                let kv = self.lavalink.player(guild_id).await?;
+                let player = kv.value();
+                player.set_volume(volume)?;
+                player.play_from(&track, position)?;
+            }
+            // Must be done seperately to avoid a deadlock.
+            self.set_filter(guild_id, config.get_filter()).await?;
+            self.set_position(guild_id, position);
         }
         Ok(())
     }
 
-    /// Plays the next item in the queue.
+    /// Plays the next item in the queue. If the queue runs out and autoplay is enabled, a track
+    /// related to the previous one is queued.
     /// Panics if a player does not exist.
     pub async fn play_next(&self, guild_id: GuildId) -> Result<Option<TrackInfo>> {
-        let prev = {
+        let (prev, autoplay) = {
             if let Some(mut kv) = self.states.get_mut(&guild_id) {
                 let state = kv.value_mut();
-                state.skip_votes.clear();
-                state.queue.pop().map(|kv| kv.value.info)
+                let prev = state.advance();
+                let autoplay = match prev {
+                    Some(ref prev) if state.autoplay && !state.is_playing() => {
+                        Some((prev.clone(), state.history.clone()))
+                    }
+                    _ => None,
+                };
+                (prev.map(|(_, track)| track.info), autoplay)
             } else {
                 return Ok(None);
             }
         };
         // Must be done seperately to avoid a deadlock.
+        if let Some(((user_id, track), history)) = autoplay {
+            if let Err(err) = self
+                .queue_related_track(guild_id, user_id, &track, &history)
+                .await
+            {
+                error!("Failed to autoplay in guild {}: {}", guild_id, err);
+            }
+        }
         if let Some(track) = self.currently_playing(guild_id) {
             self.play(guild_id, &track).await?;
         } else {
@@ -366,6 +555,38 @@ impl Client<'static> {
         Ok(prev)
     }
 
+    /// Skips to the track at an index in the queue and starts playing it. Returns the number of
+    /// tracks skipped, or None if there is no track at the index.
+    pub async fn skip_to(&self, guild_id: GuildId, idx: usize) -> Result<Option<usize>> {
+        let skipped = self
+            .mutate_state(guild_id, |state| state.skip_to(idx))
+            .flatten();
+        if skipped.is_some() {
+            if let Some(track) = self.currently_playing(guild_id) {
+                self.play(guild_id, &track).await?;
+            }
+        }
+        Ok(skipped)
+    }
+
+    async fn queue_related_track(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        previous: &Track,
+        history: &VecDeque<String>,
+    ) -> Result<()> {
+        let node = self.get_node(guild_id).await?;
+        match autoplay::find_related_track(self, &node, previous, history).await? {
+            Some(track) => {
+                info!("Autoplaying in guild {}: {}", guild_id, track.info);
+                self.mutate_state(guild_id, |state| state.queue.push(user_id, track));
+            }
+            None => info!("No related tracks found to autoplay in guild {}", guild_id),
+        }
+        Ok(())
+    }
+
     pub async fn connect(&self, guild_id: GuildId, channel_id: ChannelId) -> Result<()> {
         let shard_id = self.gateway.shard_id(guild_id);
         self.gateway
@@ -407,6 +628,12 @@ impl Client<'static> {
 
         self.lavalink.players().destroy(guild_id)?;
         self.states.remove(&guild_id);
+        self.pending_restores.remove(&guild_id);
+        self.voice_events.remove(&guild_id);
+        let mut redis = self.redis.clone();
+        CachedPlayerState::delete(guild_id)
+            .query_async::<RedisPool, ()>(&mut redis)
+            .await?;
         info!("Destroyed player and removed state for guild {}", guild_id);
         Ok(())
     }
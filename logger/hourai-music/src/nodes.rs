@@ -0,0 +1,165 @@
+use crate::{prelude::*, Client};
+use anyhow::{bail, Result};
+use hourai::{gateway::Event, models::id::GuildId};
+
+/// The latest voice events for the bot in a guild. Replaying them to Lavalink connects the
+/// guild's player to the voice channel from a different node.
+#[derive(Default)]
+pub struct VoiceEvents {
+    state: Option<Event>,
+    server: Option<Event>,
+}
+
+/// Records the bot's voice events. When the bot joins a voice server without a player, one is
+/// created on the best node for the voice server's region before Lavalink creates one on any
+/// node.
+pub async fn on_voice_event(client: &Client<'static>, event: &Event) -> Result<()> {
+    match event {
+        Event::VoiceStateUpdate(evt) if evt.0.user_id == client.user_id => {
+            if let Some(guild_id) = evt.0.guild_id {
+                client.voice_events.entry(guild_id).or_default().state = Some(event.clone());
+            }
+        }
+        Event::VoiceServerUpdate(evt) => {
+            if let Some(guild_id) = evt.guild_id {
+                client.voice_events.entry(guild_id).or_default().server = Some(event.clone());
+                if client.lavalink.players().get(&guild_id).is_none() {
+                    let node = select_node(client, evt.endpoint.as_deref()).await?;
+                    client.lavalink.players().get_or_insert(guild_id, node);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Gets the endpoint of the voice server the bot is connected to in a guild.
+pub fn voice_endpoint(client: &Client<'static>, guild_id: GuildId) -> Option<String> {
+    let kv = client.voice_events.get(&guild_id)?;
+    match &kv.value().server {
+        Some(Event::VoiceServerUpdate(evt)) => evt.endpoint.clone(),
+        _ => None,
+    }
+}
+
+/// Selects the connected node with the lowest penalty, as computed from the stats it reports.
+/// If the endpoint of a voice server is provided, nodes configured with a region the endpoint
+/// starts with are preferred.
+pub async fn select_node(client: &Client<'static>, endpoint: Option<&str>) -> Result<Node> {
+    let nodes: Vec<Node> = client
+        .lavalink
+        .nodes()
+        .iter()
+        .map(|kv| kv.value().clone())
+        .collect();
+    let mut best: Option<((bool, i32), Node)> = None;
+    for node in nodes {
+        let in_region = match (endpoint, client.node_regions.get(&node.config().address)) {
+            (Some(endpoint), Some(region)) => {
+                !region.value().is_empty() && endpoint.starts_with(region.value().as_str())
+            }
+            _ => false,
+        };
+        let rank = (!in_region, node.penalty().await);
+        if best
+            .as_ref()
+            .map_or(true, |(best_rank, _)| rank < *best_rank)
+        {
+            best = Some((rank, node));
+        }
+    }
+    match best {
+        Some((_, node)) => Ok(node),
+        None => bail!("No Lavalink nodes are available."),
+    }
+}
+
+/// Removes a lost node and moves all of its players to the other nodes.
+pub async fn on_node_lost(client: &Client<'static>, address: SocketAddr) {
+    client.lavalink.nodes().remove(&address);
+    for mut kv in client.states.iter_mut() {
+        let on_node = client
+            .lavalink
+            .players()
+            .get(kv.key())
+            .map_or(false, |player| {
+                player.value().node().config().address == address
+            });
+        if on_node {
+            kv.value_mut().orphaned = true;
+        }
+    }
+    migrate_players(client).await;
+}
+
+/// Moves every player whose node was lost to the best connected node. The players resume from
+/// where they were before the node was lost. Players that cannot be moved yet stay orphaned
+/// until the next time a node connects.
+pub async fn migrate_players(client: &Client<'static>) {
+    let guild_ids: Vec<GuildId> = client
+        .states
+        .iter()
+        .filter(|kv| kv.value().orphaned)
+        .map(|kv| *kv.key())
+        .collect();
+    for guild_id in guild_ids {
+        match migrate_player(client, guild_id).await {
+            Ok(()) => {
+                client.mutate_state(guild_id, |state| state.orphaned = false);
+            }
+            Err(err) => error!(
+                "Failed to move music player in guild {} to a new node: {}",
+                guild_id, err
+            ),
+        }
+    }
+}
+
+async fn migrate_player(client: &Client<'static>, guild_id: GuildId) -> Result<()> {
+    let (position, paused) = client.position(guild_id).unwrap_or_default();
+    let endpoint = voice_endpoint(client, guild_id);
+    let node = select_node(client, endpoint.as_deref()).await?;
+    let address = node.config().address;
+
+    if let Err(err) = client.lavalink.players().destroy(guild_id) {
+        debug!(
+            "Failed to destroy music player in guild {} on lost node: {}",
+            guild_id, err
+        );
+    }
+    client.lavalink.players().get_or_insert(guild_id, node);
+    let events: Vec<Event> = client
+        .voice_events
+        .get(&guild_id)
+        .map(|kv| {
+            let events = kv.value();
+            events
+                .state
+                .iter()
+                .chain(events.server.iter())
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    for event in events.iter() {
+        client.lavalink.process(event).await?;
+    }
+
+    client.start_playing_from(guild_id, position).await?;
+    if paused {
+        client
+            .lavalink
+            .player(guild_id)
+            .await?
+            .value()
+            .set_pause(true)?;
+    }
+    info!(
+        "Moved music player in guild {} to node {} at {}",
+        guild_id,
+        address,
+        format_duration(position)
+    );
+    Ok(())
+}
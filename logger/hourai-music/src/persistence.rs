@@ -0,0 +1,211 @@
+use crate::{player::PlayerState, prelude::*, queue::MusicQueue, track::Track, Client};
+use anyhow::Result;
+use hourai::models::id::*;
+use hourai::proto::cache::*;
+use hourai_redis::{CachedPlayerState, RedisPool};
+use std::collections::HashMap;
+
+/// The number of times restoring a saved player is attempted before it is discarded.
+const MAX_RESTORE_ATTEMPTS: u8 = 5;
+
+/// A saved player state that has yet to be restored. Until it is, it is kept in Redis with the
+/// rest of the saved players.
+pub struct PendingRestore {
+    proto: CachedPlayerStateProto,
+    /// Whether the guild has been received from its shard. Voice channels can only be joined
+    /// after it has.
+    available: bool,
+    restoring: bool,
+    attempts: u8,
+}
+
+/// Loads the player states saved by a previous run of the service. Each is restored once its
+/// guild becomes available. Must be called before the gateway is started.
+pub async fn load_states(client: &Client<'static>) {
+    let mut redis = client.redis.clone();
+    loop {
+        match CachedPlayerState::fetch_all(&mut redis).await {
+            Ok(states) => {
+                info!("Loaded {} saved music players", states.len());
+                for proto in states {
+                    let pending = PendingRestore {
+                        proto,
+                        available: false,
+                        restoring: false,
+                        attempts: 0,
+                    };
+                    let guild_id = GuildId(pending.proto.get_guild_id());
+                    client.pending_restores.insert(guild_id, pending);
+                }
+                return;
+            }
+            Err(err) => {
+                error!("Error while loading saved music players: {}", err);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Periodically retries restoring saved players in available guilds and saves the current player
+/// states to Redis.
+pub async fn run_persist_states(client: Client<'static>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let guild_ids: Vec<GuildId> = client
+            .pending_restores
+            .iter()
+            .filter(|kv| kv.value().available)
+            .map(|kv| *kv.key())
+            .collect();
+        for guild_id in guild_ids {
+            restore_pending(&client, guild_id).await;
+        }
+        if let Err(err) = save_states(&client).await {
+            error!("Error while saving music players: {}", err);
+        }
+    }
+}
+
+/// Restores the saved player for a guild, if there is one, once the guild is received from its
+/// shard.
+pub async fn on_guild_create(client: &Client<'static>, guild_id: GuildId) {
+    match client.pending_restores.get_mut(&guild_id) {
+        Some(mut kv) => kv.value_mut().available = true,
+        None => return,
+    }
+    restore_pending(client, guild_id).await;
+}
+
+async fn restore_pending(client: &Client<'static>, guild_id: GuildId) {
+    if client.lavalink.best().await.is_err() {
+        debug!("No Lavalink nodes available. Delaying restoring music players.");
+        return;
+    }
+    let proto = match client.pending_restores.get_mut(&guild_id) {
+        Some(mut kv) if kv.value().available && !kv.value().restoring => {
+            kv.value_mut().restoring = true;
+            kv.value().proto.clone()
+        }
+        _ => return,
+    };
+
+    let err = match restore_state(client, proto).await {
+        Ok(()) => {
+            client.pending_restores.remove(&guild_id);
+            return;
+        }
+        Err(err) => err,
+    };
+    // Leave nothing half restored. The saved state is kept for the next attempt.
+    client.states.remove(&guild_id);
+    let attempts = match client.pending_restores.get_mut(&guild_id) {
+        Some(mut kv) => {
+            let pending = kv.value_mut();
+            pending.restoring = false;
+            pending.attempts += 1;
+            pending.attempts
+        }
+        None => return,
+    };
+    if attempts < MAX_RESTORE_ATTEMPTS {
+        error!(
+            "Failed to restore music player in guild {} (attempt {}): {}",
+            guild_id, attempts, err
+        );
+    } else {
+        error!(
+            "Failed to restore music player in guild {}, discarding it: {}",
+            guild_id, err
+        );
+        client.pending_restores.remove(&guild_id);
+    }
+}
+
+/// Reconnects to the saved voice channel and resumes playing from the saved position.
+async fn restore_state(client: &Client<'static>, mut proto: CachedPlayerStateProto) -> Result<()> {
+    let guild_id = GuildId(proto.get_guild_id());
+    let channel_id = ChannelId(proto.get_channel_id());
+    if client.states.contains_key(&guild_id) {
+        // A new player was started before this one could be restored.
+        return Ok(());
+    }
+    let mut queue = MusicQueue::new();
+    for mut user_queue in proto.take_queue().into_iter() {
+        let tracks = user_queue.take_tracks().into_iter().map(Track::from);
+        queue.extend(UserId(user_queue.get_user_id()), tracks);
+    }
+    if queue.peek().is_none() {
+        return Ok(());
+    }
+    // The limits are only applied after restoring so that no saved tracks are dropped.
+    let config = client.get_config(guild_id).await?;
+    queue.policy_mut().configure(&config);
+
+    let mut state = PlayerState::new(queue);
+    state.repeat_mode = proto.get_repeat_mode().into();
+    state.autoplay = proto.get_autoplay();
+    client.states.insert(guild_id, state);
+    client.connect(guild_id, channel_id).await?;
+    let position = Duration::from_millis(proto.get_position());
+    client.start_playing_from(guild_id, position).await?;
+    if proto.get_paused() {
+        client
+            .lavalink
+            .player(guild_id)
+            .await?
+            .value()
+            .set_pause(true)?;
+    }
+    info!(
+        "Restored music player in guild {} at {}",
+        guild_id,
+        format_duration(position)
+    );
+    Ok(())
+}
+
+async fn save_states(client: &Client<'static>) -> Result<()> {
+    let mut states: HashMap<u64, CachedPlayerStateProto> = client
+        .states
+        .iter()
+        .filter_map(|kv| snapshot(client, *kv.key(), kv.value()))
+        .map(|state| (state.get_guild_id(), state))
+        .collect();
+    for kv in client.pending_restores.iter() {
+        states
+            .entry(kv.key().0)
+            .or_insert_with(|| kv.value().proto.clone());
+    }
+    let mut redis = client.redis.clone();
+    CachedPlayerState::save_all(states.into_iter().map(|(_, state)| state))
+        .query_async::<RedisPool, ()>(&mut redis)
+        .await?;
+    Ok(())
+}
+
+fn snapshot(
+    client: &Client<'static>,
+    guild_id: GuildId,
+    state: &PlayerState,
+) -> Option<CachedPlayerStateProto> {
+    let kv = client.lavalink.players().get(&guild_id)?;
+    let player = kv.value();
+    let (position, paused) = state.position(player);
+    let mut proto = CachedPlayerStateProto::new();
+    proto.set_guild_id(guild_id.0);
+    proto.set_channel_id(player.channel_id()?.0);
+    proto.set_position(position.as_millis() as u64);
+    proto.set_paused(paused);
+    proto.set_repeat_mode(state.repeat_mode.into());
+    proto.set_autoplay(state.autoplay);
+    for (user_id, tracks) in state.queue.iter_keys() {
+        let mut user_queue = CachedUserQueueProto::new();
+        user_queue.set_user_id(user_id.0);
+        for track in tracks {
+            user_queue.mut_tracks().push(CachedTrackProto::from(track));
+        }
+        proto.mut_queue().push(user_queue);
+    }
+    Some(proto)
+}
@@ -1,17 +1,73 @@
-use crate::{queue::MusicQueue, track::*, ui::MessageUI};
+use crate::{
+    fair_share::FairShare, filters, lyrics::LyricsPager, queue::MusicQueue, track::*,
+    ui::MessageUI, votes::Votes,
+};
 use anyhow::Result;
-use hourai::models::id::UserId;
-use std::collections::HashSet;
+use hourai::models::id::{ChannelId, UserId};
+use hourai::proto::cache::CachedPlayerStateProto_RepeatMode;
+use hourai::proto::guild_configs::AudioFilterConfig;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 use twilight_lavalink::model::*;
 
+/// The number of recently played tracks to avoid when autoplaying.
+const MAX_HISTORY: usize = 25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepeatMode {
+    Off,
+    /// Repeats the currently playing track until it is skipped.
+    Track,
+    /// Re-enqueues each track after it finishes playing.
+    Queue,
+}
+
 pub struct PlayerState {
-    pub skip_votes: HashSet<UserId>,
-    pub queue: MusicQueue<UserId, Track>,
+    pub votes: Votes,
+    pub queue: MusicQueue<UserId, Track, FairShare>,
+    pub repeat_mode: RepeatMode,
+    pub autoplay: bool,
+    /// The URIs of the most recently played tracks, newest first.
+    pub history: VecDeque<String>,
+    /// The position last started or seeked to, and when. Used until Lavalink reports the
+    /// player's new position.
+    pub seek: Option<(Duration, Instant)>,
+    /// The name of the active audio filter, if any.
+    pub filter: Option<String>,
+    /// The text channel the last music command was used in.
+    pub text_channel_id: Option<ChannelId>,
+    /// When the player last became idle, if it currently is.
+    pub idle_since: Option<Instant>,
+    /// Whether the player was paused because everyone left the voice channel.
+    pub paused_when_empty: bool,
+    /// Whether the player's Lavalink node was lost and the player has yet to be moved to
+    /// another node.
+    pub orphaned: bool,
     pub now_playing_ui: Option<MessageUI>,
     pub queue_ui: Option<MessageUI>,
+    pub lyrics_ui: Option<LyricsPager>,
 }
 
 impl PlayerState {
+    pub fn new(queue: MusicQueue<UserId, Track, FairShare>) -> Self {
+        Self {
+            votes: Votes::default(),
+            queue,
+            repeat_mode: RepeatMode::Off,
+            autoplay: false,
+            history: VecDeque::new(),
+            seek: None,
+            filter: None,
+            text_channel_id: None,
+            idle_since: None,
+            paused_when_empty: false,
+            orphaned: false,
+            now_playing_ui: None,
+            queue_ui: None,
+            lyrics_ui: None,
+        }
+    }
+
     pub fn currently_playing(&self) -> Option<(UserId, Track)> {
         self.queue.peek().map(|item| (item.key, item.value.clone()))
     }
@@ -19,6 +75,77 @@ impl PlayerState {
     pub fn is_playing(&self) -> bool {
         self.queue.peek().is_some()
     }
+
+    /// Gets the playback position and whether the player is paused. Seeks are reflected
+    /// immediately instead of waiting for Lavalink's next player update.
+    pub fn position(&self, player: &twilight_lavalink::player::Player) -> (Duration, bool) {
+        let paused = player.paused();
+        let position = match self.seek {
+            Some((position, time)) if !paused => position + time.elapsed(),
+            Some((position, _)) => position,
+            None => Duration::from_millis(std::cmp::max(player.position(), 0) as u64),
+        };
+        (position, paused)
+    }
+
+    /// Updates any running UIs immediately instead of waiting for their next update.
+    pub fn refresh_ui(&self) {
+        for ui in self.now_playing_ui.iter().chain(self.queue_ui.iter()) {
+            ui.refresh();
+        }
+    }
+
+    /// Pops the currently playing track off of the queue. If looping the queue, the track is
+    /// re-enqueued at the end of its requestor's queue.
+    pub fn advance(&mut self) -> Option<(UserId, Track)> {
+        let item = self.queue.pop()?;
+        self.votes.clear_all();
+        self.finish(item.key, &item.value);
+        Some((item.key, item.value))
+    }
+
+    /// Pops every track before the given index off of the queue, including the currently
+    /// playing one, as if each had finished playing. Returns the number of tracks skipped, or
+    /// None if there is no track at the index.
+    pub fn skip_to(&mut self, idx: usize) -> Option<usize> {
+        let skipped = self.queue.skip_to(idx)?;
+        self.votes.clear_all();
+        let count = skipped.len();
+        for item in skipped {
+            self.finish(item.key, &item.value);
+        }
+        Some(count)
+    }
+
+    fn finish(&mut self, user_id: UserId, track: &Track) {
+        self.history.push_front(track.info.uri.clone());
+        self.history.truncate(MAX_HISTORY);
+        // The track was admitted when it was first queued, so looping it is never rejected by
+        // the queue limits.
+        if self.repeat_mode == RepeatMode::Queue {
+            self.queue.push_unchecked(user_id, track.clone());
+        }
+    }
+}
+
+impl From<CachedPlayerStateProto_RepeatMode> for RepeatMode {
+    fn from(value: CachedPlayerStateProto_RepeatMode) -> Self {
+        match value {
+            CachedPlayerStateProto_RepeatMode::OFF => Self::Off,
+            CachedPlayerStateProto_RepeatMode::TRACK => Self::Track,
+            CachedPlayerStateProto_RepeatMode::QUEUE => Self::Queue,
+        }
+    }
+}
+
+impl From<RepeatMode> for CachedPlayerStateProto_RepeatMode {
+    fn from(value: RepeatMode) -> Self {
+        match value {
+            RepeatMode::Off => Self::OFF,
+            RepeatMode::Track => Self::TRACK,
+            RepeatMode::Queue => Self::QUEUE,
+        }
+    }
 }
 
 pub trait PlayerExt {
@@ -30,12 +157,30 @@ pub trait PlayerExt {
         Ok(())
     }
 
+    fn play_from(&self, track: &Track, position: Duration) -> Result<()> {
+        let player = self.as_player();
+        player.send(track.play_from(player.guild_id(), position))?;
+        Ok(())
+    }
+
+    fn seek(&self, position: Duration) -> Result<()> {
+        let player = self.as_player();
+        player.send(Seek::from((player.guild_id(), position.as_millis() as i64)))?;
+        Ok(())
+    }
+
     fn set_pause(&self, paused: bool) -> Result<()> {
         let player = self.as_player();
         player.send(Pause::from((player.guild_id(), paused)))?;
         Ok(())
     }
 
+    fn set_filters(&self, filter: &AudioFilterConfig) -> Result<()> {
+        let player = self.as_player();
+        player.send(filters::lavalink_filters(player.guild_id(), filter))?;
+        Ok(())
+    }
+
     fn set_volume(&self, mut volume: u32) -> Result<()> {
         if volume > 150 {
             volume = 150;
@@ -1,5 +1,6 @@
 use rand::seq::SliceRandom;
-use std::collections::VecDeque;
+use std::collections::{vec_deque, VecDeque};
+use std::ops::RangeBounds;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct QueueItem<K, V> {
@@ -7,6 +8,33 @@ pub struct QueueItem<K, V> {
     pub value: V,
 }
 
+/// Decides how a `MusicQueue` shares its turns between keys, and which values each key may
+/// enqueue. Each time the queue comes around to a key, the key is served values until the cost of
+/// the served values reaches the key's turn length.
+pub trait QueuePolicy<K, V> {
+    /// Checks if a value may be added to a key's queue, given the values already in it.
+    fn admits(&self, _key: K, _queued: &VecDeque<V>, _value: &V) -> bool {
+        true
+    }
+
+    /// Gets how much a key is served each turn.
+    fn turn_length(&self, _key: K) -> u64 {
+        1
+    }
+
+    /// Gets how much of a turn serving a value uses.
+    fn cost(&self, _value: &V) -> u64 {
+        1
+    }
+}
+
+/// The default queue policy. Every key is served one value per turn, and there are no limits
+/// on what may be queued.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RoundRobin;
+
+impl<K, V> QueuePolicy<K, V> for RoundRobin {}
+
 /// A FIFO, round-robin key based queue.  The input is be a pair of key, value pairs.
 /// If a queue has n keys and k values, most operations on this data structure runs in O(n) time.
 ///
@@ -14,41 +42,85 @@ pub struct QueueItem<K, V> {
 ///   Input: (a, 1), (a, 2), (b, 1), (c, 1), (a, 3), (b, 2)
 ///   Output: (a, 1), (b, 1), (c, 1), (a, 2), (b, 2), (a, 3)
 ///
+/// How the turns are shared between keys can be changed with a `QueuePolicy`. The default policy
+/// serves one value per key per turn, as in the example above.
+///
 /// This structure is thread-safe and does not implement Send or Sync. Wrapping it in a RwLock is
 /// highly suggested.
 #[derive(Clone)]
-pub struct MusicQueue<K, V>(VecDeque<(K, VecDeque<V>)>);
+pub struct MusicQueue<K, V, P = RoundRobin>(VecDeque<(K, VecDeque<V>)>, P, Option<(K, u64)>);
 
-impl<K, V> MusicQueue<K, V>
+impl<K, V, P> MusicQueue<K, V, P>
 where
     K: Copy + Eq,
+    P: QueuePolicy<K, V>,
 {
-    pub fn new() -> Self {
-        Self(VecDeque::new())
+    pub fn new() -> Self
+    where
+        P: Default,
+    {
+        Self::with_policy(P::default())
     }
 
-    pub fn push(&mut self, key: K, value: V) {
-        if let Some(kv) = self.0.iter_mut().find(|kv| kv.0 == key) {
-            kv.1.push_back(value);
-        } else {
-            self.0.push_back((key, VecDeque::from(vec![value])));
-        }
+    pub fn with_policy(policy: P) -> Self {
+        Self(VecDeque::new(), policy, None)
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.1
+    }
+
+    /// Gets a mutable reference to the queue's policy. Changes to the policy do not affect the
+    /// values already in the queue.
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.1
+    }
+
+    /// Adds a value to the end of a key's queue. Returns false if the queue's policy does not
+    /// admit the value.
+    pub fn push(&mut self, key: K, value: V) -> bool {
+        self.extend(key, std::iter::once(value)) == 0
+    }
+
+    /// Adds a value to the end of a key's queue without checking the queue's policy. Used to
+    /// re-enqueue values that were already admitted once, like when looping the queue.
+    pub fn push_unchecked(&mut self, key: K, value: V) {
+        let idx = self.key_index(key);
+        self.0[idx].1.push_back(value);
     }
 
     /// Appends a full list of values to the end of a key's queue.  If a key's queue does not
-    /// exist, one will be created for it.
+    /// exist, one will be created for it. Values not admitted by the queue's policy are skipped.
     ///
     /// If there are n keys already in the queue and m values are being added to the queue, this
     /// is a O(n + m) operation.
-    pub fn extend(&mut self, key: K, values: impl IntoIterator<Item = V>) {
-        let values: VecDeque<V> = values.into_iter().collect();
-        if let Some(kv) = self.0.iter_mut().find(|kv| kv.0 == key) {
-            kv.1.reserve(values.len());
-            for value in values {
-                kv.1.push_back(value);
+    ///
+    /// Returns the number of values that were not admitted.
+    pub fn extend(&mut self, key: K, values: impl IntoIterator<Item = V>) -> usize {
+        let idx = self.key_index(key);
+        let bucket = &mut self.0[idx].1;
+        let mut rejected = 0;
+        for value in values {
+            if self.1.admits(key, bucket, &value) {
+                bucket.push_back(value);
+            } else {
+                rejected += 1;
+            }
+        }
+        if bucket.is_empty() {
+            self.0.remove(idx);
+        }
+        rejected
+    }
+
+    /// Gets the index of a key's queue, creating an empty one at the end if it does not exist.
+    fn key_index(&mut self, key: K) -> usize {
+        match self.0.iter().position(|kv| kv.0 == key) {
+            Some(idx) => idx,
+            None => {
+                self.0.push_back((key, VecDeque::new()));
+                self.0.len() - 1
             }
-        } else {
-            self.0.push_back((key, values));
         }
     }
 
@@ -72,15 +144,39 @@ where
             (peek.0, value, peek.1.len())
         };
 
-        if size > 0 {
+        let served = self.served(key).saturating_add(self.1.cost(&value));
+        if size == 0 {
+            self.0.pop_front();
+            self.2 = None;
+        } else if served >= self.1.turn_length(key) {
             self.0.rotate_left(1);
+            self.2 = None;
         } else {
-            self.0.pop_front();
+            self.2 = Some((key, served));
         }
 
         Some(QueueItem { key, value })
     }
 
+    /// Ends the current turn if its key is no longer at the front of the queue, like after all of
+    /// its values are removed. Otherwise, a key removed and later re-added would resume the stale
+    /// turn once it reached the front again.
+    fn settle_turn(&mut self) {
+        let front = self.0.front().map(|kv| kv.0);
+        if matches!(self.2, Some((key, _)) if Some(key) != front) {
+            self.2 = None;
+        }
+    }
+
+    /// Gets how much of its current turn a key has used. Only the key at the front of the queue
+    /// can be partway through its turn.
+    fn served(&self, key: K) -> u64 {
+        match self.2 {
+            Some((turn_key, served)) if turn_key == key => served,
+            _ => 0,
+        }
+    }
+
     /// Gets the total number of items in the queue. If there are n keys and k values in the queue
     /// for a given key, this is a O(n) operation.
     pub fn len(&self) -> usize {
@@ -106,6 +202,23 @@ where
         })
     }
 
+    /// Shuffles the items for every key, leaving the item at the front of the queue in place.
+    /// Each key keeps its place in the rotation. If there are k values in the queue, this is a
+    /// O(k) operation.
+    ///
+    /// Returns the number of items shuffled in the queue.
+    pub fn shuffle_all(&mut self) -> usize {
+        let mut rng = rand::thread_rng();
+        let mut count = 0;
+        for (idx, kv) in self.0.iter_mut().enumerate() {
+            let values = kv.1.make_contiguous();
+            let values = if idx == 0 { &mut values[1..] } else { values };
+            values.shuffle(&mut rng);
+            count += values.len();
+        }
+        count
+    }
+
     /// Gets the item at the nth index in the queue, if found.
     /// Runs in O(n) time if n items are in the queue.
     pub fn get(&mut self, idx: usize) -> Option<QueueItem<K, &V>> {
@@ -120,10 +233,83 @@ where
         let retval = bucket.1.remove(b).unwrap();
         if bucket.1.len() == 0 {
             self.0.remove(k);
+            self.settle_turn();
         }
         Some(retval)
     }
 
+    /// Removes all of the items with indices within a range and returns them in queue order.
+    /// Runs in O(n log n) time if n items are in the queue.
+    pub fn remove_range(&mut self, range: impl RangeBounds<usize>) -> Vec<QueueItem<K, V>> {
+        let mut indices: Vec<(usize, usize, usize)> = self
+            .index_iter()
+            .enumerate()
+            .filter(|(idx, _)| range.contains(idx))
+            .map(|(idx, (k, b))| (k, b, idx))
+            .collect();
+        // Remove from the back of each bucket first so the remaining bucket indices stay valid.
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+        let mut removed: Vec<(usize, QueueItem<K, V>)> = indices
+            .into_iter()
+            .map(|(k, b, idx)| {
+                let bucket = self.0.get_mut(k).unwrap();
+                let value = bucket.1.remove(b).unwrap();
+                (
+                    idx,
+                    QueueItem {
+                        key: bucket.0,
+                        value,
+                    },
+                )
+            })
+            .collect();
+        self.0.retain(|kv| !kv.1.is_empty());
+        self.settle_turn();
+        removed.sort_unstable_by_key(|kv| kv.0);
+        removed.into_iter().map(|kv| kv.1).collect()
+    }
+
+    /// Moves the item at one index to another. Items can only be moved within the queue of
+    /// their own key, so the items at both indices must have the same key. Returns false if
+    /// they do not or if either index is out of bounds, in which case the queue is unchanged.
+    /// Runs in O(n) time if n items are in the queue.
+    pub fn move_item(&mut self, from: usize, to: usize) -> bool {
+        match self.same_key_indices(from, to) {
+            Some((k, from, to)) => {
+                let bucket = &mut self.0[k].1;
+                let value = bucket.remove(from).unwrap();
+                bucket.insert(to, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Swaps the items at two indices. Like `move_item`, the items at both indices must have
+    /// the same key. Returns false if they do not or if either index is out of bounds, in which
+    /// case the queue is unchanged. Runs in O(n) time if n items are in the queue.
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        match self.same_key_indices(a, b) {
+            Some((k, a, b)) => {
+                self.0[k].1.swap(a, b);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pops items until the item at the given index is at the front of the queue, and returns
+    /// the popped items in order. Returns None if the index is out of bounds, in which case the
+    /// queue is unchanged. Runs in O(n) time if n items are skipped.
+    pub fn skip_to(&mut self, idx: usize) -> Option<Vec<QueueItem<K, V>>> {
+        if idx >= self.len() {
+            return None;
+        }
+        let skipped = (0..idx).filter_map(|_| self.pop()).collect();
+        self.settle_turn();
+        Some(skipped)
+    }
+
     /// Clears all of the items with a given key in the queue.
     /// If there are n keys in the queue, this is a O(n) operation.
     ///
@@ -131,6 +317,7 @@ where
     pub fn clear_key(&mut self, key: K) -> Option<usize> {
         self.count(key).map(|count| {
             self.0.retain(|r| r.0 != key);
+            self.settle_turn();
             count
         })
     }
@@ -138,68 +325,84 @@ where
     /// Clears all of the items within a given the queue.
     /// If there are n keys in the queue, this is a O(1) operation.
     pub fn clear(&mut self) {
-        self.0.clear()
+        self.0.clear();
+        self.2 = None;
     }
 
     pub fn contains_key(&mut self, key: K) -> bool {
         self.0.iter().find(|kv| kv.0 == key).is_some()
     }
 
-    fn index_iter<'a>(&'a self) -> MusicQueueIndexer<'a, K, V> {
+    /// Iterates over each key's values, in the order the keys will be served. Extending an
+    /// empty queue with each key's values in this order recreates the queue.
+    pub fn iter_keys(&self) -> impl Iterator<Item = (K, vec_deque::Iter<'_, V>)> {
+        self.0.iter().map(|kv| (kv.0, kv.1.iter()))
+    }
+
+    /// Finds the key index and bucket indices of two items if they have the same key.
+    fn same_key_indices(&self, a: usize, b: usize) -> Option<(usize, usize, usize)> {
+        let (key_a, bucket_a) = self.index_iter().nth(a)?;
+        let (key_b, bucket_b) = self.index_iter().nth(b)?;
+        if key_a == key_b {
+            Some((key_a, bucket_a, bucket_b))
+        } else {
+            None
+        }
+    }
+
+    fn index_iter<'a>(&'a self) -> MusicQueueIndexer<'a, K, V, P> {
         MusicQueueIndexer {
             queue: self,
-            key_idx: 0,
-            bucket_idx: 0,
-            max_bucket_idx: self.0.iter().map(|kv| kv.1.len()).max().unwrap_or(0),
+            keys: (0..self.0.len()).collect(),
+            bucket_idxs: vec![0; self.0.len()],
+            served: self.0.front().map(|kv| self.served(kv.0)).unwrap_or(0),
         }
     }
 
-    pub fn iter<'a>(&'a self) -> MusicQueueIterator<'a, K, V> {
+    pub fn iter<'a>(&'a self) -> MusicQueueIterator<'a, K, V, P> {
         MusicQueueIterator {
             indexer: self.index_iter(),
         }
     }
 }
 
-struct MusicQueueIndexer<'a, K, V> {
-    queue: &'a MusicQueue<K, V>,
-    key_idx: usize,
-    bucket_idx: usize,
-    max_bucket_idx: usize,
+/// Iterates over the indices of the items in the queue by simulating popping every item off of
+/// it.
+struct MusicQueueIndexer<'a, K, V, P> {
+    queue: &'a MusicQueue<K, V, P>,
+    /// The indices of the keys with items left, in the order they will be served.
+    keys: VecDeque<usize>,
+    /// The index of the next item in each key's queue.
+    bucket_idxs: Vec<usize>,
+    /// How much of its turn the key at the front has used.
+    served: u64,
 }
 
-pub struct MusicQueueIterator<'a, K, V> {
-    indexer: MusicQueueIndexer<'a, K, V>,
+pub struct MusicQueueIterator<'a, K, V, P> {
+    indexer: MusicQueueIndexer<'a, K, V, P>,
 }
 
-impl<'a, K: Copy, V> Iterator for MusicQueueIndexer<'a, K, V> {
+impl<'a, K: Copy, V, P: QueuePolicy<K, V>> Iterator for MusicQueueIndexer<'a, K, V, P> {
     type Item = (usize, usize);
     fn next(&mut self) -> Option<Self::Item> {
-        if self.bucket_idx > self.max_bucket_idx {
-            return None;
-        }
-        loop {
-            let item = self
-                .queue
-                .0
-                .get(self.key_idx)
-                .and_then(|kv| kv.1.get(self.bucket_idx));
-            let retval = item.map(|_| (self.key_idx, self.bucket_idx));
-            self.key_idx += 1;
-            if self.key_idx >= self.queue.0.len() {
-                self.key_idx = 0;
-                self.bucket_idx += 1;
-            }
-            if retval.is_some() {
-                return retval;
-            } else if self.bucket_idx > self.max_bucket_idx {
-                return None;
-            }
+        let key_idx = *self.keys.front()?;
+        let (key, bucket) = self.queue.0.get(key_idx).unwrap();
+        let bucket_idx = self.bucket_idxs[key_idx];
+        self.bucket_idxs[key_idx] += 1;
+        let cost = self.queue.1.cost(bucket.get(bucket_idx).unwrap());
+        self.served = self.served.saturating_add(cost);
+        if bucket_idx + 1 >= bucket.len() {
+            self.keys.pop_front();
+            self.served = 0;
+        } else if self.served >= self.queue.1.turn_length(*key) {
+            self.keys.rotate_left(1);
+            self.served = 0;
         }
+        Some((key_idx, bucket_idx))
     }
 }
 
-impl<'a, K: Copy, V> Iterator for MusicQueueIterator<'a, K, V> {
+impl<'a, K: Copy, V, P: QueuePolicy<K, V>> Iterator for MusicQueueIterator<'a, K, V, P> {
     type Item = QueueItem<K, &'a V>;
     fn next(&mut self) -> Option<Self::Item> {
         let (k, b) = self.indexer.next()?;
@@ -213,7 +416,8 @@ impl<'a, K: Copy, V> Iterator for MusicQueueIterator<'a, K, V> {
 
 #[cfg(test)]
 mod test {
-    use super::{MusicQueue, QueueItem};
+    use super::{MusicQueue, QueueItem, QueuePolicy};
+    use std::collections::VecDeque;
 
     #[test]
     fn test_queue_push() {
@@ -397,6 +601,262 @@ mod test {
         assert_eq!(queue.count(5), None);
     }
 
+    fn make_queue() -> MusicQueue<u64, u64> {
+        let mut queue: MusicQueue<u64, u64> = MusicQueue::new();
+        queue.extend(20, vec![20, 40, 60]);
+        queue.extend(10, vec![10, 15]);
+        queue.extend(5, vec![30]);
+        queue
+    }
+
+    fn items<P: QueuePolicy<u64, u64>>(queue: &MusicQueue<u64, u64, P>) -> Vec<(u64, u64)> {
+        queue.iter().map(|kv| (kv.key, *kv.value)).collect()
+    }
+
+    /// Pops every item off of the queue, checking that the popped order matches the iteration
+    /// order.
+    fn assert_round_robin<P: QueuePolicy<u64, u64>>(mut queue: MusicQueue<u64, u64, P>) {
+        let expected = items(&queue);
+        let mut popped = Vec::new();
+        while let Some(item) = queue.pop() {
+            popped.push((item.key, item.value));
+        }
+        assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn test_queue_move_item() {
+        let mut queue = make_queue();
+        assert_eq!(
+            items(&queue),
+            vec![(20, 20), (10, 10), (5, 30), (20, 40), (10, 15), (20, 60)]
+        );
+        assert!(queue.move_item(5, 0));
+        assert_eq!(
+            items(&queue),
+            vec![(20, 60), (10, 10), (5, 30), (20, 20), (10, 15), (20, 40)]
+        );
+        assert!(queue.move_item(0, 3));
+        assert_eq!(
+            items(&queue),
+            vec![(20, 20), (10, 10), (5, 30), (20, 60), (10, 15), (20, 40)]
+        );
+        assert_eq!(queue.len(), 6);
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_move_item_other_key() {
+        let mut queue = make_queue();
+        let before = items(&queue);
+        assert!(!queue.move_item(1, 3));
+        assert!(!queue.move_item(1, 100));
+        assert!(!queue.move_item(100, 1));
+        assert_eq!(items(&queue), before);
+    }
+
+    #[test]
+    fn test_queue_swap() {
+        let mut queue = make_queue();
+        assert!(queue.swap(1, 4));
+        assert_eq!(
+            items(&queue),
+            vec![(20, 20), (10, 15), (5, 30), (20, 40), (10, 10), (20, 60)]
+        );
+        assert!(!queue.swap(0, 1));
+        assert!(!queue.swap(0, 100));
+        assert_eq!(queue.len(), 6);
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_shuffle_all() {
+        let mut queue = make_queue();
+        assert_eq!(queue.shuffle_all(), 5);
+        let shuffled = items(&queue);
+        assert_eq!(shuffled[0], (20, 20));
+        let keys: Vec<u64> = shuffled.iter().map(|item| item.0).collect();
+        assert_eq!(keys, vec![20, 10, 5, 20, 10, 20]);
+        let mut sorted = shuffled.clone();
+        sorted.sort_unstable();
+        assert_eq!(
+            sorted,
+            vec![(5, 30), (10, 10), (10, 15), (20, 20), (20, 40), (20, 60)]
+        );
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_skip_to() {
+        let mut queue = make_queue();
+        assert_eq!(queue.skip_to(100), None);
+        assert_eq!(queue.len(), 6);
+        assert_eq!(
+            queue.skip_to(3),
+            Some(vec![
+                QueueItem { key: 20, value: 20 },
+                QueueItem { key: 10, value: 10 },
+                QueueItem { key: 5, value: 30 },
+            ])
+        );
+        assert_eq!(items(&queue), vec![(20, 40), (10, 15), (20, 60)]);
+        assert_eq!(queue.skip_to(0), Some(vec![]));
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_remove_range() {
+        let mut queue = make_queue();
+        assert_eq!(
+            queue.remove_range(1..=3),
+            vec![
+                QueueItem { key: 10, value: 10 },
+                QueueItem { key: 5, value: 30 },
+                QueueItem { key: 20, value: 40 },
+            ]
+        );
+        assert_eq!(items(&queue), vec![(20, 20), (10, 15), (20, 60)]);
+        assert_eq!(queue.count(5), None);
+        assert_eq!(queue.remove_range(10..), vec![]);
+        assert_eq!(queue.len(), 3);
+        assert_round_robin(queue);
+    }
+
+    /// Gives key 10 two items per turn, and limits every key to three queued items.
+    #[derive(Default)]
+    struct SlotsPolicy;
+
+    impl QueuePolicy<u64, u64> for SlotsPolicy {
+        fn admits(&self, _: u64, queued: &VecDeque<u64>, _: &u64) -> bool {
+            queued.len() < 3
+        }
+
+        fn turn_length(&self, key: u64) -> u64 {
+            if key == 10 {
+                2
+            } else {
+                1
+            }
+        }
+    }
+
+    /// Serves each key until the values served in its turn add up to at least 10.
+    #[derive(Default)]
+    struct CostPolicy;
+
+    impl QueuePolicy<u64, u64> for CostPolicy {
+        fn turn_length(&self, _: u64) -> u64 {
+            10
+        }
+
+        fn cost(&self, value: &u64) -> u64 {
+            *value
+        }
+    }
+
+    #[test]
+    fn test_queue_policy_admits() {
+        let mut queue: MusicQueue<u64, u64, SlotsPolicy> = MusicQueue::new();
+        assert_eq!(queue.extend(20, vec![1, 2, 3, 4, 5]), 2);
+        assert_eq!(queue.count(20), Some(3));
+        assert!(!queue.push(20, 6));
+        assert!(queue.push(10, 7));
+        assert_eq!(queue.extend(30, vec![]), 0);
+        assert!(!queue.contains_key(30));
+        assert_eq!(queue.len(), 4);
+    }
+
+    #[test]
+    fn test_push_unchecked_ignores_policy() {
+        let mut queue: MusicQueue<u64, u64, SlotsPolicy> = MusicQueue::new();
+        assert_eq!(queue.extend(20, vec![1, 2, 3]), 0);
+        assert!(!queue.push(20, 4));
+        queue.push_unchecked(20, 4);
+        queue.push_unchecked(30, 5);
+        assert_eq!(queue.count(20), Some(4));
+        assert_eq!(queue.count(30), Some(1));
+        assert_eq!(queue.len(), 5);
+    }
+
+    #[test]
+    fn test_queue_policy_slots() {
+        let mut queue: MusicQueue<u64, u64, SlotsPolicy> = MusicQueue::new();
+        queue.extend(10, vec![1, 2, 3]);
+        queue.extend(20, vec![4, 5, 6]);
+        assert_eq!(
+            items(&queue),
+            vec![(10, 1), (10, 2), (20, 4), (10, 3), (20, 5), (20, 6)]
+        );
+        assert_eq!(queue.get(2), Some(QueueItem { key: 20, value: &4 }));
+
+        // Popping partway through a turn keeps the rest of the turn.
+        assert_eq!(queue.pop(), Some(QueueItem { key: 10, value: 1 }));
+        assert_eq!(
+            items(&queue),
+            vec![(10, 2), (20, 4), (10, 3), (20, 5), (20, 6)]
+        );
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_removing_key_ends_turn() {
+        let mut queue: MusicQueue<u64, u64, SlotsPolicy> = MusicQueue::new();
+        queue.extend(10, vec![1, 2]);
+        assert_eq!(queue.pop(), Some(QueueItem { key: 10, value: 1 }));
+        assert_eq!(queue.clear_key(10), Some(1));
+        queue.extend(10, vec![3, 4, 5]);
+        queue.extend(20, vec![6]);
+        // Key 10 starts a new turn of two items instead of resuming the cleared one.
+        assert_eq!(items(&queue), vec![(10, 3), (10, 4), (20, 6), (10, 5)]);
+
+        assert_eq!(queue.pop(), Some(QueueItem { key: 10, value: 3 }));
+        assert_eq!(queue.remove_range(..).len(), 3);
+        queue.extend(10, vec![7, 8, 9]);
+        queue.extend(20, vec![10]);
+        assert_eq!(items(&queue), vec![(10, 7), (10, 8), (20, 10), (10, 9)]);
+
+        assert_eq!(queue.pop(), Some(QueueItem { key: 10, value: 7 }));
+        assert_eq!(queue.remove(0), Some(8));
+        assert_eq!(queue.remove(0), Some(9));
+        assert_eq!(queue.remove(0), Some(10));
+        queue.extend(10, vec![11, 12, 13]);
+        queue.extend(20, vec![14]);
+        assert_eq!(items(&queue), vec![(10, 11), (10, 12), (20, 14), (10, 13)]);
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_policy_cost() {
+        let mut queue: MusicQueue<u64, u64, CostPolicy> = MusicQueue::new();
+        queue.extend(1, vec![3, 3, 3, 3, 3]);
+        queue.extend(2, vec![10, 10]);
+        assert_eq!(
+            items(&queue),
+            vec![(1, 3), (1, 3), (1, 3), (1, 3), (2, 10), (1, 3), (2, 10)]
+        );
+        assert_round_robin(queue);
+    }
+
+    #[test]
+    fn test_queue_iter_keys() {
+        let mut queue: MusicQueue<u64, u64> = MusicQueue::new();
+        queue.push(20, 20);
+        queue.push(10, 10);
+        queue.push(20, 40);
+        queue.pop();
+        let mut copy: MusicQueue<u64, u64> = MusicQueue::new();
+        for (key, values) in queue.iter_keys() {
+            copy.extend(key, values.cloned());
+        }
+        assert_eq!(
+            queue.iter().collect::<Vec<_>>(),
+            copy.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(copy.pop(), Some(QueueItem { key: 10, value: 10 }));
+        assert_eq!(copy.pop(), Some(QueueItem { key: 20, value: 40 }));
+        assert_eq!(copy.pop(), None);
+    }
+
     #[test]
     fn test_queue_iter() {
         let mut queue: MusicQueue<u64, u64> = MusicQueue::new();
@@ -0,0 +1,250 @@
+use crate::{prelude::*, Client};
+use anyhow::Result;
+use hourai::{
+    commands,
+    http::request::channel::reaction::RequestReactionType,
+    models::{
+        channel::{Message, Reaction, ReactionType},
+        id::*,
+    },
+};
+use tokio::sync::oneshot;
+use twilight_embed_builder::*;
+
+/// The maximum number of results shown when searching.
+pub const SEARCH_RESULTS: usize = 5;
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(30);
+const NUMBER_EMOJI: [&str; SEARCH_RESULTS] = [
+    "1\u{fe0f}\u{20e3}",
+    "2\u{fe0f}\u{20e3}",
+    "3\u{fe0f}\u{20e3}",
+    "4\u{fe0f}\u{20e3}",
+    "5\u{fe0f}\u{20e3}",
+];
+
+/// A search waiting on the requester to pick one of the results.
+pub struct PendingSearch {
+    channel_id: ChannelId,
+    user_id: UserId,
+    options: usize,
+    tx: oneshot::Sender<usize>,
+}
+
+/// Shows the results of a search and waits for the requester to pick one, either by replying
+/// with its number or by reacting with it. If nothing is picked before the timeout, the result
+/// most similar to the query is picked instead.
+pub async fn pick_result(
+    client: &Client<'static>,
+    ctx: &commands::Context<'_>,
+    query: &str,
+    mut results: Vec<twilight_lavalink::http::Track>,
+) -> Result<twilight_lavalink::http::Track> {
+    results.truncate(SEARCH_RESULTS);
+    let description = results
+        .iter()
+        .enumerate()
+        .map(|(idx, track)| {
+            format!(
+                "`{}.` `[{}]` **[{}]({})**",
+                idx + 1,
+                format_duration(Duration::from_millis(track.info.length)),
+                track.info.title.as_deref().unwrap_or("Unknown"),
+                track.info.uri
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let message = ctx
+        .respond_embed(
+            format!(
+                ":mag: Pick a track by replying with its number or reacting within {} seconds.",
+                SEARCH_TIMEOUT.as_secs()
+            ),
+            EmbedBuilder::new().description(description)?.build()?,
+        )
+        .await?;
+
+    let (tx, rx) = oneshot::channel();
+    client.searches.insert(
+        message.id,
+        PendingSearch {
+            channel_id: message.channel_id,
+            user_id: ctx.author().id,
+            options: results.len(),
+            tx,
+        },
+    );
+    for emoji in NUMBER_EMOJI.iter().take(results.len()) {
+        let reaction = RequestReactionType::Unicode {
+            name: (*emoji).to_owned(),
+        };
+        if let Err(err) = client
+            .http_client
+            .create_reaction(message.channel_id, message.id, reaction)
+            .await
+        {
+            warn!("Failed to add reaction to search results: {}", err);
+            break;
+        }
+    }
+
+    let picked = match tokio::time::timeout(SEARCH_TIMEOUT, rx).await {
+        Ok(Ok(idx)) => idx,
+        _ => {
+            debug!("No search result picked, falling back to the closest match.");
+            closest_match(query, results.iter().map(track_names)).unwrap_or(0)
+        }
+    };
+    client.searches.remove(&message.id);
+    if let Err(err) = client
+        .http_client
+        .delete_message(message.channel_id, message.id)
+        .await
+    {
+        warn!("Failed to delete search results: {}", err);
+    }
+    Ok(results.swap_remove(picked))
+}
+
+/// Picks a search result if the message is a number in reply to a pending search by the same
+/// user. Returns true if the message picked a result.
+pub fn on_message(client: &Client<'static>, message: &Message) -> bool {
+    let choice = match message.content.trim().parse::<usize>() {
+        Ok(choice) if choice > 0 => choice - 1,
+        _ => return false,
+    };
+    let search_id = client
+        .searches
+        .iter()
+        .find(|kv| {
+            let search = kv.value();
+            search.channel_id == message.channel_id
+                && search.user_id == message.author.id
+                && choice < search.options
+        })
+        .map(|kv| *kv.key());
+    match search_id {
+        Some(search_id) => pick(client, search_id, choice),
+        None => false,
+    }
+}
+
+/// Picks a search result if the reaction is a number added by the requester of a pending
+/// search.
+pub fn on_reaction_add(client: &Client<'static>, reaction: &Reaction) {
+    let choice = match &reaction.emoji {
+        ReactionType::Unicode { name } => NUMBER_EMOJI.iter().position(|emoji| emoji == name),
+        _ => None,
+    };
+    let is_requester = client
+        .searches
+        .get(&reaction.message_id)
+        .map_or(false, |kv| {
+            let search = kv.value();
+            search.user_id == reaction.user_id && choice.map_or(false, |c| c < search.options)
+        });
+    if let (true, Some(choice)) = (is_requester, choice) {
+        pick(client, reaction.message_id, choice);
+    }
+}
+
+fn pick(client: &Client<'static>, search_id: MessageId, choice: usize) -> bool {
+    match client.searches.remove(&search_id) {
+        Some((_, search)) => search.tx.send(choice).is_ok(),
+        None => false,
+    }
+}
+
+fn track_names(track: &twilight_lavalink::http::Track) -> (Option<&str>, Option<&str>) {
+    (track.info.title.as_deref(), track.info.author.as_deref())
+}
+
+/// Removes the search provider prefix from a Lavalink query, if present.
+pub fn strip_search_prefix(query: &str) -> &str {
+    ["ytsearch:", "scsearch:"]
+        .iter()
+        .find_map(|prefix| query.strip_prefix(prefix))
+        .unwrap_or(query)
+}
+
+/// Gets the index of the search result most similar to the query, by edit distance. Each
+/// result is compared by both its title alone and its author and title together. Ties go to
+/// the earlier result, as the search provider ranked it higher.
+pub fn closest_match<'a>(
+    query: &str,
+    results: impl IntoIterator<Item = (Option<&'a str>, Option<&'a str>)>,
+) -> Option<usize> {
+    let query = query.to_lowercase();
+    results
+        .into_iter()
+        .map(|(title, author)| {
+            let title = title.unwrap_or_default().to_lowercase();
+            let full = format!("{} {}", author.unwrap_or_default().to_lowercase(), title);
+            std::cmp::min(edit_distance(&query, &title), edit_distance(&query, &full))
+        })
+        .enumerate()
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(idx, _)| idx)
+}
+
+/// Computes the Levenshtein distance between two strings, by character.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("flaw", "lawn"), 2);
+        assert_eq!(edit_distance("touhou", "touhou"), 0);
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let results = vec![
+            (Some("Bad Apple!! (Extended Mix)"), Some("Someone")),
+            (Some("Bad Apple!!"), Some("Alstroemeria Records")),
+            (
+                Some("Bad Apple!! feat. nomico"),
+                Some("Alstroemeria Records"),
+            ),
+        ];
+        assert_eq!(closest_match("bad apple!!", results.clone()), Some(1));
+        assert_eq!(
+            closest_match("Alstroemeria Records Bad Apple!!", results),
+            Some(1)
+        );
+        assert_eq!(closest_match("anything", vec![]), None);
+    }
+
+    #[test]
+    fn test_closest_match_ties_go_to_first() {
+        let results = vec![(Some("abc"), None), (Some("abd"), None)];
+        assert_eq!(closest_match("abx", results), Some(0));
+    }
+
+    #[test]
+    fn test_strip_search_prefix() {
+        assert_eq!(strip_search_prefix("ytsearch:bad apple"), "bad apple");
+        assert_eq!(strip_search_prefix("scsearch:bad apple"), "bad apple");
+        assert_eq!(strip_search_prefix("bad apple"), "bad apple");
+    }
+}
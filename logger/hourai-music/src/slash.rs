@@ -0,0 +1,380 @@
+use crate::{commands, prelude::*, Client};
+use anyhow::Result;
+use hourai::{
+    commands::{Context, Invocation},
+    models::application::{
+        command::{BaseCommandOptionData, ChoiceCommandOptionData, Command, CommandOption},
+        interaction::{application_command::CommandDataOption, ApplicationCommand, Interaction},
+    },
+};
+use twilight_command_parser::Arguments;
+
+#[derive(Clone, Copy)]
+enum OptionKind {
+    String,
+    Integer,
+    /// A boolean option passed to the command as the given flag when set.
+    Flag(&'static str),
+}
+
+struct SlashOption {
+    name: &'static str,
+    description: &'static str,
+    kind: OptionKind,
+    required: bool,
+}
+
+struct SlashCommand {
+    name: &'static str,
+    description: &'static str,
+    options: &'static [SlashOption],
+}
+
+const fn option(
+    name: &'static str,
+    description: &'static str,
+    kind: OptionKind,
+    required: bool,
+) -> SlashOption {
+    SlashOption {
+        name,
+        description,
+        kind,
+        required,
+    }
+}
+
+// TODO(james7132): Autocomplete search queries once twilight is updated to a version that
+// supports autocomplete interactions.
+const QUERY: SlashOption = option("query", "A URL or search query.", OptionKind::String, true);
+
+const TIMESTAMP: SlashOption = option(
+    "time",
+    "A timestamp, like 1:30, or an amount of time, like 30s.",
+    OptionKind::String,
+    false,
+);
+
+/// The application commands of the music service. Each one runs the prefix command of the same
+/// name, with its options passed as arguments in the order they are listed.
+const SLASH_COMMANDS: &[SlashCommand] = &[
+    SlashCommand {
+        name: "play",
+        description: "Adds a track or playlist to the queue.",
+        options: &[QUERY],
+    },
+    SlashCommand {
+        name: "search",
+        description: "Searches for a track and picks one of the results.",
+        options: &[QUERY],
+    },
+    SlashCommand {
+        name: "pause",
+        description: "Pauses the player.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "stop",
+        description: "Stops the player and clears the queue. Requires DJ or a vote.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "shuffle",
+        description: "Shuffles your tracks in the queue.",
+        options: &[option(
+            "all",
+            "Shuffle everyone's tracks instead. Requires DJ or a vote.",
+            OptionKind::Flag("all"),
+            false,
+        )],
+    },
+    SlashCommand {
+        name: "skip",
+        description: "Votes to skip the current track.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "forceskip",
+        description: "Skips the current track. Requires DJ.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "remove",
+        description: "Removes a track, or a range of tracks like 3-5, from the queue.",
+        options: &[option(
+            "position",
+            "The position or range in the queue.",
+            OptionKind::String,
+            true,
+        )],
+    },
+    SlashCommand {
+        name: "move",
+        description: "Moves a track to another position in the queue.",
+        options: &[
+            option(
+                "from",
+                "The position of the track.",
+                OptionKind::Integer,
+                true,
+            ),
+            option(
+                "to",
+                "The position to move it to.",
+                OptionKind::Integer,
+                true,
+            ),
+        ],
+    },
+    SlashCommand {
+        name: "swap",
+        description: "Swaps the positions of two tracks in the queue.",
+        options: &[
+            option(
+                "first",
+                "The position of a track.",
+                OptionKind::Integer,
+                true,
+            ),
+            option(
+                "second",
+                "The position of a track.",
+                OptionKind::Integer,
+                true,
+            ),
+        ],
+    },
+    SlashCommand {
+        name: "skipto",
+        description: "Skips to a track in the queue.",
+        options: &[option(
+            "position",
+            "The position of the track.",
+            OptionKind::Integer,
+            true,
+        )],
+    },
+    SlashCommand {
+        name: "volume",
+        description: "Shows or sets the volume of the player.",
+        options: &[option(
+            "volume",
+            "The new volume, from 0 to 150.",
+            OptionKind::Integer,
+            false,
+        )],
+    },
+    SlashCommand {
+        name: "removeall",
+        description: "Removes all of your tracks from the queue.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "clear",
+        description: "Clears the queue. Requires DJ or a vote.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "nowplaying",
+        description: "Shows the currently playing track.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "queue",
+        description: "Shows the queue.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "lyrics",
+        description: "Shows the lyrics of the currently playing track.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "repeat",
+        description: "Toggles repeating the current track.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "loop",
+        description: "Toggles looping the queue.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "autoplay",
+        description: "Toggles queueing related tracks when the queue runs out.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "seek",
+        description: "Seeks to a position in the current track.",
+        options: &[option(
+            "time",
+            "A timestamp, like 1:30, or an offset, like +30s.",
+            OptionKind::String,
+            true,
+        )],
+    },
+    SlashCommand {
+        name: "rewind",
+        description: "Rewinds the current track.",
+        options: &[TIMESTAMP],
+    },
+    SlashCommand {
+        name: "fastforward",
+        description: "Fast forwards the current track.",
+        options: &[TIMESTAMP],
+    },
+    SlashCommand {
+        name: "replay",
+        description: "Restarts the current track.",
+        options: &[],
+    },
+    SlashCommand {
+        name: "filter",
+        description: "Shows or sets the audio filter.",
+        options: &[option(
+            "preset",
+            "The name of a preset, off, or speed=, pitch= and rate= settings.",
+            OptionKind::String,
+            false,
+        )],
+    },
+    SlashCommand {
+        name: "eq",
+        description: "Shows or sets the equalizer bands.",
+        options: &[option(
+            "bands",
+            "<band>=<gain> pairs separated by spaces, like 0=0.25 1=0.1, or reset.",
+            OptionKind::String,
+            false,
+        )],
+    },
+    SlashCommand {
+        name: "playlist",
+        description: "Saves, loads, lists or deletes playlists.",
+        options: &[
+            option(
+                "action",
+                "One of save, load, list or delete.",
+                OptionKind::String,
+                true,
+            ),
+            option(
+                "server",
+                "Use the server's playlists instead of your own.",
+                OptionKind::Flag("--server"),
+                false,
+            ),
+            option(
+                "name",
+                "The name of the playlist.",
+                OptionKind::String,
+                false,
+            ),
+        ],
+    },
+];
+
+impl SlashOption {
+    fn build(&self) -> CommandOption {
+        let name = self.name.to_owned();
+        let description = self.description.to_owned();
+        match self.kind {
+            OptionKind::String => CommandOption::String(ChoiceCommandOptionData {
+                choices: Vec::new(),
+                description,
+                name,
+                required: self.required,
+            }),
+            OptionKind::Integer => CommandOption::Integer(ChoiceCommandOptionData {
+                choices: Vec::new(),
+                description,
+                name,
+                required: self.required,
+            }),
+            OptionKind::Flag(_) => CommandOption::Boolean(BaseCommandOptionData {
+                description,
+                name,
+                required: self.required,
+            }),
+        }
+    }
+}
+
+impl SlashCommand {
+    fn build(&self) -> Command {
+        Command {
+            application_id: None,
+            default_permission: None,
+            description: self.description.to_owned(),
+            guild_id: None,
+            id: None,
+            name: self.name.to_owned(),
+            options: self.options.iter().map(SlashOption::build).collect(),
+        }
+    }
+}
+
+/// Registers the application commands with Discord, replacing any that were registered before.
+pub async fn register(client: &Client<'static>) -> Result<()> {
+    let commands: Vec<Command> = SLASH_COMMANDS.iter().map(SlashCommand::build).collect();
+    client.http_client.set_global_commands(commands)?.await?;
+    info!("Registered {} application commands.", SLASH_COMMANDS.len());
+    Ok(())
+}
+
+pub async fn on_interaction_create(
+    client: Client<'static>,
+    interaction: Interaction,
+) -> Result<()> {
+    match interaction {
+        Interaction::ApplicationCommand(command) => run_command(&client, &command).await,
+        _ => Ok(()),
+    }
+}
+
+async fn run_command(client: &Client<'static>, command: &ApplicationCommand) -> Result<()> {
+    let definition = match SLASH_COMMANDS.iter().find(|c| c.name == command.data.name) {
+        Some(definition) => definition,
+        None => {
+            debug!("Failed to find application command: {}", command.data.name);
+            return Ok(());
+        }
+    };
+
+    let ctx = Context::new(Invocation::Interaction(command), client.http_client.clone());
+    ctx.defer().await?;
+
+    let arguments = build_arguments(definition, command);
+    commands::run_command(
+        client,
+        ctx,
+        definition.name,
+        Arguments::from(arguments.as_str()),
+    )
+    .await
+}
+
+/// Converts the options of an application command to the arguments of the matching prefix
+/// command.
+fn build_arguments(definition: &SlashCommand, command: &ApplicationCommand) -> String {
+    let mut arguments = Vec::new();
+    for option in definition.options {
+        let value = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name() == option.name);
+        match (option.kind, value) {
+            (OptionKind::Flag(flag), Some(CommandDataOption::Boolean { value: true, .. })) => {
+                arguments.push(flag.to_owned())
+            }
+            (_, Some(CommandDataOption::String { value, .. })) => arguments.push(value.clone()),
+            (_, Some(CommandDataOption::Integer { value, .. })) => {
+                arguments.push(value.to_string())
+            }
+            _ => {}
+        }
+    }
+    arguments.join(" ")
+}
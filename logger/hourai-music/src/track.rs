@@ -1,4 +1,6 @@
-use hourai::models::{id::GuildId, user::User};
+use hourai::models::{id::GuildId, user::User, UserLike};
+use hourai::proto::cache::{CachedTrackProto, CachedUserProto};
+use hourai_sql::playlists::PlaylistTrack;
 use std::convert::TryFrom;
 use std::{fmt, time::Duration};
 use tracing::error;
@@ -25,15 +27,37 @@ impl fmt::Display for TrackInfo {
 
 #[derive(Clone)]
 pub struct Track {
-    pub requestor: User,
+    pub requestor: CachedUserProto,
     pub info: TrackInfo,
     pub track: Vec<u8>,
 }
 
 impl Track {
     pub fn play(&self, guild_id: GuildId) -> Play {
-        Play::new(guild_id, base64::encode(&self.track), None, None, false)
+        self.play_from(guild_id, Duration::from_secs(0))
     }
+
+    /// Creates a request to play the track starting at the given position.
+    pub fn play_from(&self, guild_id: GuildId, position: Duration) -> Play {
+        let start = if position.as_millis() > 0 {
+            Some(position.as_millis() as u64)
+        } else {
+            None
+        };
+        Play::new(guild_id, base64::encode(&self.track), start, None, false)
+    }
+}
+
+fn cache_user(user: &impl UserLike) -> CachedUserProto {
+    let mut proto = CachedUserProto::new();
+    proto.set_id(user.id().0);
+    proto.set_username(user.name().to_owned());
+    proto.set_discriminator(user.discriminator() as u32);
+    proto.set_bot(user.bot());
+    if let Some(avatar) = user.avatar_hash() {
+        proto.set_avatar(avatar.to_owned());
+    }
+    proto
 }
 
 fn decode_track(track: String) -> std::result::Result<Vec<u8>, base64::DecodeError> {
@@ -58,6 +82,15 @@ impl From<twilight_lavalink::http::TrackInfo> for TrackInfo {
 impl TryFrom<(User, twilight_lavalink::http::Track)> for Track {
     type Error = base64::DecodeError;
     fn try_from(value: (User, twilight_lavalink::http::Track)) -> Result<Self, Self::Error> {
+        Self::try_from((cache_user(&value.0), value.1))
+    }
+}
+
+impl TryFrom<(CachedUserProto, twilight_lavalink::http::Track)> for Track {
+    type Error = base64::DecodeError;
+    fn try_from(
+        value: (CachedUserProto, twilight_lavalink::http::Track),
+    ) -> Result<Self, Self::Error> {
         Ok(Self {
             requestor: value.0,
             info: TrackInfo::from(value.1.info),
@@ -65,3 +98,76 @@ impl TryFrom<(User, twilight_lavalink::http::Track)> for Track {
         })
     }
 }
+
+impl From<&Track> for CachedTrackProto {
+    fn from(value: &Track) -> Self {
+        let mut proto = Self::new();
+        proto.set_requestor(value.requestor.clone());
+        if let Some(ref title) = value.info.title {
+            proto.set_title(title.clone());
+        }
+        if let Some(ref author) = value.info.author {
+            proto.set_author(author.clone());
+        }
+        proto.set_uri(value.info.uri.clone());
+        proto.set_length(value.info.length.as_millis() as u64);
+        proto.set_is_stream(value.info.is_stream);
+        proto.set_track(value.track.clone());
+        proto
+    }
+}
+
+impl From<CachedTrackProto> for Track {
+    fn from(mut value: CachedTrackProto) -> Self {
+        Self {
+            requestor: value.take_requestor(),
+            info: TrackInfo {
+                title: if value.has_title() {
+                    Some(value.take_title())
+                } else {
+                    None
+                },
+                author: if value.has_author() {
+                    Some(value.take_author())
+                } else {
+                    None
+                },
+                uri: value.take_uri(),
+                length: Duration::from_millis(value.get_length()),
+                is_stream: value.get_is_stream(),
+            },
+            track: value.take_track(),
+        }
+    }
+}
+
+impl From<&Track> for PlaylistTrack {
+    fn from(value: &Track) -> Self {
+        Self {
+            position: 0,
+            track: value.track.clone(),
+            title: value.info.title.clone(),
+            author: value.info.author.clone(),
+            uri: value.info.uri.clone(),
+            length: value.info.length.as_millis() as i64,
+            is_stream: value.info.is_stream,
+        }
+    }
+}
+
+impl From<(User, PlaylistTrack)> for Track {
+    fn from(value: (User, PlaylistTrack)) -> Self {
+        let track = value.1;
+        Self {
+            requestor: cache_user(&value.0),
+            info: TrackInfo {
+                title: track.title,
+                author: track.author,
+                uri: track.uri,
+                length: Duration::from_millis(track.length as u64),
+                is_stream: track.is_stream,
+            },
+            track: track.track,
+        }
+    }
+}
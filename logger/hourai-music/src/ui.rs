@@ -1,11 +1,12 @@
-use crate::{prelude::*, track::Track, Client};
+use crate::{player::RepeatMode, prelude::*, track::Track, Client};
 use anyhow::{bail, Result};
 use hourai::{
     commands,
-    models::{channel::embed::Embed, id::*, UserLike},
+    models::{channel::embed::Embed, id::*, Snowflake, UserLike},
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::{oneshot::error::TryRecvError, Notify};
 use twilight_embed_builder::*;
 
 const PROGRESS_BAR_WIDTH: usize = 12;
@@ -13,6 +14,7 @@ const TRACKS_PER_PAGE: usize = 10;
 
 pub struct MessageUI {
     cancel: tokio::sync::oneshot::Sender<()>,
+    refresh: Arc<Notify>,
 }
 
 impl MessageUI {
@@ -21,11 +23,16 @@ impl MessageUI {
         U: Updateable + Send + 'static,
     {
         let (tx, mut rx) = tokio::sync::oneshot::channel();
+        let refresh = Arc::new(Notify::new());
+        let notify = refresh.clone();
         tokio::spawn(async move {
             loop {
                 match ui.update().await {
                     Ok(_) => match rx.try_recv() {
-                        Err(TryRecvError::Empty) => tokio::time::sleep(update_interval).await,
+                        Err(TryRecvError::Empty) => tokio::select! {
+                            _ = tokio::time::sleep(update_interval) => {}
+                            _ = notify.notified() => {}
+                        },
                         _ => break,
                     },
                     Err(err) => {
@@ -35,7 +42,15 @@ impl MessageUI {
                 }
             }
         });
-        Self { cancel: tx }
+        Self {
+            cancel: tx,
+            refresh,
+        }
+    }
+
+    /// Updates the UI immediately instead of waiting for the next update.
+    pub fn refresh(&self) {
+        self.refresh.notify_one();
     }
 
     pub fn cancel(self) {
@@ -70,8 +85,17 @@ where
     T: EmbedUIBuilder + Default,
 {
     pub async fn create(client: Client<'static>, ctx: commands::Context<'_>) -> Result<Self> {
+        Self::create_with(client, ctx, T::default()).await
+    }
+
+    /// Creates the UI with a builder that already has content.
+    pub async fn create_with(
+        client: Client<'static>,
+        ctx: commands::Context<'_>,
+        builder: T,
+    ) -> Result<Self> {
         let guild_id = commands::precondition::require_in_guild(&ctx)?;
-        let channel_id = ctx.message.channel_id;
+        let channel_id = ctx.channel_id();
 
         let mut dummy = Self {
             client,
@@ -79,7 +103,7 @@ where
             channel_id,
             message_id: MessageId(0),
             expiration: Instant::now() + Duration::from_secs(300),
-            builder: T::default(),
+            builder,
         };
 
         let message = dummy
@@ -118,6 +142,14 @@ pub struct NowPlayingUI;
 pub struct QueueUI {
     page: usize,
 }
+/// Pages through the lyrics of a track.
+#[derive(Default)]
+pub struct LyricsUI {
+    pub title: String,
+    pub uri: String,
+    pub pages: Vec<String>,
+    pub page: Arc<AtomicUsize>,
+}
 
 impl EmbedUIBuilder for NowPlayingUI {
     fn build_content(&self, _: &EmbedUI<Self>) -> String {
@@ -142,7 +174,7 @@ fn build_np_embed<T: EmbedUIBuilder>(ui: &EmbedUI<T>) -> Result<Embed> {
                 .icon_url(ImageSource::url(track.requestor.avatar_url())?),
         )
         .title(track.info.title.unwrap_or_else(|| "Unknown".to_owned()))?
-        .description(build_progress_bar(&ui))?
+        .description(build_np_description(&ui))?
         .url(track.info.uri.clone())
         .build()?)
 }
@@ -176,7 +208,7 @@ impl EmbedUIBuilder for QueueUI {
                 format_duration(track.info.length),
                 track.info,
                 track.info.uri,
-                track.requestor.id
+                track.requestor.id()
             )
         }
 
@@ -209,20 +241,66 @@ impl EmbedUIBuilder for QueueUI {
     }
 }
 
-fn build_progress_bar<T: EmbedUIBuilder + Default>(ui: &EmbedUI<T>) -> String {
-    let (pos, paused) = match ui.client.lavalink.players().get(&ui.guild_id) {
+impl EmbedUIBuilder for LyricsUI {
+    fn build_content(&self, _: &EmbedUI<Self>) -> String {
+        ":page_facing_up: **Lyrics**".to_owned()
+    }
+
+    fn build_embed(&self, _: &EmbedUI<Self>) -> Result<Embed> {
+        let page = std::cmp::min(
+            self.page.load(Ordering::SeqCst),
+            self.pages.len().saturating_sub(1),
+        );
+        let footer = format!("Page {}/{}", page + 1, std::cmp::max(self.pages.len(), 1));
+        Ok(EmbedBuilder::new()
+            .title(self.title.clone())?
+            .url(self.uri.clone())
+            .description(self.pages.get(page).cloned().unwrap_or_default())?
+            .footer(EmbedFooterBuilder::new(footer)?)
+            .build()?)
+    }
+}
+
+fn build_np_description<T: EmbedUIBuilder + Default>(ui: &EmbedUI<T>) -> String {
+    let progress_bar = build_progress_bar(ui);
+    let (repeat_mode, autoplay, filter) = match ui.client.states.get(&ui.guild_id) {
         Some(kv) => {
-            let player = kv.value();
-            (player.position(), player.paused())
+            let state = kv.value();
+            (state.repeat_mode, state.autoplay, state.filter.clone())
         }
-        None => (0, true),
+        None => return progress_bar,
     };
 
+    let mut modes = Vec::new();
+    match repeat_mode {
+        RepeatMode::Off => {}
+        RepeatMode::Track => modes.push(":repeat_one: Repeating track".to_owned()),
+        RepeatMode::Queue => modes.push(":repeat: Looping queue".to_owned()),
+    }
+    if autoplay {
+        modes.push(":infinity: Autoplay".to_owned());
+    }
+    if let Some(filter) = filter {
+        modes.push(format!(":level_slider: Filter: `{}`", filter));
+    }
+
+    if modes.is_empty() {
+        progress_bar
+    } else {
+        format!("{}\n{}", progress_bar, modes.join(" | "))
+    }
+}
+
+fn build_progress_bar<T: EmbedUIBuilder + Default>(ui: &EmbedUI<T>) -> String {
+    let (pos, paused) = ui
+        .client
+        .position(ui.guild_id)
+        .unwrap_or((Duration::from_secs(0), true));
+
     let length = match ui.client.currently_playing(ui.guild_id) {
         Some(track) => track.info.length,
         None => Duration::from_millis(i64::MAX as u64),
     };
-    let pos = Duration::from_millis(pos as u64);
     let complete = (pos.as_millis() as f64) / (length.as_millis() as f64);
     let prefix = if paused {
         ":pause_button:"
@@ -0,0 +1,109 @@
+use hourai::models::id::UserId;
+use hourai::proto::guild_configs::MusicConfig;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// An action that affects everyone listening, and can be voted on by listeners who are not DJs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VoteAction {
+    Skip,
+    Stop,
+    Shuffle,
+    Clear,
+    /// Setting the volume to a given value. Votes for different volumes are counted separately.
+    Volume(u32),
+}
+
+impl VoteAction {
+    /// Gets the percentage of listeners that must vote for the action for it to pass. Zero
+    /// means only DJs can take the action.
+    pub fn threshold(&self, config: &MusicConfig) -> u32 {
+        let threshold = match self {
+            Self::Skip => config.get_skip_vote_threshold(),
+            Self::Stop => config.get_stop_vote_threshold(),
+            Self::Shuffle => config.get_shuffle_vote_threshold(),
+            Self::Clear => config.get_clear_vote_threshold(),
+            Self::Volume(_) => config.get_volume_vote_threshold(),
+        };
+        std::cmp::min(threshold, 100)
+    }
+}
+
+impl fmt::Display for VoteAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skip => f.write_str("skip the current track"),
+            Self::Stop => f.write_str("stop the player"),
+            Self::Shuffle => f.write_str("shuffle the queue"),
+            Self::Clear => f.write_str("clear the queue"),
+            Self::Volume(volume) => write!(f, "set the volume to {}", volume),
+        }
+    }
+}
+
+/// The votes cast by listeners for each action.
+#[derive(Debug, Default)]
+pub struct Votes(HashMap<VoteAction, HashSet<UserId>>);
+
+impl Votes {
+    /// Casts a user's vote for an action. Returns the number of votes for the action.
+    pub fn cast(&mut self, action: VoteAction, user_id: UserId) -> usize {
+        let votes = self.0.entry(action).or_default();
+        votes.insert(user_id);
+        votes.len()
+    }
+
+    /// Clears the votes for an action, usually after it has been taken.
+    pub fn clear(&mut self, action: VoteAction) {
+        self.0.remove(&action);
+    }
+
+    /// Clears the votes for every action. Votes only count towards the track they were cast
+    /// during, so this is done whenever the current track changes.
+    pub fn clear_all(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Gets the number of votes needed for an action to pass, given the number of listeners and
+/// the action's threshold. At least one vote is always needed.
+pub fn required_votes(listeners: usize, threshold: u32) -> usize {
+    let threshold = std::cmp::min(threshold, 100) as usize;
+    std::cmp::max((listeners * threshold + 99) / 100, 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_required_votes() {
+        assert_eq!(required_votes(0, 50), 1);
+        assert_eq!(required_votes(1, 50), 1);
+        assert_eq!(required_votes(3, 50), 2);
+        assert_eq!(required_votes(4, 50), 2);
+        assert_eq!(required_votes(10, 75), 8);
+        assert_eq!(required_votes(10, 100), 10);
+        assert_eq!(required_votes(10, 200), 10);
+        assert_eq!(required_votes(10, 1), 1);
+    }
+
+    #[test]
+    fn test_votes_are_counted_per_action() {
+        let mut votes = Votes::default();
+        assert_eq!(votes.cast(VoteAction::Skip, UserId(1)), 1);
+        assert_eq!(votes.cast(VoteAction::Skip, UserId(1)), 1);
+        assert_eq!(votes.cast(VoteAction::Skip, UserId(2)), 2);
+        assert_eq!(votes.cast(VoteAction::Stop, UserId(1)), 1);
+        assert_eq!(votes.cast(VoteAction::Volume(50), UserId(1)), 1);
+        assert_eq!(votes.cast(VoteAction::Volume(100), UserId(2)), 1);
+
+        votes.clear(VoteAction::Skip);
+        assert_eq!(votes.cast(VoteAction::Skip, UserId(2)), 1);
+        assert_eq!(votes.cast(VoteAction::Stop, UserId(2)), 2);
+
+        votes.clear_all();
+        assert_eq!(votes.cast(VoteAction::Stop, UserId(1)), 1);
+        assert_eq!(votes.cast(VoteAction::Volume(50), UserId(2)), 1);
+    }
+}
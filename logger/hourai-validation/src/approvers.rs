@@ -1,26 +1,25 @@
 use super::{context, *};
 use async_trait::async_trait;
-use hourai::cache::InMemoryCache;
 use hourai::models::id::UserId;
 use hourai::models::user::{User, UserFlags};
 use std::collections::HashSet;
 
 const VERIFIED_FEATURE: &str = "VERIFIED";
 
-struct DistinguishedUserVerifier(InMemoryCache);
+struct DistinguishedUserVerifier;
 
 #[async_trait]
 impl Verifier for DistinguishedUserVerifier {
     async fn verify(&self, ctx: &mut context::VerificationContext) -> Result<()> {
         let flags = ctx.member().user.flags.unwrap_or_else(UserFlags::empty);
         if flags.contains(UserFlags::DISCORD_EMPLOYEE) {
-            ctx.add_approval_reason("User is Discord Staff.");
+            ctx.add_reason(distinguished("User is Discord Staff."));
         }
         if flags.contains(UserFlags::DISCORD_PARTNER) {
-            ctx.add_approval_reason("User is a Discord Partner.");
+            ctx.add_reason(distinguished("User is a Discord Partner."));
         }
         if flags.contains(UserFlags::VERIFIED_BOT_DEVELOPER) {
-            ctx.add_approval_reason("User is a verified bot developer.");
+            ctx.add_reason(distinguished("User is a verified bot developer."));
         }
         // TODO(james7123): This will not scale to multiple processes
         //let member_id = ctx.member().user.id;
@@ -39,6 +38,10 @@ impl Verifier for DistinguishedUserVerifier {
     }
 }
 
+fn distinguished(message: &str) -> VerificationReason {
+    VerificationReason::approval("distinguished_user", Severity::Malicious, message)
+}
+
 pub fn user_has_nitro(user: &User) -> bool {
     let flag = user
         .flags
@@ -54,6 +57,8 @@ pub fn user_has_nitro(user: &User) -> bool {
 
 pub(super) fn nitro() -> BoxedVerifier {
     GenericVerifier::new_approver(
+        "nitro",
+        Severity::Suspicion,
         "User currently has or has had Nitro. Probably not a user bot.",
         |ctx| Ok(user_has_nitro(&ctx.member().user)),
     )
@@ -61,18 +66,23 @@ pub(super) fn nitro() -> BoxedVerifier {
 
 pub(super) fn bot_owners(owners: impl IntoIterator<Item = UserId>) -> BoxedVerifier {
     let owner_ids: HashSet<UserId> = owners.into_iter().collect();
-    GenericVerifier::new_approver("User is an owner of this bot.", move |ctx| {
-        Ok(owner_ids.contains(&ctx.member().user.id))
-    })
+    GenericVerifier::new_approver(
+        "bot_owner",
+        Severity::Override,
+        "User is an owner of this bot.",
+        move |ctx| Ok(owner_ids.contains(&ctx.member().user.id)),
+    )
 }
 
 pub(super) fn bot() -> BoxedVerifier {
     GenericVerifier::new_approver(
+        "bot",
+        Severity::Override,
         "User is an OAuth2 bot that can only be manually added by moderators.",
         |ctx| Ok(ctx.member().user.bot),
     )
 }
 
-pub(super) fn distinguished_user(cache: InMemoryCache) -> BoxedVerifier {
-    Box::new(DistinguishedUserVerifier(cache))
+pub(super) fn distinguished_user() -> BoxedVerifier {
+    Box::new(DistinguishedUserVerifier)
 }
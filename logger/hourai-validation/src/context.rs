@@ -1,32 +1,218 @@
 use hourai::models::guild::Member;
+use hourai::proto::verification::*;
+
+/// How strongly a reason counts towards a verdict. Approvals override all rejections of the same
+/// or lower severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// High recall, low precision checks for suspicious characteristics.
+    Suspicion,
+    /// Red flags for potentially troublesome users.
+    Questionable,
+    /// Known offenders.
+    Malicious,
+    /// Explicit overrides for a small, specific group of users.
+    Override,
+    /// Holds every user for manual review while a guild is locked down. Cannot be overridden and
+    /// carries no weight, as it says nothing about the user themselves.
+    Lockdown,
+}
+
+impl Severity {
+    pub fn weight(self) -> i64 {
+        match self {
+            Severity::Suspicion => 1,
+            Severity::Questionable => 4,
+            Severity::Malicious => 16,
+            Severity::Override => 1000,
+            Severity::Lockdown => 0,
+        }
+    }
+
+    fn to_proto(self) -> VerificationVerdictReason_Severity {
+        match self {
+            Severity::Suspicion => VerificationVerdictReason_Severity::SUSPICION,
+            Severity::Questionable => VerificationVerdictReason_Severity::QUESTIONABLE,
+            Severity::Malicious => VerificationVerdictReason_Severity::MALICIOUS,
+            Severity::Override => VerificationVerdictReason_Severity::OVERRIDE,
+            Severity::Lockdown => VerificationVerdictReason_Severity::LOCKDOWN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReasonKind {
+    Approval,
+    Rejection,
+}
 
 #[derive(Debug, Clone)]
-pub enum VerificationReason {
-    Approval(String),
-    Rejection(String),
+pub struct VerificationReason {
+    /// A stable identifier for the verifier that produced the reason.
+    pub verifier: &'static str,
+    pub kind: ReasonKind,
+    pub severity: Severity,
+    pub message: String,
+    /// The value that triggered the reason, if any. (i.e. a matched username)
+    pub evidence: Option<String>,
 }
 
 impl VerificationReason {
+    pub fn approval(
+        verifier: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(verifier, ReasonKind::Approval, severity, message)
+    }
+
+    pub fn rejection(
+        verifier: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(verifier, ReasonKind::Rejection, severity, message)
+    }
+
+    fn new(
+        verifier: &'static str,
+        kind: ReasonKind,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            verifier,
+            kind,
+            severity,
+            message: message.into(),
+            evidence: None,
+        }
+    }
+
+    pub fn with_evidence(mut self, evidence: impl Into<String>) -> Self {
+        self.evidence = Some(evidence.into());
+        self
+    }
+
     pub fn is_approval(&self) -> bool {
-        self.approval_reason().is_some()
+        self.kind == ReasonKind::Approval
     }
 
     pub fn is_rejection(&self) -> bool {
-        self.rejection_reason().is_some()
+        self.kind == ReasonKind::Rejection
     }
 
-    pub fn approval_reason(&self) -> Option<&str> {
-        match self {
-            VerificationReason::Approval(reason) => Some(reason.as_str()),
-            _ => None,
+    fn to_proto(&self, overridden: bool) -> VerificationVerdictReason {
+        let mut proto = VerificationVerdictReason::new();
+        proto.set_kind(match self.kind {
+            ReasonKind::Approval => VerificationVerdictReason_Kind::APPROVAL,
+            ReasonKind::Rejection => VerificationVerdictReason_Kind::REJECTION,
+        });
+        proto.set_verifier(self.verifier.to_owned());
+        proto.set_severity(self.severity.to_proto());
+        proto.set_message(self.message.clone());
+        if let Some(ref evidence) = self.evidence {
+            proto.set_evidence(evidence.clone());
         }
+        proto.set_overridden(overridden);
+        proto
     }
+}
 
-    pub fn rejection_reason(&self) -> Option<&str> {
-        match self {
-            VerificationReason::Rejection(reason) => Some(reason.as_str()),
-            _ => None,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerdictOutcome {
+    Approve,
+    Reject,
+    NeedsReview,
+}
+
+/// The combined result of all of the reasons found while verifying a user. Unlike the reasons
+/// themselves, the verdict does not depend on the order the verifiers were run in.
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub outcome: VerdictOutcome,
+    /// The sum of the weights of all approvals minus the sum of the weights of all rejections
+    /// that were not overridden. Higher is more trustworthy.
+    pub score: i64,
+    pub reasons: Vec<VerificationReason>,
+    /// The highest severity of any approval. Rejections at or below this are overridden.
+    override_severity: Option<Severity>,
+}
+
+impl Verdict {
+    /// Computes the verdict for a set of reasons:
+    ///  - If every rejection was overridden by an approval, the user is approved.
+    ///  - If any remaining rejection is `Severity::Malicious` or `Severity::Override`, the user
+    ///    is rejected.
+    ///  - Otherwise, the user needs to be manually reviewed.
+    pub fn new(reasons: Vec<VerificationReason>) -> Self {
+        let override_severity = reasons
+            .iter()
+            .filter(|reason| reason.is_approval())
+            .map(|reason| reason.severity)
+            .max();
+        let mut verdict = Self {
+            outcome: VerdictOutcome::Approve,
+            score: 0,
+            reasons,
+            override_severity,
+        };
+
+        let mut score = 0;
+        let mut outcome = VerdictOutcome::Approve;
+        for reason in verdict.reasons.iter() {
+            if reason.is_approval() {
+                score += reason.severity.weight();
+            } else if !verdict.is_overridden(reason) {
+                score -= reason.severity.weight();
+                match reason.severity {
+                    Severity::Malicious | Severity::Override => outcome = VerdictOutcome::Reject,
+                    _ if outcome == VerdictOutcome::Approve => {
+                        outcome = VerdictOutcome::NeedsReview
+                    }
+                    _ => {}
+                }
+            }
+        }
+        verdict.score = score;
+        verdict.outcome = outcome;
+        verdict
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.outcome == VerdictOutcome::Approve
+    }
+
+    /// Checks if a rejection was overridden by an approval of the same or higher severity.
+    pub fn is_overridden(&self, reason: &VerificationReason) -> bool {
+        reason.is_rejection()
+            && self
+                .override_severity
+                .map_or(false, |severity| reason.severity <= severity)
+    }
+
+    pub fn approval_reasons(&self) -> impl Iterator<Item = &VerificationReason> {
+        self.reasons.iter().filter(|r| r.is_approval())
+    }
+
+    pub fn rejection_reasons(&self) -> impl Iterator<Item = &VerificationReason> {
+        self.reasons.iter().filter(|r| r.is_rejection())
+    }
+
+    pub fn to_proto(&self) -> VerificationVerdict {
+        let mut proto = VerificationVerdict::new();
+        proto.set_outcome(match self.outcome {
+            VerdictOutcome::Approve => VerificationVerdict_Outcome::APPROVE,
+            VerdictOutcome::Reject => VerificationVerdict_Outcome::REJECT,
+            VerdictOutcome::NeedsReview => VerificationVerdict_Outcome::NEEDS_REVIEW,
+        });
+        proto.set_score(self.score);
+        for reason in self.reasons.iter() {
+            proto
+                .mut_reasons()
+                .push(reason.to_proto(self.is_overridden(reason)));
         }
+        proto
     }
 }
 
@@ -47,29 +233,97 @@ impl VerificationContext {
         &self.member
     }
 
-    pub fn add_approval_reason(&mut self, reason: impl Into<String>) {
-        self.reasons
-            .push(VerificationReason::Approval(reason.into()));
+    pub fn add_reason(&mut self, reason: VerificationReason) {
+        self.reasons.push(reason);
     }
 
-    pub fn add_rejection_reason(&mut self, reason: impl Into<String>) {
-        self.reasons
-            .push(VerificationReason::Rejection(reason.into()));
+    pub fn reasons(&self) -> &[VerificationReason] {
+        &self.reasons
     }
 
-    pub fn add_reason(&mut self, reason: VerificationReason) {
-        self.reasons.push(reason);
+    pub fn verdict(&self) -> Verdict {
+        Verdict::new(self.reasons.clone())
     }
 
     pub fn is_approved(&self) -> bool {
-        self.reasons.last().map(|r| r.is_approval()).unwrap_or(true)
+        self.verdict().is_approved()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn approval(severity: Severity) -> VerificationReason {
+        VerificationReason::approval("test", severity, "")
+    }
+
+    fn rejection(severity: Severity) -> VerificationReason {
+        VerificationReason::rejection("test", severity, "")
+    }
+
+    #[test]
+    fn test_verdict_no_reasons_approves() {
+        let verdict = Verdict::new(Vec::new());
+        assert_eq!(verdict.outcome, VerdictOutcome::Approve);
+        assert_eq!(verdict.score, 0);
+    }
+
+    #[test]
+    fn test_verdict_low_severity_rejection_needs_review() {
+        let verdict = Verdict::new(vec![
+            rejection(Severity::Suspicion),
+            rejection(Severity::Questionable),
+        ]);
+        assert_eq!(verdict.outcome, VerdictOutcome::NeedsReview);
+        assert_eq!(verdict.score, -5);
+    }
+
+    #[test]
+    fn test_verdict_malicious_rejection_rejects() {
+        let verdict = Verdict::new(vec![
+            rejection(Severity::Suspicion),
+            rejection(Severity::Malicious),
+        ]);
+        assert_eq!(verdict.outcome, VerdictOutcome::Reject);
+    }
+
+    #[test]
+    fn test_verdict_approval_overrides_lower_severity() {
+        let reasons = vec![
+            rejection(Severity::Suspicion),
+            approval(Severity::Suspicion),
+            rejection(Severity::Questionable),
+        ];
+        let verdict = Verdict::new(reasons.clone());
+        assert_eq!(verdict.outcome, VerdictOutcome::NeedsReview);
+        assert_eq!(verdict.score, -3);
+        assert!(verdict.is_overridden(&reasons[0]));
+        assert!(!verdict.is_overridden(&reasons[2]));
     }
 
-    pub fn approval_reasons(&self) -> impl Iterator<Item = &str> {
-        self.reasons.iter().filter_map(|r| r.approval_reason())
+    #[test]
+    fn test_verdict_lockdown_holds_approved_users() {
+        let verdict = Verdict::new(vec![
+            approval(Severity::Override),
+            rejection(Severity::Lockdown),
+        ]);
+        assert_eq!(verdict.outcome, VerdictOutcome::NeedsReview);
+        assert_eq!(verdict.score, Severity::Override.weight());
     }
 
-    pub fn rejection_reasons(&self) -> impl Iterator<Item = &str> {
-        self.reasons.iter().filter_map(|r| r.approval_reason())
+    #[test]
+    fn test_verdict_is_order_independent() {
+        let forward = Verdict::new(vec![
+            rejection(Severity::Malicious),
+            approval(Severity::Override),
+        ]);
+        let backward = Verdict::new(vec![
+            approval(Severity::Override),
+            rejection(Severity::Malicious),
+        ]);
+        assert_eq!(forward.outcome, VerdictOutcome::Approve);
+        assert_eq!(backward.outcome, VerdictOutcome::Approve);
+        assert_eq!(forward.score, backward.score);
     }
 }
@@ -0,0 +1,183 @@
+use crate::Client;
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use hourai::models::id::*;
+use hourai::proto::{action::*, guild_configs::VerificationConfig};
+use hourai_redis::{CachedGuild, GuildConfig, RedisPool};
+use std::collections::HashSet;
+use tracing::{error, info};
+
+/// The minimum amount of time, in seconds, unvalidated users are allowed to stay in a guild.
+const MIN_KICK_AFTER: u64 = 3600;
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Periodically kicks members that have not been validated within the time configured by
+/// `VerificationConfig.kick_unvalidated_users_after`.
+pub async fn run_kick_unvalidated(client: Client, interval: std::time::Duration) {
+    let mut last_run: Option<DateTime<Utc>> = None;
+    loop {
+        let now = Utc::now();
+        match kick_unvalidated(&client, last_run, now).await {
+            Ok(()) => last_run = Some(now),
+            Err(err) => error!("Error while kicking unvalidated users: {}", err),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn kick_unvalidated(
+    client: &Client,
+    last_run: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let guild_ids = hourai_sql::Member::fetch_guild_ids()
+        .fetch_all(&client.sql)
+        .await?;
+    for (guild_id,) in guild_ids {
+        let guild_id = GuildId(guild_id as u64);
+        if let Err(err) = kick_guild_unvalidated(client, guild_id, last_run, now).await {
+            error!(
+                "Error while kicking unvalidated users from guild {}: {}",
+                guild_id, err
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Kicks all of the unvalidated members of a guild that joined after the option was enabled.
+/// Moderators and the owner of the guild are never kicked. In dry run mode, the members are
+/// only reported to the modlog, and only the ones that passed the deadline since the last run
+/// so that each member is reported once.
+async fn kick_guild_unvalidated(
+    client: &Client,
+    guild_id: GuildId,
+    last_run: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let mut redis = client.redis.clone();
+    let mut config =
+        GuildConfig::fetch_or_default::<VerificationConfig>(guild_id, &mut redis).await?;
+    if !config.get_enabled() || !config.has_role_id() || !config.has_kick_unvalidated_users_after()
+    {
+        if config.has_kick_unvalidated_users_since() {
+            config.clear_kick_unvalidated_users_since();
+            save_config(guild_id, config, &mut redis).await?;
+        }
+        return Ok(());
+    }
+    if !config.has_kick_unvalidated_users_since() {
+        config.set_kick_unvalidated_users_since(now.timestamp() as u64);
+        save_config(guild_id, config.clone(), &mut redis).await?;
+    }
+    if hourai_validation::is_locked_down(&config, now) {
+        return Ok(());
+    }
+
+    let kick_after = std::cmp::max(config.get_kick_unvalidated_users_after(), MIN_KICK_AFTER);
+    let kick_after = Duration::seconds(kick_after as i64);
+    let dry_run = config.get_kick_unvalidated_users_dry_run();
+    let enabled_at = Utc.timestamp(config.get_kick_unvalidated_users_since() as i64, 0);
+    let joined_after = match last_run {
+        Some(time) if dry_run => std::cmp::max(enabled_at, time - kick_after),
+        _ => enabled_at,
+    };
+    let members = hourai_sql::Member::fetch_without_role(
+        guild_id,
+        RoleId(config.get_role_id()),
+        Some(joined_after),
+        now - kick_after,
+    )
+    .fetch_all(&client.sql)
+    .await?;
+    if members.is_empty() {
+        return Ok(());
+    }
+
+    let mod_roles: HashSet<RoleId> = CachedGuild::fetch_moderator_roles(guild_id, &mut redis)
+        .await?
+        .into_iter()
+        .collect();
+    let owner_id = CachedGuild::fetch_owner_id(guild_id, &mut redis).await?;
+    let members: Vec<_> = members
+        .into_iter()
+        .filter(|member| Some(member.user_id()) != owner_id)
+        .filter(|member| !member.role_ids().any(|id| mod_roles.contains(&id)))
+        .collect();
+    if members.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "Found {} unvalidated members in guild {} (dry run: {})",
+        members.len(),
+        guild_id,
+        dry_run
+    );
+    let reason = format!(
+        "Unvalidated for more than {}.",
+        humantime::format_duration(kick_after.to_std()?)
+    );
+    let mut lines = Vec::new();
+    for member in members {
+        let user_id = member.user_id();
+        if dry_run {
+            lines.push(format!(
+                "[Dry Run] Would have kicked <@{}> ({}): {}",
+                user_id, user_id, reason
+            ));
+            continue;
+        }
+
+        let mut action = Action::new();
+        action.set_guild_id(guild_id.0);
+        action.set_user_id(user_id.0);
+        action.set_reason(reason.clone());
+        action.mut_kick();
+        lines.push(match client.actions.execute(action).await.outcome {
+            Ok(_) => format!("Kicked <@{}> ({}): {}", user_id, user_id, reason),
+            Err(err) => format!(":x: Failed to kick <@{}> ({}): {}", user_id, user_id, err),
+        });
+    }
+
+    send_modlog_lines(client, guild_id, lines).await
+}
+
+async fn save_config(
+    guild_id: GuildId,
+    config: VerificationConfig,
+    redis: &mut RedisPool,
+) -> Result<()> {
+    GuildConfig::set(guild_id, config)
+        .query_async::<RedisPool, ()>(redis)
+        .await?;
+    Ok(())
+}
+
+/// Sends lines of text to the modlog, splitting them across as few messages as possible.
+async fn send_modlog_lines(client: &Client, guild_id: GuildId, lines: Vec<String>) -> Result<()> {
+    let channel_id = match client.get_modlog_channel(guild_id).await? {
+        Some(channel_id) => channel_id,
+        None => return Ok(()),
+    };
+
+    let mut messages: Vec<String> = Vec::new();
+    for line in lines {
+        match messages.last_mut() {
+            Some(message) if message.len() + line.len() < MAX_MESSAGE_LENGTH => {
+                message.push('\n');
+                message.push_str(&line);
+            }
+            _ => messages.push(line.chars().take(MAX_MESSAGE_LENGTH).collect()),
+        }
+    }
+
+    for message in messages {
+        client
+            .http_client
+            .create_message(channel_id)
+            .content(message)?
+            .await?;
+    }
+    Ok(())
+}
@@ -3,13 +3,19 @@ extern crate lazy_static;
 
 mod approvers;
 mod context;
+mod lockdown;
+mod pipeline;
 mod rejectors;
 
-use self::context::VerificationContext;
+pub use self::context::{
+    ReasonKind, Severity, Verdict, VerdictOutcome, VerificationContext, VerificationReason,
+};
+pub use self::lockdown::{is_locked_down, lockdown_expiration, RaidDetector};
+pub use self::pipeline::{VerifierDeps, VerifierPipeline};
 use anyhow::Result;
 use async_trait::async_trait;
 
-pub type BoxedVerifier = Box<dyn Verifier + Sync + 'static>;
+pub type BoxedVerifier = Box<dyn Verifier + Send + Sync + 'static>;
 
 #[async_trait]
 pub trait Verifier {
@@ -28,31 +34,35 @@ impl Verifier for Vec<BoxedVerifier> {
 
 pub struct GenericVerifier {
     pub reason: context::VerificationReason,
-    pub pred: Box<dyn Fn(&VerificationContext) -> Result<bool> + Sync + 'static>,
+    pub pred: Box<dyn Fn(&VerificationContext) -> Result<bool> + Send + Sync + 'static>,
 }
 
 impl GenericVerifier {
-    pub fn new_approver<T: Fn(&VerificationContext) -> Result<bool> + Sync + 'static>(
+    pub fn new_approver<T: Fn(&VerificationContext) -> Result<bool> + Send + Sync + 'static>(
+        verifier: &'static str,
+        severity: Severity,
         reason: impl Into<String>,
         approver: T,
     ) -> BoxedVerifier {
         Self::new(
-            context::VerificationReason::Approval(reason.into()),
+            VerificationReason::approval(verifier, severity, reason),
             approver,
         )
     }
 
-    pub fn new_rejector<T: Fn(&VerificationContext) -> Result<bool> + Sync + 'static>(
+    pub fn new_rejector<T: Fn(&VerificationContext) -> Result<bool> + Send + Sync + 'static>(
+        verifier: &'static str,
+        severity: Severity,
         reason: impl Into<String>,
         approver: T,
     ) -> BoxedVerifier {
         Self::new(
-            context::VerificationReason::Rejection(reason.into()),
+            VerificationReason::rejection(verifier, severity, reason),
             approver,
         )
     }
 
-    fn new<T: Fn(&VerificationContext) -> Result<bool> + Sync + 'static>(
+    fn new<T: Fn(&VerificationContext) -> Result<bool> + Send + Sync + 'static>(
         reason: context::VerificationReason,
         approver: T,
     ) -> BoxedVerifier {
@@ -0,0 +1,77 @@
+use crate::*;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use dashmap::DashMap;
+use hourai::models::id::GuildId;
+use hourai::proto::guild_configs::{RaidDetectionConfig, VerificationConfig};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Gets the time a guild's lockdown will be lifted, if it is currently locked down.
+pub fn lockdown_expiration(
+    config: &VerificationConfig,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if !config.has_lockdown_expiration() {
+        return None;
+    }
+    let expiration = Utc.timestamp(config.get_lockdown_expiration() as i64, 0);
+    if expiration > now {
+        Some(expiration)
+    } else {
+        None
+    }
+}
+
+pub fn is_locked_down(config: &VerificationConfig, now: DateTime<Utc>) -> bool {
+    lockdown_expiration(config, now).is_some()
+}
+
+pub(super) fn lockdown(expiration: DateTime<Utc>) -> BoxedVerifier {
+    GenericVerifier::new_rejector(
+        "lockdown",
+        Severity::Lockdown,
+        format!(
+            "Server is under lockdown until {}. All new users must be manually verified.",
+            expiration.to_rfc2822()
+        ),
+        |_| Ok(true),
+    )
+}
+
+/// Tracks the rate that users join each guild to detect raids. Join history is only kept in
+/// memory, so each process tracks the joins it sees independently.
+#[derive(Clone, Default)]
+pub struct RaidDetector {
+    joins: Arc<DashMap<GuildId, VecDeque<DateTime<Utc>>>>,
+}
+
+impl RaidDetector {
+    /// Records a user joining a guild. Returns the number of joins within the configured window
+    /// if it has reached the threshold for a raid, in which case the guild's join history is
+    /// reset.
+    pub fn record_join(
+        &self,
+        guild_id: GuildId,
+        config: &RaidDetectionConfig,
+        timestamp: DateTime<Utc>,
+    ) -> Option<usize> {
+        if config.get_join_threshold() == 0 {
+            return None;
+        }
+
+        let window_start = timestamp - Duration::seconds(config.get_window() as i64);
+        let mut joins = self.joins.entry(guild_id).or_insert_with(VecDeque::new);
+        joins.push_back(timestamp);
+        while joins.front().map_or(false, |join| *join < window_start) {
+            joins.pop_front();
+        }
+
+        let count = joins.len();
+        if count >= config.get_join_threshold() as usize {
+            joins.clear();
+            Some(count)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,349 @@
+mod kick;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::StreamExt;
+use hourai::{
+    actions::{ActionExecutor, ActionResult},
+    config,
+    gateway::{cluster::*, Event, EventTypeFlags, Intents},
+    init,
+    models::{channel::embed::Embed, guild::Member, id::*, Snowflake, UserLike},
+    proto::action::*,
+    proto::guild_configs::VerificationConfig,
+};
+use hourai_redis::{CachedGuild, GuildConfig, OnlineStatus, RedisPool};
+use hourai_sql::SqlPool;
+use hourai_validation::{
+    RaidDetector, Verdict, VerdictOutcome, VerificationContext, VerificationReason, Verifier,
+    VerifierDeps, VerifierPipeline,
+};
+use tracing::{error, info};
+use twilight_embed_builder::*;
+
+const BOT_INTENTS: Intents =
+    Intents::from_bits_truncate(Intents::GUILDS.bits() | Intents::GUILD_MEMBERS.bits());
+
+const BOT_EVENTS: EventTypeFlags = EventTypeFlags::from_bits_truncate(
+    EventTypeFlags::READY.bits() | EventTypeFlags::MEMBER_ADD.bits(),
+);
+
+/// The maximum length of an embed field's value.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+#[tokio::main]
+async fn main() {
+    let config = config::load_config(config::get_config_path().as_ref());
+    init::init(&config);
+
+    let http_client = init::http_client(&config);
+    let sql = hourai_sql::init(&config).await;
+    let redis = hourai_redis::init(&config).await;
+    let gateway = init::cluster(&config, BOT_INTENTS)
+        .shard_scheme(ShardScheme::Auto)
+        .http_client(http_client.clone())
+        .build()
+        .await
+        .expect("Failed to connect to the Discord gateway");
+
+    let bot_owners = fetch_bot_owners(&http_client)
+        .await
+        .expect("Failed to fetch the owners of the bot");
+    let verifier_deps = VerifierDeps::new(&config, sql.clone(), bot_owners)
+        .expect("Failed to load verifier dependencies");
+    let client = Client {
+        actions: ActionExecutor::new(http_client.clone()),
+        http_client,
+        verifier_deps,
+        raid_detector: RaidDetector::default(),
+        sql,
+        redis,
+    };
+
+    tokio::spawn(kick::run_kick_unvalidated(
+        client.clone(),
+        std::time::Duration::from_secs(300),
+    ));
+
+    info!("Starting gateway...");
+    gateway.up().await;
+    info!("Client started.");
+
+    let mut events = gateway.some_events(BOT_EVENTS);
+    while let Some((_, evt)) = events.next().await {
+        tokio::spawn(client.clone().consume_event(evt));
+    }
+
+    info!("Shutting down gateway...");
+    gateway.down();
+    info!("Client stopped.");
+}
+
+#[derive(Clone)]
+pub struct Client {
+    pub http_client: hourai::http::Client,
+    pub actions: ActionExecutor,
+    pub verifier_deps: VerifierDeps,
+    pub raid_detector: RaidDetector,
+    pub sql: SqlPool,
+    pub redis: RedisPool,
+}
+
+impl Client {
+    async fn consume_event(self, event: Event) {
+        let kind = event.kind();
+        let result = match event {
+            Event::Ready(_) => Ok(()),
+            Event::MemberAdd(evt) => self.on_member_add(evt.0).await,
+            _ => {
+                error!("Unexpected event type: {:?}", event);
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            error!("Error while running event with {:?}: {}", kind, err);
+        }
+    }
+
+    async fn on_member_add(&self, member: Member) -> Result<()> {
+        let mut redis = self.redis.clone();
+        let mut config =
+            GuildConfig::fetch_or_default::<VerificationConfig>(member.guild_id, &mut redis)
+                .await?;
+        if !config.get_enabled() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        if config.has_raid_detection() && !hourai_validation::is_locked_down(&config, now) {
+            let detection = config.get_raid_detection();
+            if let Some(joins) = self
+                .raid_detector
+                .record_join(member.guild_id, detection, now)
+            {
+                let window = Duration::seconds(detection.get_window() as i64);
+                let expiration = now + Duration::seconds(detection.get_lockdown_duration() as i64);
+                config.set_lockdown_expiration(expiration.timestamp() as u64);
+                GuildConfig::set(member.guild_id, config.clone())
+                    .query_async::<RedisPool, ()>(&mut redis)
+                    .await?;
+                self.report_raid(member.guild_id, joins, window, expiration)
+                    .await?;
+            }
+        }
+
+        let pipeline = match VerifierPipeline::from_config(&config, &self.verifier_deps) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                self.report_invalid_config(&member, &err).await?;
+                return Err(err);
+            }
+        };
+        let mut ctx = VerificationContext::new(member);
+        pipeline.verify(&mut ctx).await?;
+        let verdict = ctx.verdict();
+        let member = ctx.member();
+
+        hourai_sql::verification::VerdictEntry::insert(
+            member.guild_id,
+            member.user.id,
+            now,
+            verdict.to_proto(),
+        )
+        .execute(&self.sql)
+        .await?;
+
+        let role_result = if verdict.is_approved() && config.has_role_id() {
+            Some(self.grant_role(member, RoleId(config.get_role_id())).await)
+        } else {
+            None
+        };
+
+        self.send_report(&config, member, &verdict, role_result)
+            .await
+    }
+
+    /// Reports a verification config that could not be loaded, i.e. one with an invalid
+    /// username filter, so that moderators know to fix it and to verify the member manually.
+    async fn report_invalid_config(&self, member: &Member, err: &anyhow::Error) -> Result<()> {
+        let channel_id = match self.get_modlog_channel(member.guild_id).await? {
+            Some(channel_id) => channel_id,
+            None => return Ok(()),
+        };
+        let content = format!(
+            ":x: Could not verify <@{}> ({}). {}",
+            member.user.id, member.user.id, err
+        );
+        self.http_client
+            .create_message(channel_id)
+            .content(content)?
+            .await?;
+        Ok(())
+    }
+
+    async fn grant_role(&self, member: &Member, role_id: RoleId) -> ActionResult {
+        let mut action = Action::new();
+        action.set_guild_id(member.guild_id.0);
+        action.set_user_id(member.user.id.0);
+        action.set_reason("Verified user.".to_owned());
+        let change_role = action.mut_change_role();
+        change_role.set_field_type(StatusType::APPLY);
+        change_role.mut_role_ids().push(role_id.0);
+        self.actions.execute(action).await
+    }
+
+    async fn send_report(
+        &self,
+        config: &VerificationConfig,
+        member: &Member,
+        verdict: &Verdict,
+        role_result: Option<ActionResult>,
+    ) -> Result<()> {
+        let channel_id = match self.get_modlog_channel(member.guild_id).await? {
+            Some(channel_id) => channel_id,
+            None => return Ok(()),
+        };
+
+        let user = &member.user;
+        let mut content = if verdict.is_approved() {
+            format!("Verified user: <@{}> ({}).", user.id, user.id)
+        } else {
+            let mut content = format!(
+                "User <@{}> ({}) requires manual verification.",
+                user.id, user.id
+            );
+            if verdict.outcome == VerdictOutcome::Reject {
+                content.push_str(" User matches known malicious users.");
+            }
+            if config.get_ping_moderator_on_fail() {
+                let mention = self.mention_online_moderator(member.guild_id).await?;
+                content = format!("{} {}", mention, content);
+            }
+            content
+        };
+        if let Some(Err(err)) = role_result.map(|result| result.outcome) {
+            content.push_str(&format!("\n:x: Failed to add verification role: {}", err));
+        }
+
+        self.http_client
+            .create_message(channel_id)
+            .content(content)?
+            .embed(Self::report_embed(member, verdict)?)?
+            .await?;
+        Ok(())
+    }
+
+    fn report_embed(member: &Member, verdict: &Verdict) -> Result<Embed> {
+        let user = &member.user;
+        let (outcome, color) = match verdict.outcome {
+            VerdictOutcome::Approve => ("Approved", 0x2ecc71),
+            VerdictOutcome::NeedsReview => ("Needs Review", 0xf1c40f),
+            VerdictOutcome::Reject => ("Rejected", 0xe74c3c),
+        };
+        let mut builder = EmbedBuilder::new()
+            .title(user.display_name())?
+            .color(color)?
+            .thumbnail(ImageSource::url(user.avatar_url())?)
+            .field(EmbedFieldBuilder::new(
+                "Verdict",
+                format!("{} (Score: {})", outcome, verdict.score),
+            )?)
+            .field(EmbedFieldBuilder::new(
+                "Account Created",
+                user.created_at().to_rfc2822(),
+            )?)
+            .footer(EmbedFooterBuilder::new(user.id.to_string())?);
+
+        let approvals = reason_list(verdict, verdict.approval_reasons());
+        if !approvals.is_empty() {
+            builder = builder.field(EmbedFieldBuilder::new("Approval Reasons", approvals)?);
+        }
+        let rejections = reason_list(verdict, verdict.rejection_reasons());
+        if !rejections.is_empty() {
+            builder = builder.field(EmbedFieldBuilder::new("Rejection Reasons", rejections)?);
+        }
+        Ok(builder.build()?)
+    }
+
+    async fn report_raid(
+        &self,
+        guild_id: GuildId,
+        joins: usize,
+        window: Duration,
+        expiration: DateTime<Utc>,
+    ) -> Result<()> {
+        let channel_id = match self.get_modlog_channel(guild_id).await? {
+            Some(channel_id) => channel_id,
+            None => return Ok(()),
+        };
+        let mention = self.mention_online_moderator(guild_id).await?;
+        let content = format!(
+            "{} Possible raid detected: {} users joined in the last {}. Locking down the server \
+             until {}. All new users will require manual verification.",
+            mention,
+            joins,
+            humantime::format_duration(window.to_std()?),
+            expiration.to_rfc2822()
+        );
+        self.http_client
+            .create_message(channel_id)
+            .content(content)?
+            .await?;
+        Ok(())
+    }
+
+    async fn get_modlog_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>> {
+        let mut redis = self.redis.clone();
+        GuildConfig::fetch_modlog_channel(guild_id, &mut redis).await
+    }
+
+    /// Creates a mention for a random online moderator. If no moderator is online, the owner of
+    /// the guild is mentioned instead.
+    async fn mention_online_moderator(&self, guild_id: GuildId) -> Result<String> {
+        let mut redis = self.redis.clone();
+        let mod_roles = CachedGuild::fetch_moderator_roles(guild_id, &mut redis).await?;
+        let online = OnlineStatus::fetch_online(guild_id, &mut redis).await?;
+        let moderators: Vec<UserId> = hourai_sql::Member::fetch_with_roles(guild_id, &mod_roles)
+            .fetch_all(&self.sql)
+            .await?
+            .iter()
+            .map(|member| member.user_id())
+            .filter(|id| online.contains(id))
+            .collect();
+        let owner_id = CachedGuild::fetch_owner_id(guild_id, &mut redis).await?;
+        Ok(hourai::moderation::mention_moderator(&moderators, owner_id))
+    }
+}
+
+/// Fetches the owners of the bot: the members of its team if it has one, otherwise the owner of
+/// the application.
+async fn fetch_bot_owners(http_client: &hourai::http::Client) -> Result<Vec<UserId>> {
+    let info = http_client.current_user_application().await?;
+    Ok(match info.team {
+        Some(team) => team.members.iter().map(|member| member.user.id).collect(),
+        None => vec![info.owner.id],
+    })
+}
+
+/// Formats a list of reasons for the report embed. Overridden rejections are struck through.
+fn reason_list<'a>(
+    verdict: &Verdict,
+    reasons: impl Iterator<Item = &'a VerificationReason>,
+) -> String {
+    let list = reasons
+        .map(|reason| {
+            let mut line = format!("[{}] {}", reason.verifier, reason.message);
+            if let Some(ref evidence) = reason.evidence {
+                line.push_str(&format!(" (`{}`)", evidence));
+            }
+            if verdict.is_overridden(reason) {
+                format!("- ~~{}~~", line)
+            } else {
+                format!("- {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    list.chars().take(MAX_FIELD_LENGTH).collect()
+}
@@ -0,0 +1,170 @@
+use crate::rejectors::UsernameMatchRejector;
+use crate::*;
+use chrono::{Duration, Utc};
+use hourai::config::{self, HouraiConfig};
+use hourai::models::id::UserId;
+use hourai::proto::guild_configs::*;
+use hourai::proto::util::FilterSettings;
+use hourai_sql::SqlPool;
+use regex::RegexSet;
+
+/// External dependencies shared by the verifiers in a pipeline. Expensive to create, as all of
+/// the filter lists are loaded and compiled upfront, but cheap to clone.
+#[derive(Clone)]
+pub struct VerifierDeps {
+    pub sql: SqlPool,
+    /// Users who own the bot. Members in this list are always approved.
+    pub bot_owners: Vec<UserId>,
+    user_bot_names: UsernameMatchRejector,
+    user_bot_names_fullmatch: UsernameMatchRejector,
+    offensive_usernames: UsernameMatchRejector,
+    sexual_usernames: UsernameMatchRejector,
+}
+
+impl VerifierDeps {
+    pub fn new(config: &HouraiConfig, sql: SqlPool, bot_owners: Vec<UserId>) -> Result<Self> {
+        let fullmatch = config::load_list(config, "user_bot_names_fullmatch")
+            .into_iter()
+            .map(|name| format!("^{}$", name))
+            .collect();
+        Ok(Self {
+            user_bot_names: UsernameMatchRejector::new(
+                sql.clone(),
+                "user_bot_names",
+                Severity::Suspicion,
+                "Likely user bot.",
+                config::load_list(config, "user_bot_names"),
+            )?,
+            user_bot_names_fullmatch: UsernameMatchRejector::new(
+                sql.clone(),
+                "user_bot_names_fullmatch",
+                Severity::Suspicion,
+                "Likely user bot.",
+                fullmatch,
+            )?,
+            offensive_usernames: UsernameMatchRejector::new(
+                sql.clone(),
+                "offensive_username",
+                Severity::Questionable,
+                "Offensive username.",
+                config::load_list(config, "offensive_usernames"),
+            )?,
+            sexual_usernames: UsernameMatchRejector::new(
+                sql.clone(),
+                "sexual_username",
+                Severity::Questionable,
+                "Sexually inappropriate username.",
+                config::load_list(config, "sexually_inappropriate_usernames"),
+            )?,
+            bot_owners,
+            sql,
+        })
+    }
+}
+
+/// A chain of verifiers. Each verifier adds its reasons to the context; the final result is
+/// decided by `VerificationContext::verdict`, which does not depend on the order of the chain.
+#[derive(Default)]
+pub struct VerifierPipeline {
+    verifiers: Vec<BoxedVerifier>,
+}
+
+impl VerifierPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the pipeline used to verify new members of a guild with the given config.
+    ///
+    /// Verifiers are grouped by the `Severity` of the reasons they produce. Approvers can
+    /// override the rejections of their own level and any level before it.
+    pub fn from_config(config: &VerificationConfig, deps: &VerifierDeps) -> Result<Self> {
+        let mut pipeline = Self::new();
+        let username = config.get_username();
+
+        if let Some(expiration) = lockdown::lockdown_expiration(config, Utc::now()) {
+            pipeline.push(lockdown::lockdown(expiration));
+        }
+
+        // Suspicion Level
+        if config.get_minimum_account_age() > 0 {
+            let lookback = Duration::seconds(config.get_minimum_account_age() as i64);
+            pipeline.push(rejectors::new_account(lookback));
+        }
+        if config.get_avatar().get_reject_default_avatars() {
+            pipeline.push(rejectors::no_avatar());
+        }
+        pipeline.push(rejectors::deleted_user(deps.sql.clone()));
+        if username.get_reject_likely_user_bots() {
+            pipeline.push(Box::new(deps.user_bot_names.clone()));
+            pipeline.push(Box::new(deps.user_bot_names_fullmatch.clone()));
+        }
+        pipeline.push(approvers::nitro());
+
+        // Questionable Level
+        if username.has_username_filter() {
+            pipeline.push(username_filter(username.get_username_filter())?);
+        }
+        if username.get_reject_offensive_usernames() {
+            pipeline.push(Box::new(deps.offensive_usernames.clone()));
+        }
+        if username.get_reject_sexual_usernames() {
+            pipeline.push(Box::new(deps.sexual_usernames.clone()));
+        }
+
+        // Malicious Level
+        let cross_server = config.get_cross_server();
+        if cross_server.get_reject_banned_users() {
+            pipeline.push(rejectors::banned_user(
+                deps.sql.clone(),
+                cross_server.get_minimum_guild_size(),
+            ));
+        }
+        pipeline.push(rejectors::banned_username(deps.sql.clone()));
+        pipeline.push(approvers::distinguished_user());
+
+        // Override Level
+        pipeline.push(approvers::bot());
+        if !deps.bot_owners.is_empty() {
+            pipeline.push(approvers::bot_owners(deps.bot_owners.iter().cloned()));
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Adds a verifier to the end of the pipeline.
+    pub fn push(&mut self, verifier: BoxedVerifier) {
+        self.verifiers.push(verifier);
+    }
+
+    pub fn len(&self) -> usize {
+        self.verifiers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verifiers.is_empty()
+    }
+}
+
+#[async_trait]
+impl Verifier for VerifierPipeline {
+    async fn verify(&self, ctx: &mut VerificationContext) -> Result<()> {
+        self.verifiers.verify(ctx).await
+    }
+}
+
+/// Rejects users whose username matches the blacklist, unless it also matches the whitelist.
+fn username_filter(filter: &FilterSettings) -> Result<BoxedVerifier> {
+    let invalid = |err| anyhow::anyhow!("Invalid username filter: {}", err);
+    let blacklist = RegexSet::new(filter.get_blacklist()).map_err(invalid)?;
+    let whitelist = RegexSet::new(filter.get_whitelist()).map_err(invalid)?;
+    Ok(GenericVerifier::new_rejector(
+        "username_filter",
+        Severity::Questionable,
+        "Username matches the server's username filter.",
+        move |ctx| {
+            let name = ctx.member().user.name.as_str();
+            Ok(blacklist.is_match(name) && !whitelist.is_match(name))
+        },
+    ))
+}
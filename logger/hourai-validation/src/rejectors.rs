@@ -6,6 +6,7 @@ use dashmap::DashMap;
 use hourai::models::{user::User, Snowflake};
 use hourai_sql::{Ban, SqlPool, Username, VerificationBan};
 use regex::Regex;
+use std::sync::Arc;
 
 lazy_static! {
     static ref DELETED_USERNAME_MATCH: Regex = Regex::new("Deleted User [0-9a-fA-F]{8}").unwrap();
@@ -22,11 +23,11 @@ struct DeletedUserRejector(SqlPool);
 impl Verifier for DeletedUserRejector {
     async fn verify(&self, ctx: &mut context::VerificationContext) -> Result<()> {
         if is_user_deleted(&ctx.member().user) {
-            ctx.add_rejection_reason(
+            ctx.add_reason(deleted_user_reason(
                 "Deleted users cannot be active on Discord. User has been \
                 deleted by Discord of their own accord or for Trust and \
                 Safety reasons, or is faking account deletion.",
-            );
+            ));
         }
 
         let usernames: Vec<Username> = Username::fetch(ctx.member().user.id, None)
@@ -37,19 +38,25 @@ impl Verifier for DeletedUserRejector {
             let name = username.name.as_str();
             let is_deleted = DELETED_USERNAME_MATCH.is_match(name);
             if !is_deleted && LOOSE_DELETED_USERNAME_MATCH.is_match(name) {
-                ctx.add_rejection_reason(format!(
-                    "\"{}\" does not match Discord\'s deletion patterns. User may have \
+                ctx.add_reason(
+                    deleted_user_reason(format!(
+                        "\"{}\" does not match Discord\'s deletion patterns. User may have \
                              attemtped to fake account deletion.",
-                    name
-                ));
+                        name
+                    ))
+                    .with_evidence(name),
+                );
             } else if is_deleted && username.discriminator.map(|d| d < 100).unwrap_or(false) {
-                ctx.add_rejection_reason(format!(
-                    "\"{}#{:04}\" has a unusual discriminator for a deleted user. These \
+                let tag = format!("{}#{:04}", name, username.discriminator.unwrap());
+                ctx.add_reason(
+                    deleted_user_reason(format!(
+                        "\"{}\" has a unusual discriminator for a deleted user. These \
                              are randomly generated. User may have attemtped to fake account \
                              deletion.",
-                    name,
-                    username.discriminator.unwrap()
-                ));
+                        tag
+                    ))
+                    .with_evidence(tag),
+                );
             }
         }
 
@@ -57,6 +64,10 @@ impl Verifier for DeletedUserRejector {
     }
 }
 
+fn deleted_user_reason(message: impl Into<String>) -> VerificationReason {
+    VerificationReason::rejection("deleted_user", Severity::Suspicion, message)
+}
+
 struct BannedUserRejector {
     sql: SqlPool,
     min_guild_size: u64,
@@ -69,6 +80,7 @@ impl Verifier for BannedUserRejector {
             .fetch_all(&self.sql)
             .await?;
 
+        let mut guilds: Vec<String> = Vec::new();
         let mut reasons: Vec<Option<String>> = Vec::new();
         for ban in bans {
             let count = hourai_sql::Member::count_guild_members(
@@ -78,6 +90,7 @@ impl Verifier for BannedUserRejector {
             .fetch_one(&self.sql)
             .await?;
             if count.0 as u64 >= self.min_guild_size {
+                guilds.push(ban.guild_id().to_string());
                 reasons.push(ban.reason);
             }
         }
@@ -93,7 +106,10 @@ impl Verifier for BannedUserRejector {
                 reason.push_str(" for the following reasons\n");
                 reason.push_str(list.as_str());
             }
-            ctx.add_rejection_reason(reason);
+            ctx.add_reason(
+                VerificationReason::rejection("banned_user", Severity::Malicious, reason)
+                    .with_evidence(guilds.join(", ")),
+            );
         }
 
         Ok(())
@@ -117,7 +133,7 @@ impl Verifier for BannedUsernameRejector {
             if let Some(ban_reason) = ban.reason {
                 reason.push_str(format!(" (Ban Reason: {})", ban_reason).as_str());
             }
-            ctx.add_rejection_reason(reason);
+            ctx.add_reason(banned_username_reason(reason).with_evidence(ban.name));
         }
 
         if ctx.member().user.avatar.is_none() {
@@ -138,16 +154,23 @@ impl Verifier for BannedUsernameRejector {
             if let Some(ban_reason) = ban.reason {
                 reason.push_str(format!(" (Ban Reason: {})", ban_reason).as_str());
             }
-            ctx.add_rejection_reason(reason);
+            let avatar = ctx.member().user.avatar.clone().unwrap();
+            ctx.add_reason(banned_username_reason(reason).with_evidence(avatar));
         }
 
         Ok(())
     }
 }
 
+fn banned_username_reason(message: String) -> VerificationReason {
+    VerificationReason::rejection("banned_username", Severity::Malicious, message)
+}
+
 #[async_trait]
 pub trait StringMatchRejector: Sync {
     type Key;
+    fn verifier(&self) -> &'static str;
+    fn severity(&self) -> Severity;
     fn regexes(&self) -> Vec<(Self::Key, Regex)>;
     async fn criteria(&self, ctx: &context::VerificationContext) -> Result<Vec<String>>;
     fn reason(&self, key: &Self::Key, matched: &str) -> String;
@@ -162,7 +185,10 @@ impl<T: StringMatchRejector> Verifier for T {
             for (key, regex) in &regexes {
                 if regex.find(check.as_str()).is_some() {
                     let reason = self.reason(&key, check.as_str());
-                    ctx.add_rejection_reason(reason);
+                    ctx.add_reason(
+                        VerificationReason::rejection(self.verifier(), self.severity(), reason)
+                            .with_evidence(check.as_str()),
+                    );
                 }
             }
         }
@@ -171,17 +197,28 @@ impl<T: StringMatchRejector> Verifier for T {
     }
 }
 
+#[derive(Clone)]
 pub struct UsernameMatchRejector {
     sql: SqlPool,
-    matches: DashMap<String, Regex>,
+    verifier: &'static str,
+    severity: Severity,
+    matches: Arc<DashMap<String, Regex>>,
     prefix: String,
 }
 
 impl UsernameMatchRejector {
-    pub fn new(sql: SqlPool, prefix: impl Into<String>, matches: Vec<String>) -> Result<Self> {
+    pub fn new(
+        sql: SqlPool,
+        verifier: &'static str,
+        severity: Severity,
+        prefix: impl Into<String>,
+        matches: Vec<String>,
+    ) -> Result<Self> {
         Ok(Self {
             sql,
-            matches: Self::compile(matches)?,
+            verifier,
+            severity,
+            matches: Arc::new(Self::compile(matches)?),
             prefix: prefix.into(),
         })
     }
@@ -214,6 +251,14 @@ impl UsernameMatchRejector {
 impl StringMatchRejector for UsernameMatchRejector {
     type Key = String;
 
+    fn verifier(&self) -> &'static str {
+        self.verifier
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
     fn regexes(&self) -> Vec<(Self::Key, Regex)> {
         self.matches
             .iter()
@@ -238,15 +283,20 @@ impl StringMatchRejector for UsernameMatchRejector {
 pub(super) fn new_account(lookback: Duration) -> BoxedVerifier {
     let human_lookback = humantime::format_duration(lookback.to_std().unwrap());
     GenericVerifier::new_rejector(
+        "new_account",
+        Severity::Suspicion,
         format!("Account created less than {} ago.", human_lookback),
-        move |ctx| Ok(ctx.member().created_at() - Utc::now() < lookback),
+        move |ctx| Ok(Utc::now() - ctx.member().created_at() < lookback),
     )
 }
 
 pub(super) fn no_avatar() -> BoxedVerifier {
-    GenericVerifier::new_rejector("User has no avatar.", move |ctx| {
-        Ok(ctx.member().user.avatar.is_none())
-    })
+    GenericVerifier::new_rejector(
+        "no_avatar",
+        Severity::Suspicion,
+        "User has no avatar.",
+        move |ctx| Ok(ctx.member().user.avatar.is_none()),
+    )
 }
 
 pub(super) fn banned_user(sql: SqlPool, min_guild_size: u64) -> BoxedVerifier {
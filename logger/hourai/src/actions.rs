@@ -0,0 +1,419 @@
+use crate::models::id::*;
+use crate::proto::action::*;
+use anyhow::Result;
+use http::status::StatusCode;
+use thiserror::Error;
+use twilight_http::request::AuditLogReason;
+use twilight_http::Error as HttpError;
+
+type ExecuteResult = std::result::Result<ActionOutcome, ActionError>;
+
+/// The result of executing a single action.
+#[derive(Debug)]
+pub struct ActionResult {
+    pub action: Action,
+    pub outcome: ExecuteResult,
+}
+
+impl ActionResult {
+    pub fn is_success(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The action was applied as requested.
+    Applied,
+    /// The action did not need to be applied. For example, removing a role the member does
+    /// not have, or targetting a user that is no longer in the server.
+    Skipped(&'static str),
+}
+
+/// The sum type of all errors that might result from executing an action.
+#[derive(Error, Debug)]
+pub enum ActionError {
+    #[error("Action is missing a required field: {}", .0)]
+    MissingField(&'static str),
+    #[error("Action type is not supported by this executor: {}", .0)]
+    Unsupported(&'static str),
+    #[error("Failed to execute action: {}", .0)]
+    Failed(#[from] anyhow::Error),
+}
+
+/// Gets the name of the type of action.
+pub fn action_type(action: &Action) -> &'static str {
+    match action.details {
+        Some(Action_oneof_details::kick(_)) => "kick",
+        Some(Action_oneof_details::ban(_)) => "ban",
+        Some(Action_oneof_details::escalate(_)) => "escalate",
+        Some(Action_oneof_details::mute(_)) => "mute",
+        Some(Action_oneof_details::deafen(_)) => "deafen",
+        Some(Action_oneof_details::change_role(_)) => "change_role",
+        Some(Action_oneof_details::direct_message(_)) => "direct_message",
+        Some(Action_oneof_details::send_message(_)) => "send_message",
+        Some(Action_oneof_details::command(_)) => "command",
+        None => "none",
+    }
+}
+
+/// Creates the action that undoes a given action, if one exists. The created action will not
+/// have a duration.
+pub fn invert_action(action: &Action) -> Option<Action> {
+    fn invert_status(status: StatusType) -> StatusType {
+        match status {
+            StatusType::APPLY => StatusType::UNAPPLY,
+            StatusType::UNAPPLY => StatusType::APPLY,
+            StatusType::TOGGLE => StatusType::TOGGLE,
+        }
+    }
+
+    let mut inverted = action.clone();
+    inverted.clear_duration();
+    if action.has_reason() {
+        inverted.set_reason(format!("Undo: {}", action.get_reason()));
+    }
+
+    match inverted.details {
+        Some(Action_oneof_details::ban(ref mut ban)) => match ban.get_field_type() {
+            BanMember_Type::BAN => ban.set_field_type(BanMember_Type::UNBAN),
+            BanMember_Type::UNBAN => ban.set_field_type(BanMember_Type::BAN),
+            BanMember_Type::SOFTBAN => return None,
+        },
+        Some(Action_oneof_details::change_role(ref mut change)) => {
+            change.set_field_type(invert_status(change.get_field_type()))
+        }
+        Some(Action_oneof_details::mute(ref mut mute)) => {
+            mute.set_field_type(invert_status(mute.get_field_type()))
+        }
+        Some(Action_oneof_details::deafen(ref mut deafen)) => {
+            deafen.set_field_type(invert_status(deafen.get_field_type()))
+        }
+        Some(Action_oneof_details::escalate(ref mut escalate)) => {
+            escalate.set_amount(-escalate.get_amount())
+        }
+        _ => return None,
+    }
+
+    Some(inverted)
+}
+
+/// Executes `Action` protos against Discord via the HTTP API.
+///
+/// Escalations and commands cannot be executed by this executor as they require access to
+/// service specific state, and will return `ActionError::Unsupported`.
+#[derive(Clone)]
+pub struct ActionExecutor {
+    http: twilight_http::Client,
+}
+
+impl ActionExecutor {
+    pub fn new(http: twilight_http::Client) -> Self {
+        Self { http }
+    }
+
+    pub fn http(&self) -> &twilight_http::Client {
+        &self.http
+    }
+
+    /// Executes multiple actions sequentially. All of the actions will be executed even if
+    /// earlier ones fail.
+    pub async fn execute_all(
+        &self,
+        actions: impl IntoIterator<Item = Action>,
+    ) -> Vec<ActionResult> {
+        let mut results = Vec::new();
+        for action in actions {
+            results.push(self.execute(action).await);
+        }
+        results
+    }
+
+    /// Executes a single action.
+    pub async fn execute(&self, action: Action) -> ActionResult {
+        let outcome = self.execute_inner(&action).await;
+        if let Err(ref err) = outcome {
+            tracing::error!(
+                "Error while executing {} action: {}",
+                action_type(&action),
+                err
+            );
+        }
+        ActionResult { action, outcome }
+    }
+
+    async fn execute_inner(&self, action: &Action) -> ExecuteResult {
+        match action.details {
+            Some(Action_oneof_details::kick(_)) => self.apply_kick(action).await,
+            Some(Action_oneof_details::ban(ref info)) => self.apply_ban(action, info).await,
+            Some(Action_oneof_details::mute(ref info)) => {
+                self.apply_mute(action, info.get_field_type()).await
+            }
+            Some(Action_oneof_details::deafen(ref info)) => {
+                self.apply_deafen(action, info.get_field_type()).await
+            }
+            Some(Action_oneof_details::change_role(ref info)) => {
+                self.apply_change_role(action, info).await
+            }
+            Some(Action_oneof_details::direct_message(ref info)) => {
+                self.apply_direct_message(action, info).await
+            }
+            Some(Action_oneof_details::send_message(ref info)) => {
+                self.apply_send_message(info).await
+            }
+            Some(Action_oneof_details::escalate(_)) => Err(ActionError::Unsupported("escalate")),
+            Some(Action_oneof_details::command(_)) => Err(ActionError::Unsupported("command")),
+            None => Err(ActionError::MissingField("details")),
+        }
+    }
+
+    async fn apply_kick(&self, action: &Action) -> ExecuteResult {
+        let (guild_id, user_id) = Self::target(action)?;
+        let request = self.http.remove_guild_member(guild_id, user_id);
+        match Self::with_reason(request, action)?.await {
+            Err(err) if is_not_found(&err) => {
+                Ok(ActionOutcome::Skipped("User is not in the server."))
+            }
+            result => {
+                result.map_err(anyhow::Error::from)?;
+                Ok(ActionOutcome::Applied)
+            }
+        }
+    }
+
+    async fn apply_ban(&self, action: &Action, info: &BanMember) -> ExecuteResult {
+        let (guild_id, user_id) = Self::target(action)?;
+        if info.get_field_type() != BanMember_Type::UNBAN {
+            let request = self
+                .http
+                .create_ban(guild_id, user_id)
+                .delete_message_days(info.get_delete_message_days() as u64)
+                .map_err(anyhow::Error::from)?;
+            match Self::with_reason(request, action)?.await {
+                Err(err) if is_not_found(&err) => {
+                    return Ok(ActionOutcome::Skipped("User does not exist."));
+                }
+                result => result.map_err(anyhow::Error::from)?,
+            };
+        }
+        if info.get_field_type() != BanMember_Type::BAN {
+            let request = self.http.delete_ban(guild_id, user_id);
+            match Self::with_reason(request, action)?.await {
+                Err(err) if is_not_found(&err) => {
+                    return Ok(ActionOutcome::Skipped("User is not banned."));
+                }
+                result => result.map_err(anyhow::Error::from)?,
+            };
+        }
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn apply_mute(&self, action: &Action, status: StatusType) -> ExecuteResult {
+        let (guild_id, user_id) = Self::target(action)?;
+        let mute = match status {
+            StatusType::APPLY => true,
+            StatusType::UNAPPLY => false,
+            StatusType::TOGGLE => match self.fetch_member(guild_id, user_id).await? {
+                Some(member) => !member.mute,
+                None => return Ok(ActionOutcome::Skipped("User is not in the server.")),
+            },
+        };
+        let request = self.http.update_guild_member(guild_id, user_id).mute(mute);
+        Self::with_reason(request, action)?
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn apply_deafen(&self, action: &Action, status: StatusType) -> ExecuteResult {
+        let (guild_id, user_id) = Self::target(action)?;
+        let deafen = match status {
+            StatusType::APPLY => true,
+            StatusType::UNAPPLY => false,
+            StatusType::TOGGLE => match self.fetch_member(guild_id, user_id).await? {
+                Some(member) => !member.deaf,
+                None => return Ok(ActionOutcome::Skipped("User is not in the server.")),
+            },
+        };
+        let request = self
+            .http
+            .update_guild_member(guild_id, user_id)
+            .deaf(deafen);
+        Self::with_reason(request, action)?
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn apply_change_role(&self, action: &Action, info: &ChangeRole) -> ExecuteResult {
+        let (guild_id, user_id) = Self::target(action)?;
+        let member = match self.fetch_member(guild_id, user_id).await? {
+            Some(member) => member,
+            None => return Ok(ActionOutcome::Skipped("User is not in the server.")),
+        };
+
+        let mut add: Vec<RoleId> = Vec::new();
+        let mut remove: Vec<RoleId> = Vec::new();
+        for role_id in info.get_role_ids().iter().map(|id| RoleId(*id)) {
+            let has_role = member.roles.contains(&role_id);
+            match info.get_field_type() {
+                StatusType::APPLY if !has_role => add.push(role_id),
+                StatusType::UNAPPLY if has_role => remove.push(role_id),
+                StatusType::TOGGLE if has_role => remove.push(role_id),
+                StatusType::TOGGLE => add.push(role_id),
+                _ => {}
+            }
+        }
+
+        if add.is_empty() && remove.is_empty() {
+            return Ok(ActionOutcome::Skipped("No roles needed to be changed."));
+        }
+
+        for role_id in add {
+            let request = self.http.add_guild_member_role(guild_id, user_id, role_id);
+            Self::with_reason(request, action)?
+                .await
+                .map_err(anyhow::Error::from)?;
+        }
+        for role_id in remove {
+            let request = self
+                .http
+                .remove_guild_member_role(guild_id, user_id, role_id);
+            Self::with_reason(request, action)?
+                .await
+                .map_err(anyhow::Error::from)?;
+        }
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn apply_direct_message(&self, action: &Action, info: &DirectMessage) -> ExecuteResult {
+        if !action.has_user_id() {
+            return Err(ActionError::MissingField("user_id"));
+        }
+        if info.get_content().is_empty() {
+            return Ok(ActionOutcome::Skipped("No message content was provided."));
+        }
+        let channel = self
+            .http
+            .create_private_channel(UserId(action.get_user_id()))
+            .await
+            .map_err(anyhow::Error::from)?;
+        self.http
+            .create_message(channel.id)
+            .content(info.get_content().to_owned())
+            .map_err(anyhow::Error::from)?
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn apply_send_message(&self, info: &SendMessage) -> ExecuteResult {
+        if !info.has_channel_id() {
+            return Err(ActionError::MissingField("send_message.channel_id"));
+        }
+        if info.get_content().is_empty() {
+            return Ok(ActionOutcome::Skipped("No message content was provided."));
+        }
+        self.http
+            .create_message(ChannelId(info.get_channel_id()))
+            .content(info.get_content().to_owned())
+            .map_err(anyhow::Error::from)?
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(ActionOutcome::Applied)
+    }
+
+    async fn fetch_member(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Option<crate::models::guild::Member>> {
+        Ok(self.http.guild_member(guild_id, user_id).await?)
+    }
+
+    fn target(action: &Action) -> std::result::Result<(GuildId, UserId), ActionError> {
+        if !action.has_guild_id() {
+            return Err(ActionError::MissingField("guild_id"));
+        }
+        if !action.has_user_id() {
+            return Err(ActionError::MissingField("user_id"));
+        }
+        Ok((GuildId(action.get_guild_id()), UserId(action.get_user_id())))
+    }
+
+    fn with_reason<T: AuditLogReason>(
+        request: T,
+        action: &Action,
+    ) -> std::result::Result<T, ActionError> {
+        if action.has_reason() {
+            Ok(request
+                .reason(action.get_reason().to_owned())
+                .map_err(anyhow::Error::from)?)
+        } else {
+            Ok(request)
+        }
+    }
+}
+
+/// Checks if a request failed because the targeted resource does not exist, i.e. the user has
+/// already left the server or is not banned.
+fn is_not_found(err: &HttpError) -> bool {
+    matches!(
+        err,
+        HttpError::Response {
+            status: StatusCode::NOT_FOUND,
+            ..
+        }
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invert_ban() {
+        let mut action = Action::new();
+        action.set_reason("Spamming".to_owned());
+        action.set_duration(3600);
+        action.mut_ban().set_field_type(BanMember_Type::BAN);
+        let inverted = invert_action(&action).unwrap();
+        assert_eq!(inverted.get_ban().get_field_type(), BanMember_Type::UNBAN);
+        assert_eq!(inverted.get_reason(), "Undo: Spamming");
+        assert!(!inverted.has_duration());
+    }
+
+    #[test]
+    fn test_invert_softban() {
+        let mut action = Action::new();
+        action.mut_ban().set_field_type(BanMember_Type::SOFTBAN);
+        assert!(invert_action(&action).is_none());
+    }
+
+    #[test]
+    fn test_invert_change_role() {
+        let mut action = Action::new();
+        action.mut_change_role().set_field_type(StatusType::APPLY);
+        let inverted = invert_action(&action).unwrap();
+        assert_eq!(
+            inverted.get_change_role().get_field_type(),
+            StatusType::UNAPPLY
+        );
+        assert!(!inverted.has_reason());
+    }
+
+    #[test]
+    fn test_invert_escalate() {
+        let mut action = Action::new();
+        action.mut_escalate().set_amount(2);
+        let inverted = invert_action(&action).unwrap();
+        assert_eq!(inverted.get_escalate().get_amount(), -2);
+    }
+
+    #[test]
+    fn test_invert_kick() {
+        let mut action = Action::new();
+        action.mut_kick();
+        assert!(invert_action(&action).is_none());
+    }
+}
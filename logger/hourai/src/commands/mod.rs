@@ -1,21 +1,177 @@
 pub mod precondition;
 pub mod prelude;
 
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
-use twilight_http::request::channel::message::*;
-use twilight_model::channel::Message;
+use twilight_model::application::{
+    callback::{CallbackData, InteractionResponse},
+    interaction::ApplicationCommand,
+};
+use twilight_model::channel::{embed::Embed, Message};
+use twilight_model::id::*;
+use twilight_model::user::User;
 
+/// How a command was invoked.
+#[derive(Debug, Clone, Copy)]
+pub enum Invocation<'a> {
+    /// A prefixed command sent as a message.
+    Message(&'a Message),
+    /// An application command, received as an interaction.
+    Interaction(&'a ApplicationCommand),
+}
+
+/// The context a command runs in. Commands use it the same way no matter how they were invoked.
 #[derive(Debug, Clone)]
 pub struct Context<'a> {
-    pub message: &'a Message,
+    pub invocation: Invocation<'a>,
     pub http: twilight_http::Client,
+    responded: Arc<AtomicBool>,
 }
 
-impl Context<'_> {
-    pub fn respond(&self) -> CreateMessage {
-        self.http
-            .create_message(self.message.channel_id)
-            .reply(self.message.id)
+impl<'a> Context<'a> {
+    pub fn new(invocation: Invocation<'a>, http: twilight_http::Client) -> Self {
+        Self {
+            invocation,
+            http,
+            responded: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn author(&self) -> &'a User {
+        match self.invocation {
+            Invocation::Message(message) => &message.author,
+            Invocation::Interaction(command) => command
+                .member
+                .as_ref()
+                .and_then(|member| member.user.as_ref())
+                .or_else(|| command.user.as_ref())
+                .expect("Interactions are always invoked by a user"),
+        }
+    }
+
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self.invocation {
+            Invocation::Message(message) => message.guild_id,
+            Invocation::Interaction(command) => command.guild_id,
+        }
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        match self.invocation {
+            Invocation::Message(message) => message.channel_id,
+            Invocation::Interaction(command) => command.channel_id,
+        }
+    }
+
+    /// Gets the roles of the author. Returns None if the command was not invoked in a server.
+    pub fn roles(&self) -> Option<&'a [RoleId]> {
+        match self.invocation {
+            Invocation::Message(message) => message.member.as_ref().map(|m| m.roles.as_slice()),
+            Invocation::Interaction(command) => command.member.as_ref().map(|m| m.roles.as_slice()),
+        }
+    }
+
+    /// Gets the message the command was invoked with, if it was invoked with one.
+    pub fn message(&self) -> Option<&'a Message> {
+        match self.invocation {
+            Invocation::Message(message) => Some(message),
+            Invocation::Interaction(_) => None,
+        }
+    }
+
+    /// Acknowledges the command. Interactions must be acknowledged within three seconds of
+    /// being received, and show that the bot is thinking until they are responded to. Does
+    /// nothing for messages.
+    pub async fn defer(&self) -> Result<()> {
+        if let Invocation::Interaction(command) = self.invocation {
+            let response = InteractionResponse::DeferredChannelMessageWithSource(CallbackData {
+                allowed_mentions: None,
+                content: None,
+                embeds: Vec::new(),
+                flags: None,
+                tts: None,
+            });
+            self.http
+                .interaction_callback(command.id, &command.token, response)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Responds to the command. Messages are replied to and interactions are followed up on.
+    pub async fn respond(&self, content: impl Into<String>) -> Result<Message> {
+        self.send(content.into(), None, false).await
+    }
+
+    /// Responds to the command with an embed.
+    pub async fn respond_embed(&self, content: impl Into<String>, embed: Embed) -> Result<Message> {
+        self.send(content.into(), Some(embed), false).await
+    }
+
+    /// Responds to the command with an error. Interactions that have not been responded to yet
+    /// show the error in place of their deferred response. Otherwise, the error is followed up
+    /// on ephemerally and only shown to the author.
+    pub async fn respond_error(&self, error: &CommandError) -> Result<()> {
+        let content = format!(":x: {}", error);
+        match self.invocation {
+            Invocation::Interaction(command) if !self.responded.load(Ordering::SeqCst) => {
+                self.http
+                    .update_interaction_original(&command.token)?
+                    .content(Some(content))?
+                    .await?;
+                self.responded.store(true, Ordering::SeqCst);
+            }
+            _ => {
+                self.send(content, None, true).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cleans up after the command has run. Interactions that were never responded to have
+    /// their deferred response deleted so the bot does not appear to be thinking forever.
+    pub async fn finish(&self) -> Result<()> {
+        if let Invocation::Interaction(command) = self.invocation {
+            if !self.responded.load(Ordering::SeqCst) {
+                self.http
+                    .delete_interaction_original(&command.token)?
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        content: String,
+        embed: Option<Embed>,
+        ephemeral: bool,
+    ) -> Result<Message> {
+        let message = match self.invocation {
+            Invocation::Message(message) => {
+                let request = self
+                    .http
+                    .create_message(message.channel_id)
+                    .reply(message.id)
+                    .content(content)?;
+                match embed {
+                    Some(embed) => request.embed(embed)?.await?,
+                    None => request.await?,
+                }
+            }
+            Invocation::Interaction(command) => {
+                self.http
+                    .create_followup_message(&command.token)?
+                    .content(content)
+                    .embeds(embed.into_iter().collect())
+                    .ephemeral(ephemeral)
+                    .await?
+            }
+        };
+        self.responded.store(true, Ordering::SeqCst);
+        Ok(message)
     }
 }
 
@@ -4,8 +4,7 @@ use anyhow::Result;
 use twilight_command_parser::Arguments;
 
 pub fn require_in_guild(ctx: &Context<'_>) -> Result<GuildId> {
-    ctx.message
-        .guild_id
+    ctx.guild_id()
         .ok_or_else(|| CommandError::FailedPrecondition("Command must be run in a server.").into())
 }
 
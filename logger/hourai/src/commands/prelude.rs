@@ -2,6 +2,8 @@ use super::CommandError;
 use anyhow::Result;
 use std::iter::Peekable;
 use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
 use twilight_command_parser::Arguments;
 
 pub type PeekableArguments<'a> = Peekable<Arguments<'a>>;
@@ -51,3 +53,149 @@ where
         arg.as_ref().parse()
     }
 }
+
+/// A position in a piece of media, either absolute or relative to the current position.
+///
+/// Absolute timestamps are written as `1:23:45`, `23:45` or `1h23m45s`. Relative timestamps
+/// are prefixed with `+` or `-`, like `+30s` or `-1:00`. A bare number is a number of seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+    Absolute(Duration),
+    Forward(Duration),
+    Backward(Duration),
+}
+
+impl Timestamp {
+    /// Resolves the timestamp against the current position. Moving backwards past the start
+    /// resolves to the start, and moving forwards past the largest duration saturates.
+    pub fn resolve(&self, current: Duration) -> Duration {
+        match self {
+            Self::Absolute(position) => *position,
+            Self::Forward(offset) => current.saturating_add(*offset),
+            Self::Backward(offset) => current.checked_sub(*offset).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("`{}` is not a valid timestamp. Try `1:23:45`, `2m30s` or `+30s`.", .0)]
+pub struct ParseTimestampError(String);
+
+impl FromStr for Timestamp {
+    type Err = ParseTimestampError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let err = || ParseTimestampError(value.to_owned());
+        let timestamp = if let Some(offset) = value.strip_prefix('+') {
+            Self::Forward(parse_duration(offset).ok_or_else(err)?)
+        } else if let Some(offset) = value.strip_prefix('-') {
+            Self::Backward(parse_duration(offset).ok_or_else(err)?)
+        } else {
+            Self::Absolute(parse_duration(value).ok_or_else(err)?)
+        };
+        Ok(timestamp)
+    }
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    if value.is_empty() {
+        return None;
+    }
+    if let Some(secs) = parse_number(value) {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut secs: u64 = 0;
+    if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        for part in parts {
+            secs = secs.checked_mul(60)?.checked_add(parse_number(part)?)?;
+        }
+        return Some(Duration::from_secs(secs));
+    }
+
+    // Each unit may only be used once, from largest to smallest.
+    let mut start = 0;
+    let mut last_multiplier = u64::MAX;
+    for (idx, unit) in value.char_indices() {
+        let multiplier = match unit {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => continue,
+        };
+        if multiplier >= last_multiplier {
+            return None;
+        }
+        last_multiplier = multiplier;
+        let amount = parse_number(&value[start..idx])?;
+        secs = secs.checked_add(amount.checked_mul(multiplier)?)?;
+        start = idx + 1;
+    }
+    if start != value.len() {
+        return None;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_absolute_timestamp() {
+        let parse = |value: &str| value.parse::<Timestamp>().unwrap();
+        assert_eq!(parse("45"), Timestamp::Absolute(Duration::from_secs(45)));
+        assert_eq!(
+            parse("23:45"),
+            Timestamp::Absolute(Duration::from_secs(1425))
+        );
+        assert_eq!(
+            parse("1:23:45"),
+            Timestamp::Absolute(Duration::from_secs(5025))
+        );
+        assert_eq!(
+            parse("1h23m45s"),
+            Timestamp::Absolute(Duration::from_secs(5025))
+        );
+        assert_eq!(parse("2m"), Timestamp::Absolute(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn test_parse_relative_timestamp() {
+        let parse = |value: &str| value.parse::<Timestamp>().unwrap();
+        assert_eq!(parse("+30s"), Timestamp::Forward(Duration::from_secs(30)));
+        assert_eq!(parse("+90"), Timestamp::Forward(Duration::from_secs(90)));
+        assert_eq!(parse("-1:00"), Timestamp::Backward(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_parse_invalid_timestamp() {
+        for value in &[
+            "", "+", "abc", "1:2:3:4", "1::2", "30x", "5m30", "++5", "1.5s", "1h1h", "5s1h",
+            "30s30s", "1m1h", "1h5s2m",
+        ] {
+            assert!(value.parse::<Timestamp>().is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_resolve_timestamp() {
+        let current = Duration::from_secs(60);
+        let resolve = |value: &str| value.parse::<Timestamp>().unwrap().resolve(current);
+        assert_eq!(resolve("10"), Duration::from_secs(10));
+        assert_eq!(resolve("+30s"), Duration::from_secs(90));
+        assert_eq!(resolve("-30s"), Duration::from_secs(30));
+        assert_eq!(resolve("-5m"), Duration::from_secs(0));
+        assert_eq!(resolve(&format!("+{}", u64::MAX)), Duration::MAX);
+    }
+}
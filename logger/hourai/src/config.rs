@@ -7,12 +7,14 @@ use std::{
 };
 
 const DEFAULT_ENV: &str = "dev";
+const DEFAULT_LIST_DIRECTORY: &str = "config/lists";
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HouraiConfig {
     pub command_prefix: String,
     pub database: String,
     pub redis: String,
+    pub list_directory: Option<String>,
     pub music: MusicConfig,
     pub discord: DiscordConfig,
     pub web: WebConfig,
@@ -34,6 +36,8 @@ pub struct MetricsConfig {
 #[derive(Debug, Deserialize, Clone)]
 pub struct MusicConfig {
     pub nodes: Vec<MusicNode>,
+    /// The base URI of a lyrics.ovh compatible lyrics API.
+    pub lyrics_uri: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -83,6 +87,30 @@ pub fn load_config(path: &Path) -> HouraiConfig {
     simd_json::serde::from_reader(reader).unwrap()
 }
 
+/// Loads a JSON list of strings from the configured list directory. Returns an empty list if the
+/// list is missing or malformed.
+pub fn load_list(config: &HouraiConfig, name: &str) -> Vec<String> {
+    let mut path: PathBuf = config
+        .list_directory
+        .as_deref()
+        .unwrap_or(DEFAULT_LIST_DIRECTORY)
+        .into();
+    path.push(format!("{}.json", name));
+    let list = File::open(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| Ok(simd_json::serde::from_reader(BufReader::new(file))?));
+    match list {
+        Ok(list) => {
+            tracing::info!("Loaded config list {} from {:?}", name, path);
+            list
+        }
+        Err(err) => {
+            tracing::error!("Failed to load config list {} from {:?}: {}", name, path, err);
+            Vec::new()
+        }
+    }
+}
+
 pub fn get_config_path() -> Box<Path> {
     let mut buffer: PathBuf = ["/etc", "hourai"].iter().collect();
     let execution_env: String = env::var("HOURAI_ENV")
@@ -1,8 +1,10 @@
+pub mod actions;
 pub mod cache;
 pub mod commands;
 pub mod config;
 pub mod init;
 pub mod models;
+pub mod moderation;
 pub mod prelude;
 
 // Include the auto-generated protos as a module
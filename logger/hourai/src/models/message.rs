@@ -1,7 +1,9 @@
 use super::user::UserLike;
 use super::Snowflake;
-use crate::proto::cache::{CachedMessageProto, CachedUserProto};
-use twilight_model::channel::Message;
+use crate::proto::cache::{
+    CachedAttachmentProto, CachedEmbedProto, CachedMessageProto, CachedUserProto,
+};
+use twilight_model::channel::{embed::Embed, Attachment, Message};
 use twilight_model::gateway::payload::MessageUpdate;
 use twilight_model::id::*;
 use twilight_model::user::User;
@@ -13,6 +15,10 @@ pub trait MessageLike: Snowflake<MessageId> {
     fn guild_id(&self) -> Option<GuildId>;
     fn author(&self) -> &Self::Author;
     fn content(&self) -> &str;
+    /// Gets the metadata of the message's attachments.
+    fn attachments(&self) -> Vec<CachedAttachmentProto>;
+    /// Gets summaries of the message's embeds.
+    fn embeds(&self) -> Vec<CachedEmbedProto>;
 
     /// Gets the link to the message
     fn message_link(&self) -> String {
@@ -66,6 +72,14 @@ impl MessageLike for Message {
     fn content(&self) -> &str {
         self.content.as_str()
     }
+
+    fn attachments(&self) -> Vec<CachedAttachmentProto> {
+        self.attachments.iter().map(Into::into).collect()
+    }
+
+    fn embeds(&self) -> Vec<CachedEmbedProto> {
+        self.embeds.iter().map(Into::into).collect()
+    }
 }
 
 impl MessageLike for CachedMessageProto {
@@ -90,4 +104,42 @@ impl MessageLike for CachedMessageProto {
     fn content(&self) -> &str {
         self.get_content()
     }
+
+    fn attachments(&self) -> Vec<CachedAttachmentProto> {
+        self.get_attachments().to_vec()
+    }
+
+    fn embeds(&self) -> Vec<CachedEmbedProto> {
+        self.get_embeds().to_vec()
+    }
+}
+
+impl From<&Attachment> for CachedAttachmentProto {
+    fn from(attachment: &Attachment) -> Self {
+        let mut proto = Self::new();
+        proto.set_id(attachment.id.0);
+        proto.set_filename(attachment.filename.clone());
+        proto.set_size(attachment.size);
+        proto.set_proxy_url(attachment.proxy_url.clone());
+        proto
+    }
+}
+
+impl From<&Embed> for CachedEmbedProto {
+    fn from(embed: &Embed) -> Self {
+        let mut proto = Self::new();
+        if let Some(ref title) = embed.title {
+            proto.set_title(title.clone());
+        }
+        if let Some(ref description) = embed.description {
+            proto.set_description(description.clone());
+        }
+        if let Some(ref url) = embed.url {
+            proto.set_url(url.clone());
+        }
+        if let Some(url) = embed.image.as_ref().and_then(|i| i.proxy_url.clone()) {
+            proto.set_image_url(url);
+        }
+        proto
+    }
 }
@@ -0,0 +1,35 @@
+use crate::models::id::UserId;
+use rand::seq::SliceRandom;
+
+/// Creates a mention for a random moderator out of those that are online. If none are online,
+/// the owner of the guild is mentioned instead.
+pub fn mention_moderator(online_moderators: &[UserId], owner_id: Option<UserId>) -> String {
+    if let Some(moderator) = online_moderators.choose(&mut rand::thread_rng()) {
+        return format!("<@{}>", moderator);
+    }
+    match owner_id {
+        Some(owner_id) => format!("<@{}>, no mods are online!", owner_id),
+        None => "No mods are online!".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mention_moderator() {
+        assert_eq!(mention_moderator(&[UserId(1)], Some(UserId(2))), "<@1>");
+        let mention = mention_moderator(&[UserId(1), UserId(3)], None);
+        assert!(mention == "<@1>" || mention == "<@3>");
+    }
+
+    #[test]
+    fn test_mention_moderator_falls_back_to_owner() {
+        assert_eq!(
+            mention_moderator(&[], Some(UserId(2))),
+            "<@2>, no mods are online!"
+        );
+        assert_eq!(mention_moderator(&[], None), "No mods are online!");
+    }
+}
@@ -17,6 +17,14 @@ pub enum CachePrefix {
     Guild = 4_u8,
     /// Cached voice state data.
     VoiceState = 5_u8,
+    /// Music player states, including their queues. Stored as a single hash of protobufs, keyed
+    /// by guild ID.
+    MusicPlayers = 6_u8,
+    /// The contents of small message attachments, kept so they can be uploaded again if the
+    /// message is deleted. Stored as a hash per message, keyed by attachment ID.
+    Attachments = 7_u8,
+    /// The total size, in bytes, of the attachments cached for each guild in the current day.
+    AttachmentBudgets = 8_u8,
 }
 
 impl CachePrefix {
@@ -5,9 +5,10 @@ mod protobuf;
 
 use self::compression::Compressed;
 pub use self::guild_config::CachedGuildConfig;
-use self::keys::{CacheKey, CachePrefix, GuildKey, Id};
+use self::keys::{CacheKey, CachePrefix, GuildKey, GuildPrefix, Id};
 use self::protobuf::Protobuf;
 use anyhow::Result;
+use byteorder::{BigEndian, ByteOrder};
 use hourai::models::{
     channel::GuildChannel,
     guild::{Guild, PartialGuild, Permissions, Role},
@@ -15,12 +16,18 @@ use hourai::models::{
     MessageLike, Snowflake, UserLike,
 };
 use hourai::proto::cache::*;
+use hourai::proto::guild_configs::LoggingConfig;
 use redis::aio::ConnectionLike;
 use redis::ToRedisArgs;
+use std::collections::HashMap;
 use tracing::debug;
 
 pub type RedisPool = redis::aio::ConnectionManager;
 
+const MODERATOR_PREFIX: &str = "mod";
+/// How long cached attachments, and the budgets they are counted against, are kept.
+const ATTACHMENT_EXPIRATION_SECS: usize = 86400;
+
 pub async fn init(config: &hourai::config::HouraiConfig) -> RedisPool {
     debug!("Creating Redis client");
     let client = redis::Client::open(config.redis.as_ref()).expect("Failed to create Redis client");
@@ -65,6 +72,17 @@ impl OnlineStatus {
     pub fn build(self) -> redis::Pipeline {
         self.pipeline
     }
+
+    /// Fetches the IDs of all of the online users in a guild.
+    pub async fn fetch_online(guild_id: GuildId, conn: &mut RedisPool) -> Result<Vec<UserId>> {
+        let key = CachePrefix::OnlineStatus.make_key(guild_id.0);
+        let ids: Vec<Vec<u8>> = redis::Cmd::smembers(key).query_async(conn).await?;
+        Ok(ids
+            .into_iter()
+            .filter(|id| id.len() == 8)
+            .map(|id| UserId(BigEndian::read_u64(&id)))
+            .collect())
+    }
 }
 
 pub struct GuildConfig;
@@ -88,6 +106,19 @@ impl GuildConfig {
         Ok(Self::fetch::<T>(id, conn).await?.unwrap_or_else(T::new))
     }
 
+    /// Fetches the modlog channel of a guild, if one has been configured.
+    pub async fn fetch_modlog_channel(
+        id: GuildId,
+        conn: &mut RedisPool,
+    ) -> Result<Option<ChannelId>> {
+        let config = Self::fetch_or_default::<LoggingConfig>(id, conn).await?;
+        Ok(if config.has_modlog_channel_id() {
+            Some(ChannelId(config.get_modlog_channel_id()))
+        } else {
+            None
+        })
+    }
+
     pub fn set<T: ::protobuf::Message + CachedGuildConfig>(id: GuildId, value: T) -> redis::Cmd {
         let key = CachePrefix::GuildConfigs.make_key(id.0);
         redis::Cmd::hset(key, vec![T::SUBKEY], Compressed(Protobuf(value)))
@@ -104,6 +135,8 @@ impl CachedMessage {
         msg.set_id(message.id().0);
         msg.set_channel_id(message.channel_id().0);
         msg.set_content(message.content().to_owned());
+        msg.set_attachments(::protobuf::RepeatedField::from_vec(message.attachments()));
+        msg.set_embeds(::protobuf::RepeatedField::from_vec(message.embeds()));
         if let Some(guild_id) = message.guild_id() {
             msg.set_guild_id(guild_id.0)
         }
@@ -166,6 +199,79 @@ impl CachedMessage {
     }
 }
 
+pub struct CachedAttachment;
+
+impl CachedAttachment {
+    /// Caches the contents of an attachment for as long as its message is cached.
+    pub fn save(
+        channel_id: ChannelId,
+        message_id: MessageId,
+        attachment_id: u64,
+        data: Vec<u8>,
+    ) -> redis::Pipeline {
+        let key = CachePrefix::Attachments.make_key((channel_id.0, message_id.0));
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset(key, Id(attachment_id), data)
+            .ignore()
+            .expire(key, ATTACHMENT_EXPIRATION_SECS)
+            .ignore();
+        pipe
+    }
+
+    /// Reserves space for an attachment in a guild's daily budget of cached attachments. Returns
+    /// false, without reserving anything, if the attachment does not fit in the budget.
+    pub async fn reserve<C: ConnectionLike>(
+        guild_id: GuildId,
+        size: u64,
+        budget: u64,
+        conn: &mut C,
+    ) -> Result<bool> {
+        let key = CachePrefix::AttachmentBudgets.make_key(guild_id.0);
+        let used: u64 = redis::Cmd::incr(key, size).query_async(conn).await?;
+        if used == size {
+            // The first reservation of the day starts the budget's expiration.
+            redis::Cmd::expire(key, ATTACHMENT_EXPIRATION_SECS)
+                .query_async::<_, ()>(conn)
+                .await?;
+        }
+        if used > budget {
+            redis::Cmd::decr(key, size)
+                .query_async::<_, ()>(conn)
+                .await?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    pub async fn fetch<C: ConnectionLike>(
+        channel_id: ChannelId,
+        message_id: MessageId,
+        attachment_id: u64,
+        conn: &mut C,
+    ) -> Result<Option<Vec<u8>>> {
+        let key = CachePrefix::Attachments.make_key((channel_id.0, message_id.0));
+        Ok(redis::Cmd::hget(key, Id(attachment_id))
+            .query_async(conn)
+            .await?)
+    }
+
+    pub fn delete(channel_id: ChannelId, id: MessageId) -> redis::Cmd {
+        Self::bulk_delete(channel_id, vec![id])
+    }
+
+    pub fn bulk_delete(
+        channel_id: ChannelId,
+        ids: impl IntoIterator<Item = MessageId>,
+    ) -> redis::Cmd {
+        let keys: Vec<CacheKey<(u64, u64)>> = ids
+            .into_iter()
+            .map(|id| CachePrefix::Attachments.make_key((channel_id.0, id.0)))
+            .collect();
+        redis::Cmd::del(keys)
+    }
+}
+
 pub struct CachedVoiceState;
 
 impl CachedVoiceState {
@@ -205,6 +311,41 @@ impl CachedVoiceState {
 
 }
 
+pub struct CachedPlayerState;
+
+impl CachedPlayerState {
+    /// Replaces all of the saved player states with the provided ones.
+    pub fn save_all(states: impl IntoIterator<Item = CachedPlayerStateProto>) -> redis::Pipeline {
+        let key = CachePrefix::MusicPlayers.make_key(());
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(key).ignore();
+        for state in states {
+            pipe.hset(key, state.get_guild_id(), Compressed(Protobuf(state)))
+                .ignore();
+        }
+        pipe
+    }
+
+    pub fn delete(guild_id: GuildId) -> redis::Cmd {
+        redis::Cmd::hdel(CachePrefix::MusicPlayers.make_key(()), guild_id.0)
+    }
+
+    pub async fn fetch_all(conn: &mut RedisPool) -> Result<Vec<CachedPlayerStateProto>> {
+        let key = CachePrefix::MusicPlayers.make_key(());
+        let states: HashMap<u64, Compressed<Protobuf<CachedPlayerStateProto>>> =
+            redis::Cmd::hgetall(key).query_async(conn).await?;
+        Ok(states.into_iter().map(|(_, state)| state.0 .0).collect())
+    }
+}
+
+/// Checks if a role is a moderator role: either it has administrator permissions or its name
+/// starts with "mod".
+pub fn is_moderator_role(role: &CachedRoleProto) -> bool {
+    let perms = Permissions::from_bits_truncate(role.get_permissions());
+    perms.contains(Permissions::ADMINISTRATOR)
+        || role.get_name().to_lowercase().starts_with(MODERATOR_PREFIX)
+}
+
 pub struct CachedGuild;
 
 impl CachedGuild {
@@ -268,6 +409,58 @@ impl CachedGuild {
             .collect())
     }
 
+    /// Fetches all of the cached channels in a guild.
+    pub async fn fetch_channels(
+        guild_id: GuildId,
+        conn: &mut RedisPool,
+    ) -> Result<Vec<CachedGuildChannelProto>> {
+        Self::fetch_all(guild_id, GuildPrefix::Channel, conn).await
+    }
+
+    /// Fetches all of the cached roles in a guild.
+    pub async fn fetch_roles(
+        guild_id: GuildId,
+        conn: &mut RedisPool,
+    ) -> Result<Vec<CachedRoleProto>> {
+        Self::fetch_all(guild_id, GuildPrefix::Role, conn).await
+    }
+
+    /// Fetches the ID of the owner of a guild, if the guild is cached.
+    pub async fn fetch_owner_id(guild_id: GuildId, conn: &mut RedisPool) -> Result<Option<UserId>> {
+        let guild = Self::fetch_resource::<Guild>(guild_id, guild_id, conn).await?;
+        Ok(guild.map(|guild| UserId(guild.get_owner_id())))
+    }
+
+    /// Fetches the IDs of all of the moderator roles in a guild.
+    pub async fn fetch_moderator_roles(
+        guild_id: GuildId,
+        conn: &mut RedisPool,
+    ) -> Result<Vec<RoleId>> {
+        Ok(Self::fetch_roles(guild_id, conn)
+            .await?
+            .iter()
+            .filter(|role| is_moderator_role(role))
+            .map(|role| RoleId(role.get_role_id()))
+            .collect())
+    }
+
+    async fn fetch_all<T: ::protobuf::Message>(
+        guild_id: GuildId,
+        prefix: GuildPrefix,
+        conn: &mut RedisPool,
+    ) -> Result<Vec<T>> {
+        let guild_key = CachePrefix::Guild.make_key(guild_id.0);
+        let resources: HashMap<Vec<u8>, Vec<u8>> =
+            redis::Cmd::hgetall(guild_key).query_async(conn).await?;
+        let mut protos = Vec::new();
+        for (key, value) in resources {
+            if key.first() == Some(&u8::from(prefix)) {
+                protos.push(T::parse_from_bytes(&value)?);
+            }
+        }
+        Ok(protos)
+    }
+
     /// Saves a resoruce into the cache.
     pub fn save_resource<T: GuildResource>(
         guild_id: GuildId,
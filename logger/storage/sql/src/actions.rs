@@ -7,11 +7,50 @@ use sqlx::types::chrono::{DateTime, Utc};
 pub struct PendingAction {
     id: i32,
     data: types::Protobuf<Action>,
+    attempts: i32,
 }
 
 impl PendingAction {
+    pub fn action(&self) -> &Action {
+        &self.data.0
+    }
+
+    /// The number of times the action has previously failed to execute.
+    pub fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
     pub fn fetch_expired<'a>() -> SqlQueryAs<'a, Self> {
-        sqlx::query_as("SELECT id, data FROM pending_actions WHERE ts < now()")
+        sqlx::query_as(
+            "SELECT id, data, attempts FROM pending_actions \
+             WHERE timestamp < now() ORDER BY timestamp",
+        )
+    }
+
+    /// Claims up to `limit` expired actions for execution.
+    ///
+    /// Claimed rows are leased until `lease_expiration` by pushing their timestamp forward, so
+    /// other processes polling the table will not pick them up. If the claiming process dies
+    /// before deleting or rescheduling them, they will be picked up again once the lease
+    /// expires. Safe to run concurrently from multiple processes, as long as every process
+    /// claims rows this way. The Python bot uses the same query in `ActionScheduler`.
+    pub fn claim_expired<'a>(
+        limit: i64,
+        lease_expiration: impl Into<DateTime<Utc>>,
+    ) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "UPDATE pending_actions SET timestamp = $2 \
+             WHERE id IN ( \
+                SELECT id FROM pending_actions \
+                WHERE timestamp < now() \
+                ORDER BY timestamp \
+                LIMIT $1 \
+                FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING id, data, attempts",
+        )
+        .bind(limit)
+        .bind(lease_expiration.into())
     }
 
     pub fn schedule<'a>(action: Action, timestamp: impl Into<DateTime<Utc>>) -> SqlQuery<'a> {
@@ -20,6 +59,22 @@ impl PendingAction {
             .bind(types::Protobuf(action))
     }
 
+    /// Reschedules a failed action for another attempt at a later time.
+    pub fn reschedule<'a>(&self, timestamp: impl Into<DateTime<Utc>>) -> SqlQuery<'a> {
+        sqlx::query(
+            "UPDATE pending_actions SET timestamp = $1, attempts = attempts + 1 \
+             WHERE id = $2",
+        )
+        .bind(timestamp.into())
+        .bind(self.id)
+    }
+
+    /// Releases a claimed action without executing it, making it immediately available to
+    /// other processes.
+    pub fn release<'a>(&self) -> SqlQuery<'a> {
+        sqlx::query("UPDATE pending_actions SET timestamp = now() WHERE id = $1").bind(self.id)
+    }
+
     pub fn delete<'a>(&self) -> SqlQuery<'a> {
         sqlx::query("DELETE FROM pending_actions WHERE id = $1").bind(self.id)
     }
@@ -0,0 +1,221 @@
+use crate::models::{SqlQuery, SqlQueryAs};
+use crate::types;
+use hourai::models::id::*;
+use hourai::proto::action::ActionSet;
+use sqlx::types::chrono::{DateTime, Utc};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct EscalationEntry {
+    pub id: i32,
+    pub guild_id: i64,
+    pub subject_id: i64,
+    pub authorizer_id: i64,
+    pub authorizer_name: String,
+    pub display_name: String,
+    pub timestamp: DateTime<Utc>,
+    action: types::Protobuf<ActionSet>,
+    pub level_delta: i32,
+}
+
+impl EscalationEntry {
+    pub fn guild_id(&self) -> GuildId {
+        GuildId(self.guild_id as u64)
+    }
+
+    pub fn subject_id(&self) -> UserId {
+        UserId(self.subject_id as u64)
+    }
+
+    pub fn authorizer_id(&self) -> UserId {
+        UserId(self.authorizer_id as u64)
+    }
+
+    pub fn actions(&self) -> &ActionSet {
+        &self.action.0
+    }
+
+    /// Computes the escalation level after applying the provided entries in order. The level
+    /// cannot go below -1, which is the level for users who have never been escalated.
+    pub fn compute_level<'a>(entries: impl IntoIterator<Item = &'a EscalationEntry>) -> i64 {
+        entries.into_iter().fold(-1, |level, entry| {
+            std::cmp::max(-1, level + entry.level_delta as i64)
+        })
+    }
+
+    /// Constructs a query to fetch the full escalation history of a user in a guild, ordered
+    /// from oldest to newest.
+    pub fn fetch_history<'a>(guild_id: GuildId, user_id: UserId) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT * FROM escalation_histories \
+             WHERE guild_id = $1 AND subject_id = $2 \
+             ORDER BY timestamp",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(user_id.0 as i64)
+    }
+
+    /// Constructs a query to add a new entry. Returns the ID of the created entry.
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert<'a>(
+        guild_id: GuildId,
+        subject_id: UserId,
+        authorizer_id: UserId,
+        authorizer_name: impl Into<String>,
+        display_name: impl Into<String>,
+        timestamp: impl Into<DateTime<Utc>>,
+        actions: ActionSet,
+        level_delta: i32,
+    ) -> SqlQueryAs<'a, (i32,)> {
+        sqlx::query_as(
+            "INSERT INTO escalation_histories \
+                (guild_id, subject_id, authorizer_id, authorizer_name, display_name, \
+                 timestamp, action, level_delta) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             RETURNING id",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(subject_id.0 as i64)
+        .bind(authorizer_id.0 as i64)
+        .bind(authorizer_name.into())
+        .bind(display_name.into())
+        .bind(timestamp.into())
+        .bind(types::Protobuf(actions))
+        .bind(level_delta)
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct PendingDeescalation {
+    pub user_id: i64,
+    pub guild_id: i64,
+    pub expiration: DateTime<Utc>,
+    pub amount: i64,
+    pub entry_id: i32,
+}
+
+impl PendingDeescalation {
+    pub fn guild_id(&self) -> GuildId {
+        GuildId(self.guild_id as u64)
+    }
+
+    pub fn user_id(&self) -> UserId {
+        UserId(self.user_id as u64)
+    }
+
+    pub fn fetch<'a>(guild_id: GuildId, user_id: UserId) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as("SELECT * FROM pending_deescalations WHERE guild_id = $1 AND user_id = $2")
+            .bind(guild_id.0 as i64)
+            .bind(user_id.0 as i64)
+    }
+
+    /// Claims up to `limit` expired deescalations.
+    ///
+    /// Claimed rows are leased until `lease_expiration` by pushing their expiration forward, so
+    /// other processes polling the table will not pick them up. If the claiming process fails to
+    /// apply a deescalation, it will be picked up again once the lease expires. Safe to run
+    /// concurrently from multiple processes, as long as every process claims rows this way. The
+    /// Python bot uses the same query in `EscalationMixin`.
+    pub fn claim_expired<'a>(
+        limit: i64,
+        lease_expiration: impl Into<DateTime<Utc>>,
+    ) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "UPDATE pending_deescalations SET expiration = $2 \
+             WHERE (user_id, guild_id) IN ( \
+                SELECT user_id, guild_id FROM pending_deescalations \
+                WHERE expiration < now() \
+                ORDER BY expiration \
+                LIMIT $1 \
+                FOR UPDATE SKIP LOCKED \
+             ) \
+             RETURNING *",
+        )
+        .bind(limit)
+        .bind(lease_expiration.into())
+    }
+
+    /// Constructs a query to schedule a deescalation, replacing any previously scheduled
+    /// deescalation for the user.
+    pub fn schedule<'a>(
+        guild_id: GuildId,
+        user_id: UserId,
+        expiration: impl Into<DateTime<Utc>>,
+        amount: i64,
+        entry_id: i32,
+    ) -> SqlQuery<'a> {
+        sqlx::query(
+            "INSERT INTO pending_deescalations (user_id, guild_id, expiration, amount, entry_id) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT ON CONSTRAINT pending_deescalations_pkey \
+             DO UPDATE SET \
+                expiration = excluded.expiration, \
+                amount = excluded.amount, \
+                entry_id = excluded.entry_id",
+        )
+        .bind(user_id.0 as i64)
+        .bind(guild_id.0 as i64)
+        .bind(expiration.into())
+        .bind(amount)
+        .bind(entry_id)
+    }
+
+    pub fn delete<'a>(guild_id: GuildId, user_id: UserId) -> SqlQuery<'a> {
+        sqlx::query("DELETE FROM pending_deescalations WHERE guild_id = $1 AND user_id = $2")
+            .bind(guild_id.0 as i64)
+            .bind(user_id.0 as i64)
+    }
+
+    /// Deletes a claimed deescalation once it has been applied. Does nothing if the
+    /// deescalation has since been replaced by a newer one.
+    pub fn delete_claimed<'a>(&self) -> SqlQuery<'a> {
+        sqlx::query(
+            "DELETE FROM pending_deescalations \
+             WHERE guild_id = $1 AND user_id = $2 AND entry_id = $3",
+        )
+        .bind(self.guild_id)
+        .bind(self.user_id)
+        .bind(self.entry_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(level_delta: i32) -> EscalationEntry {
+        EscalationEntry {
+            id: 0,
+            guild_id: 0,
+            subject_id: 0,
+            authorizer_id: 0,
+            authorizer_name: String::new(),
+            display_name: String::new(),
+            timestamp: Utc::now(),
+            action: types::Protobuf(ActionSet::new()),
+            level_delta,
+        }
+    }
+
+    fn level_after(deltas: &[i32]) -> i64 {
+        let entries: Vec<_> = deltas.iter().map(|delta| entry(*delta)).collect();
+        EscalationEntry::compute_level(&entries)
+    }
+
+    #[test]
+    fn test_compute_level() {
+        assert_eq!(level_after(&[]), -1);
+        assert_eq!(level_after(&[1]), 0);
+        assert_eq!(level_after(&[1, 1, 1]), 2);
+        assert_eq!(level_after(&[1, 1, -1]), 0);
+        assert_eq!(level_after(&[3, -2]), 0);
+    }
+
+    #[test]
+    fn test_compute_level_does_not_go_below_negative_one() {
+        assert_eq!(level_after(&[-1]), -1);
+        assert_eq!(level_after(&[-5]), -1);
+        // Deescalations past -1 are not remembered by later escalations.
+        assert_eq!(level_after(&[-3, 1]), 0);
+        assert_eq!(level_after(&[1, -4, 1, 1]), 1);
+    }
+}
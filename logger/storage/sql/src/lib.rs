@@ -1,6 +1,9 @@
 pub mod actions;
+pub mod escalation;
 mod models;
+pub mod playlists;
 mod types;
+pub mod verification;
 
 pub use self::models::*;
 pub use sqlx::types as sql_types;
@@ -65,6 +65,34 @@ impl Username {
         .bind(self.discriminator)
     }
 
+    /// Constructs a query to fetch the most recently recorded username of a user.
+    pub fn fetch_latest<'a>(user_id: UserId) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT user_id, timestamp, name, discriminator \
+             FROM usernames WHERE user_id = $1 \
+             ORDER BY timestamp DESC LIMIT 1",
+        )
+        .bind(user_id.0 as i64)
+    }
+
+    /// Constructs a query to record the username as the user's most recent one. Returns a row
+    /// only if the username was not already the most recent one, so only one of several
+    /// concurrent updates sees the change.
+    pub fn insert_latest<'a>(&self) -> SqlQueryAs<'a, (i64,)> {
+        sqlx::query_as(
+            "INSERT INTO usernames (user_id, name, discriminator) \
+             VALUES ($1, $2, $3) \
+             ON CONFLICT ON CONSTRAINT idx_unique_username \
+             DO UPDATE SET timestamp = now() \
+             WHERE usernames.timestamp < \
+                (SELECT MAX(timestamp) FROM usernames WHERE user_id = $1) \
+             RETURNING user_id",
+        )
+        .bind(self.user_id)
+        .bind(self.name.clone())
+        .bind(self.discriminator)
+    }
+
     pub fn bulk_insert<'a>(usernames: Vec<Self>) -> SqlQuery<'a> {
         let user_ids: Vec<i64> = usernames.iter().map(|u| u.user_id).collect();
         let names: Vec<String> = usernames.iter().map(|u| u.name.clone()).collect();
@@ -234,6 +262,7 @@ pub struct Member {
     pub nickname: Option<String>,
     pub bot: bool,
     pub premium_since: Option<DateTime<Utc>>,
+    pub joined_at: Option<DateTime<Utc>>,
 }
 
 impl From<&TwilightMember> for Member {
@@ -242,6 +271,10 @@ impl From<&TwilightMember> for Member {
             .premium_since
             .as_ref()
             .and_then(|p| p.parse::<DateTime<Utc>>().ok());
+        let joined_at = member
+            .joined_at
+            .as_ref()
+            .and_then(|j| j.parse::<DateTime<Utc>>().ok());
         Self {
             guild_id: member.guild_id.0 as i64,
             user_id: member.user.id.0 as i64,
@@ -249,6 +282,7 @@ impl From<&TwilightMember> for Member {
             nickname: member.nick.clone(),
             bot: member.user.bot,
             premium_since: premium,
+            joined_at,
         }
     }
 }
@@ -266,6 +300,7 @@ impl From<&MemberUpdate> for Member {
             nickname: member.nick.clone(),
             bot: member.user.bot,
             premium_since: premium,
+            joined_at: member.joined_at.parse::<DateTime<Utc>>().ok(),
         }
     }
 }
@@ -297,13 +332,14 @@ impl Member {
 
     pub fn insert<'a>(self) -> SqlQuery<'a> {
         sqlx::query(
-            "INSERT INTO members (guild_id, user_id, role_ids, nickname, present, bot, premium_since) \
-                     VALUES ($1, $2, $3, $4, true, $5, $6) \
+            "INSERT INTO members (guild_id, user_id, role_ids, nickname, present, bot, premium_since, joined_at) \
+                     VALUES ($1, $2, $3, $4, true, $5, $6, $7) \
                      ON CONFLICT ON CONSTRAINT members_pkey \
                      DO UPDATE SET \
                         role_ids = excluded.role_ids, \
                         nickname = excluded.nickname, \
                         premium_since = excluded.premium_since, \
+                        joined_at = COALESCE(excluded.joined_at, members.joined_at), \
                         bot = excluded.bot, \
                         last_seen = now(), \
                         present = true",
@@ -314,6 +350,7 @@ impl Member {
         .bind(self.nickname)
         .bind(self.bot)
         .bind(self.premium_since)
+        .bind(self.joined_at)
     }
 
     pub fn count_guilds<'a>() -> SqlQueryAs<'a, (i64,)> {
@@ -345,6 +382,46 @@ impl Member {
             .bind(user_id.0 as i64)
     }
 
+    /// Fetches all present, non-bot members of a guild that have any of the provided roles.
+    pub fn fetch_with_roles<'a>(guild_id: GuildId, role_ids: &[RoleId]) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT * FROM members \
+             WHERE guild_id = $1 AND role_ids && $2 AND present AND NOT bot",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(role_ids.iter().map(|id| id.0 as i64).collect::<Vec<_>>())
+    }
+
+    /// Fetches the IDs of all guilds a user is currently a member of.
+    pub fn fetch_user_guild_ids<'a>(user_id: UserId) -> SqlQueryAs<'a, (i64,)> {
+        sqlx::query_as("SELECT guild_id FROM members WHERE user_id = $1 AND present")
+            .bind(user_id.0 as i64)
+    }
+
+    /// Fetches the IDs of all guilds with at least one present member.
+    pub fn fetch_guild_ids<'a>() -> SqlQueryAs<'a, (i64,)> {
+        sqlx::query_as("SELECT DISTINCT guild_id FROM members WHERE present")
+    }
+
+    /// Fetches all present, non-bot members of a guild without a role that joined within the
+    /// given time range.
+    pub fn fetch_without_role<'a>(
+        guild_id: GuildId,
+        role_id: RoleId,
+        joined_after: Option<DateTime<Utc>>,
+        joined_before: DateTime<Utc>,
+    ) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT * FROM members \
+             WHERE guild_id = $1 AND NOT ($2 = ANY(role_ids)) AND present AND NOT bot \
+                AND ($3::timestamptz IS NULL OR joined_at >= $3) AND joined_at < $4",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(role_id.0 as i64)
+        .bind(joined_after)
+        .bind(joined_before)
+    }
+
     /// Marks all members as not present in preparation for repopulating the column.
     pub fn clear_present_shard<'a>(shard_id: u64, shard_total: u64) -> SqlQuery<'a> {
         sqlx::query("UPDATE members SET present = false WHERE (guild_id >> 22) % $2 = $1")
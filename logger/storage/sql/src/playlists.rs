@@ -0,0 +1,157 @@
+use crate::models::{SqlQuery, SqlQueryAs};
+use hourai::models::id::*;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Who a saved playlist belongs to. Playlist names are unique per owner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistOwner {
+    User(UserId),
+    Guild(GuildId),
+}
+
+impl PlaylistOwner {
+    fn owner_type(&self) -> i32 {
+        match self {
+            Self::User(_) => 0,
+            Self::Guild(_) => 1,
+        }
+    }
+
+    fn owner_id(&self) -> i64 {
+        match self {
+            Self::User(id) => id.0 as i64,
+            Self::Guild(id) => id.0 as i64,
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Playlist {
+    pub id: i32,
+    pub name: String,
+    pub creator_id: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An overview of a saved playlist, without its tracks.
+#[derive(Debug, sqlx::FromRow)]
+pub struct PlaylistSummary {
+    pub name: String,
+    pub track_count: i64,
+    /// The total length of all of the tracks, in milliseconds.
+    pub length: i64,
+}
+
+impl Playlist {
+    pub fn creator_id(&self) -> UserId {
+        UserId(self.creator_id as u64)
+    }
+
+    pub fn fetch<'a>(owner: PlaylistOwner, name: impl Into<String>) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT id, name, creator_id, updated_at FROM playlists \
+             WHERE owner_type = $1 AND owner_id = $2 AND name = $3",
+        )
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+        .bind(name.into())
+    }
+
+    /// Constructs a query to list all of the playlists of an owner, ordered by name.
+    pub fn list<'a>(owner: PlaylistOwner) -> SqlQueryAs<'a, PlaylistSummary> {
+        sqlx::query_as(
+            "SELECT playlists.name, \
+                COUNT(playlist_tracks.position) AS track_count, \
+                COALESCE(SUM(playlist_tracks.length), 0)::bigint AS length \
+             FROM playlists \
+             LEFT JOIN playlist_tracks ON playlist_tracks.playlist_id = playlists.id \
+             WHERE playlists.owner_type = $1 AND playlists.owner_id = $2 \
+             GROUP BY playlists.id \
+             ORDER BY playlists.name",
+        )
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+    }
+
+    /// Constructs a query to create a playlist, or mark an existing one with the same name as
+    /// updated. Returns the ID of the playlist.
+    pub fn upsert<'a>(
+        owner: PlaylistOwner,
+        name: impl Into<String>,
+        creator_id: UserId,
+        timestamp: impl Into<DateTime<Utc>>,
+    ) -> SqlQueryAs<'a, (i32,)> {
+        sqlx::query_as(
+            "INSERT INTO playlists (owner_type, owner_id, name, creator_id, updated_at) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT ON CONSTRAINT playlists_owner_type_owner_id_name_key \
+             DO UPDATE SET \
+                creator_id = excluded.creator_id, \
+                updated_at = excluded.updated_at \
+             RETURNING id",
+        )
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+        .bind(name.into())
+        .bind(creator_id.0 as i64)
+        .bind(timestamp.into())
+    }
+
+    /// Constructs a query to delete a playlist and all of its tracks.
+    pub fn delete<'a>(owner: PlaylistOwner, name: impl Into<String>) -> SqlQuery<'a> {
+        sqlx::query(
+            "DELETE FROM playlists \
+             WHERE owner_type = $1 AND owner_id = $2 AND name = $3",
+        )
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+        .bind(name.into())
+    }
+}
+
+/// A track in a saved playlist. The track is stored as encoded by Lavalink, alongside its info
+/// so the playlist can be displayed without decoding it.
+#[derive(Debug, sqlx::FromRow)]
+pub struct PlaylistTrack {
+    pub position: i32,
+    pub track: Vec<u8>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub uri: String,
+    /// The length of the track, in milliseconds.
+    pub length: i64,
+    pub is_stream: bool,
+}
+
+impl PlaylistTrack {
+    /// Constructs a query to fetch all of the tracks in a playlist, in order.
+    pub fn fetch<'a>(playlist_id: i32) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT position, track, title, author, uri, length, is_stream \
+             FROM playlist_tracks \
+             WHERE playlist_id = $1 \
+             ORDER BY position",
+        )
+        .bind(playlist_id)
+    }
+
+    pub fn clear<'a>(playlist_id: i32) -> SqlQuery<'a> {
+        sqlx::query("DELETE FROM playlist_tracks WHERE playlist_id = $1").bind(playlist_id)
+    }
+
+    pub fn insert<'a>(self, playlist_id: i32) -> SqlQuery<'a> {
+        sqlx::query(
+            "INSERT INTO playlist_tracks \
+                (playlist_id, position, track, title, author, uri, length, is_stream) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(playlist_id)
+        .bind(self.position)
+        .bind(self.track)
+        .bind(self.title)
+        .bind(self.author)
+        .bind(self.uri)
+        .bind(self.length)
+        .bind(self.is_stream)
+    }
+}
@@ -0,0 +1,61 @@
+use crate::models::{SqlQuery, SqlQueryAs};
+use crate::types;
+use hourai::models::id::*;
+use hourai::proto::verification::VerificationVerdict;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// A record of the verdict reached when verifying a user that joined a guild.
+#[derive(Debug, sqlx::FromRow)]
+pub struct VerdictEntry {
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub timestamp: DateTime<Utc>,
+    verdict: types::Protobuf<VerificationVerdict>,
+}
+
+impl VerdictEntry {
+    pub fn guild_id(&self) -> GuildId {
+        GuildId(self.guild_id as u64)
+    }
+
+    pub fn user_id(&self) -> UserId {
+        UserId(self.user_id as u64)
+    }
+
+    pub fn verdict(&self) -> &VerificationVerdict {
+        &self.verdict.0
+    }
+
+    /// Constructs a query to fetch all of the verdicts for a user in a guild, ordered from
+    /// newest to oldest.
+    pub fn fetch<'a>(guild_id: GuildId, user_id: UserId) -> SqlQueryAs<'a, Self> {
+        sqlx::query_as(
+            "SELECT guild_id, user_id, timestamp, verdict FROM verification_verdicts \
+             WHERE guild_id = $1 AND user_id = $2 \
+             ORDER BY timestamp DESC",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(user_id.0 as i64)
+    }
+
+    pub fn insert<'a>(
+        guild_id: GuildId,
+        user_id: UserId,
+        timestamp: impl Into<DateTime<Utc>>,
+        verdict: VerificationVerdict,
+    ) -> SqlQuery<'a> {
+        let outcome = verdict.get_outcome() as i32;
+        let score = verdict.get_score();
+        sqlx::query(
+            "INSERT INTO verification_verdicts \
+                (guild_id, user_id, timestamp, outcome, score, verdict) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(guild_id.0 as i64)
+        .bind(user_id.0 as i64)
+        .bind(timestamp.into())
+        .bind(outcome)
+        .bind(score)
+        .bind(types::Protobuf(verdict))
+    }
+}